@@ -0,0 +1,603 @@
+//! `RaworcApi`: the operations the MCP server needs from a Raworc backend.
+//! - `RaworcClient` implements it over HTTP
+//! - `testing::InMemoryRaworc` implements it in memory
+//! - Downstream crates can wrap either (e.g. with a cache) and hand it to `RaworcMcpServer::with_client`
+
+use crate::error::{RaworcError, RaworcResult};
+use crate::models::*;
+use crate::RaworcClient;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Callback the waiting helpers invoke between polls with the elapsed time and a status line
+pub type ProgressFn<'a> = &'a (dyn Fn(Duration, &str) + Send + Sync);
+
+/// Raworc backend operations. The waiting helpers are provided on top of the
+/// required methods, so implementors only supply the plain API calls.
+#[async_trait]
+pub trait RaworcApi: Send + Sync {
+    /* Auth & system */
+    async fn authenticate(&mut self, username: &str, password: &str) -> RaworcResult<()>;
+    async fn get_user_info(&self) -> RaworcResult<UserInfo>;
+    async fn health_check(&self) -> RaworcResult<String>;
+    async fn get_version(&self) -> RaworcResult<VersionResponse>;
+
+    /* Spaces */
+    async fn list_spaces(&self) -> RaworcResult<Vec<Space>>;
+    async fn create_space(&self, request: &CreateSpaceRequest) -> RaworcResult<Space>;
+    async fn get_space(&self, name: &str) -> RaworcResult<Space>;
+    async fn update_space(&self, name: &str, request: &UpdateSpaceRequest) -> RaworcResult<Space>;
+    async fn delete_space(&self, name: &str) -> RaworcResult<()>;
+
+    /* Sessions (space-scoped) */
+    async fn list_sessions(&self, space: Option<&str>) -> RaworcResult<Vec<Session>>;
+    async fn create_session(&self, space: Option<&str>, metadata: Option<HashMap<String, Value>>) -> RaworcResult<Session>;
+    async fn get_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<Session>;
+    async fn update_session(&self, space: Option<&str>, session_id: &str, request: &UpdateSessionRequest) -> RaworcResult<Session>;
+    async fn update_session_state(&self, space: Option<&str>, session_id: &str, state: SessionState) -> RaworcResult<()>;
+    async fn pause_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()>;
+    async fn resume_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()>;
+    async fn terminate_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()>;
+
+    /* Messages (space+session) */
+    async fn get_messages(&self, space: Option<&str>, session_id: &str, limit: Option<u64>) -> RaworcResult<Vec<Message>>;
+    async fn send_message(&self, space: Option<&str>, session_id: &str, content: &str) -> RaworcResult<Message>;
+    async fn get_message_count(&self, space: Option<&str>, session_id: &str) -> RaworcResult<MessageCount>;
+    async fn clear_messages(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()>;
+
+    /* Sessions (global) */
+    async fn list_all_sessions(&self) -> RaworcResult<Vec<Session>>;
+    async fn create_global_session(&self, request: &CreateSessionRequest) -> RaworcResult<Session>;
+    async fn get_global_session(&self, session_id: &str) -> RaworcResult<Session>;
+    async fn update_global_session(&self, session_id: &str, request: &UpdateSessionRequest) -> RaworcResult<Session>;
+    async fn update_global_session_state(&self, session_id: &str, request: &UpdateSessionStateRequest) -> RaworcResult<()>;
+    async fn close_session(&self, session_id: &str) -> RaworcResult<()>;
+    async fn restore_session(&self, session_id: &str) -> RaworcResult<()>;
+    async fn remix_session(&self, session_id: &str, request: &CreateSessionRequest) -> RaworcResult<Session>;
+    async fn delete_global_session(&self, session_id: &str) -> RaworcResult<()>;
+    async fn get_global_messages(&self, session_id: &str, limit: Option<u64>) -> RaworcResult<Vec<Message>>;
+    async fn send_global_message(&self, session_id: &str, request: &CreateMessageRequest) -> RaworcResult<Message>;
+    async fn get_global_message_count(&self, session_id: &str) -> RaworcResult<MessageCount>;
+    async fn clear_global_messages(&self, session_id: &str) -> RaworcResult<()>;
+
+    /* Agents */
+    async fn list_agents(&self, space: Option<&str>) -> RaworcResult<Vec<Agent>>;
+    async fn create_agent(&self, space: &str, request: &CreateAgentRequest) -> RaworcResult<Agent>;
+    async fn get_agent(&self, space: &str, agent_name: &str) -> RaworcResult<Agent>;
+    async fn update_agent(&self, space: &str, agent_name: &str, request: &UpdateAgentRequest) -> RaworcResult<Agent>;
+    async fn delete_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()>;
+    async fn get_agent_logs(&self, space: &str, agent_name: &str) -> RaworcResult<String>;
+    async fn get_agent_log_lines(&self, space: &str, agent_name: &str, query: &AgentLogsQuery) -> RaworcResult<Vec<String>>;
+    async fn update_agent_status(&self, space: &str, agent_name: &str, request: &UpdateAgentStatusRequest) -> RaworcResult<()>;
+    async fn deploy_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()>;
+    async fn stop_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()>;
+    async fn list_running_agents(&self, space: &str) -> RaworcResult<Vec<RunningAgent>>;
+
+    /* Secrets */
+    async fn list_secrets(&self, space: Option<&str>) -> RaworcResult<Vec<Secret>>;
+    async fn get_secret(&self, space: &str, key: &str) -> RaworcResult<Secret>;
+    async fn create_secret(&self, space: &str, request: &CreateSecretRequest) -> RaworcResult<Secret>;
+    async fn set_secret(&self, space: &str, key: &str, value: &str) -> RaworcResult<Secret>;
+    async fn update_secret(&self, space: &str, key: &str, request: &UpdateSecretRequest) -> RaworcResult<Secret>;
+    async fn delete_secret(&self, space: &str, key: &str) -> RaworcResult<()>;
+
+    /* Service accounts */
+    async fn list_service_accounts(&self) -> RaworcResult<Vec<ServiceAccount>>;
+    async fn create_service_account(&self, request: &CreateServiceAccountRequest) -> RaworcResult<ServiceAccount>;
+    async fn get_service_account(&self, id: &str) -> RaworcResult<ServiceAccount>;
+    async fn update_service_account(&self, id: &str, request: &UpdateServiceAccountRequest) -> RaworcResult<ServiceAccount>;
+    async fn delete_service_account(&self, id: &str) -> RaworcResult<()>;
+    async fn update_service_account_password(&self, id: &str, request: &UpdatePasswordRequest) -> RaworcResult<()>;
+
+    /* Roles */
+    async fn list_roles(&self) -> RaworcResult<Vec<Role>>;
+    async fn create_role(&self, request: &CreateRoleRequest) -> RaworcResult<Role>;
+    async fn get_role(&self, id: &str) -> RaworcResult<Role>;
+    async fn delete_role(&self, id: &str) -> RaworcResult<()>;
+
+    /* Role bindings */
+    async fn list_role_bindings(&self) -> RaworcResult<Vec<RoleBinding>>;
+    async fn create_role_binding(&self, request: &CreateRoleBindingRequest) -> RaworcResult<RoleBinding>;
+    async fn get_role_binding(&self, id: &str) -> RaworcResult<RoleBinding>;
+    async fn delete_role_binding(&self, id: &str) -> RaworcResult<()>;
+
+    /* Builds */
+    async fn create_build(&self, space: &str, req: &CreateBuildRequest) -> RaworcResult<Build>;
+    async fn get_latest_build(&self, space: &str) -> RaworcResult<Build>;
+    async fn get_build(&self, space: &str, build_id: &str) -> RaworcResult<Build>;
+
+    /// Request id of the latest non-GET request, cleared by reading it. Backends
+    /// without request ids keep the default.
+    fn take_request_id(&self) -> Option<String> {
+        None
+    }
+
+    /// Plan non-GET requests instead of sending them (they fail with
+    /// `RaworcError::DryRun`). Returns false for backends that cannot, which
+    /// leave the setting off.
+    fn set_dry_run(&self, _enabled: bool) -> bool {
+        false
+    }
+
+    /* Waiting helpers */
+
+    /// Poll a session until it reaches one of `targets`. Fails early if the session
+    /// ends up TERMINATED or CLOSED when that is not one of the targets.
+    async fn wait_for_session_state(
+        &self,
+        space: Option<&str>,
+        session_id: &str,
+        targets: &[SessionState],
+        timeout: Duration,
+        on_progress: ProgressFn<'_>,
+    ) -> RaworcResult<Session> {
+        let started = Instant::now();
+        let mut backoff = Backoff::new();
+
+        loop {
+            let session = self.get_session(space, session_id).await?;
+            if targets.contains(&session.state) {
+                return Ok(session);
+            }
+            if matches!(session.state, SessionState::Terminated | SessionState::Closed) {
+                return Err(RaworcError::state_error(&format!(
+                    "session {} is {:?} (reason: {}) while waiting for {:?}",
+                    session_id,
+                    session.state,
+                    session.termination_reason.as_deref().unwrap_or("none given"),
+                    targets
+                )));
+            }
+
+            let elapsed = started.elapsed();
+            if elapsed >= timeout {
+                return Err(RaworcError::timeout_error(&format!(
+                    "session {} still {:?} after {}s, wanted {:?}",
+                    session_id,
+                    session.state,
+                    timeout.as_secs(),
+                    targets
+                )));
+            }
+            on_progress(elapsed, &format!("Session is {:?}", session.state));
+            backoff.wait(timeout - elapsed).await;
+        }
+    }
+
+    /// Send a message, then poll until an assistant reply shows up, the session
+    /// stops running, or `timeout` elapses. The reply is the earliest assistant
+    /// message created at or after the sent one. Messages are fetched in full,
+    /// and only when the count changed: `?limit=N` is not documented to return
+    /// the newest N, so no order or window of the listing is relied on. The
+    /// timeout error names the sent message, so callers know not to send it again.
+    async fn send_message_and_wait(
+        &self,
+        space: Option<&str>,
+        session_id: &str,
+        content: &str,
+        timeout: Duration,
+        on_progress: ProgressFn<'_>,
+    ) -> RaworcResult<MessageExchange> {
+        let sent = self.send_message(space, session_id, content).await?;
+        let started = Instant::now();
+        let mut backoff = Backoff::new();
+        let mut seen_running = false;
+        // Count at the last fetch; `None` until the first, which also covers a
+        // reply that landed before any count was taken
+        let mut seen = None;
+
+        loop {
+            // State first: a reply written between the calls is still picked up below
+            let state = self.get_session(space, session_id).await?.state;
+            let count = self.get_message_count(space, session_id).await?.count;
+            let mut reply = None;
+            if seen != Some(count) {
+                reply = self
+                    .get_messages(space, session_id, None)
+                    .await?
+                    .into_iter()
+                    .filter(|m| {
+                        m.role == MessageRole::Assistant
+                            && m.id != sent.id
+                            && m.created_at >= sent.created_at
+                    })
+                    .min_by_key(|m| m.created_at);
+                seen = Some(count);
+            }
+
+            let stopped = match state {
+                SessionState::Running => {
+                    seen_running = true;
+                    false
+                }
+                SessionState::Terminated | SessionState::Closed => true,
+                _ => seen_running,
+            };
+            if reply.is_some() || stopped {
+                return Ok(MessageExchange { sent, reply, state });
+            }
+
+            let elapsed = started.elapsed();
+            if elapsed >= timeout {
+                return Err(RaworcError::timeout_error(&format!(
+                    "no reply from session {} after {}s; message {} was sent at {} and is still in the session",
+                    session_id,
+                    timeout.as_secs(),
+                    sent.id,
+                    sent.created_at.to_rfc3339()
+                )));
+            }
+            on_progress(elapsed, &format!("Waiting for reply (session {:?})", state));
+            backoff.wait(timeout - elapsed).await;
+        }
+    }
+
+    /// Poll a build until it is COMPLETED or FAILED, passing each new chunk of
+    /// `Build.logs` to `on_progress`. With no `build_id` the latest build is followed.
+    async fn wait_for_build(
+        &self,
+        space: &str,
+        build_id: Option<&str>,
+        timeout: Duration,
+        on_progress: ProgressFn<'_>,
+    ) -> RaworcResult<Build> {
+        let started = Instant::now();
+        let mut backoff = Backoff::new();
+        let mut logs_seen = 0;
+
+        loop {
+            let build = match build_id {
+                Some(id) => self.get_build(space, id).await?,
+                None => self.get_latest_build(space).await?,
+            };
+            let elapsed = started.elapsed();
+
+            let logs = build.logs.as_deref().unwrap_or_default();
+            // Logs are expected to only grow; start over if they were rewritten
+            if logs.len() < logs_seen || !logs.is_char_boundary(logs_seen) {
+                logs_seen = 0;
+            }
+            let chunk = &logs[logs_seen..];
+            logs_seen = logs.len();
+
+            if matches!(build.status, BuildStatus::Completed | BuildStatus::Failed) {
+                if !chunk.is_empty() {
+                    on_progress(elapsed, chunk);
+                }
+                return Ok(build);
+            }
+            if elapsed >= timeout {
+                return Err(RaworcError::timeout_error(&format!(
+                    "build in space {} still {:?} after {}s",
+                    space,
+                    build.status,
+                    timeout.as_secs()
+                )));
+            }
+            if chunk.is_empty() {
+                on_progress(elapsed, &format!("Build is {:?}", build.status));
+            } else {
+                on_progress(elapsed, chunk);
+            }
+            backoff.wait(timeout - elapsed).await;
+        }
+    }
+}
+
+#[async_trait]
+impl RaworcApi for RaworcClient {
+    /* Auth & system */
+    async fn authenticate(&mut self, username: &str, password: &str) -> RaworcResult<()> {
+        RaworcClient::authenticate(self, username, password).await
+    }
+
+    async fn get_user_info(&self) -> RaworcResult<UserInfo> {
+        RaworcClient::get_user_info(self).await
+    }
+
+    async fn health_check(&self) -> RaworcResult<String> {
+        RaworcClient::health_check(self).await
+    }
+
+    async fn get_version(&self) -> RaworcResult<VersionResponse> {
+        RaworcClient::get_version(self).await
+    }
+
+    /* Spaces */
+    async fn list_spaces(&self) -> RaworcResult<Vec<Space>> {
+        RaworcClient::list_spaces(self).await
+    }
+
+    async fn create_space(&self, request: &CreateSpaceRequest) -> RaworcResult<Space> {
+        RaworcClient::create_space(self, request).await
+    }
+
+    async fn get_space(&self, name: &str) -> RaworcResult<Space> {
+        RaworcClient::get_space(self, name).await
+    }
+
+    async fn update_space(&self, name: &str, request: &UpdateSpaceRequest) -> RaworcResult<Space> {
+        RaworcClient::update_space(self, name, request).await
+    }
+
+    async fn delete_space(&self, name: &str) -> RaworcResult<()> {
+        RaworcClient::delete_space(self, name).await
+    }
+
+    /* Sessions (space-scoped) */
+    async fn list_sessions(&self, space: Option<&str>) -> RaworcResult<Vec<Session>> {
+        RaworcClient::list_sessions(self, space).await
+    }
+
+    async fn create_session(&self, space: Option<&str>, metadata: Option<HashMap<String, Value>>) -> RaworcResult<Session> {
+        RaworcClient::create_session(self, space, metadata).await
+    }
+
+    async fn get_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<Session> {
+        RaworcClient::get_session(self, space, session_id).await
+    }
+
+    async fn update_session(&self, space: Option<&str>, session_id: &str, request: &UpdateSessionRequest) -> RaworcResult<Session> {
+        RaworcClient::update_session(self, space, session_id, request).await
+    }
+
+    async fn update_session_state(&self, space: Option<&str>, session_id: &str, state: SessionState) -> RaworcResult<()> {
+        RaworcClient::update_session_state(self, space, session_id, state).await
+    }
+
+    async fn pause_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        RaworcClient::pause_session(self, space, session_id).await
+    }
+
+    async fn resume_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        RaworcClient::resume_session(self, space, session_id).await
+    }
+
+    async fn terminate_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        RaworcClient::terminate_session(self, space, session_id).await
+    }
+
+    /* Messages (space+session) */
+    async fn get_messages(&self, space: Option<&str>, session_id: &str, limit: Option<u64>) -> RaworcResult<Vec<Message>> {
+        RaworcClient::get_messages(self, space, session_id, limit).await
+    }
+
+    async fn send_message(&self, space: Option<&str>, session_id: &str, content: &str) -> RaworcResult<Message> {
+        RaworcClient::send_message(self, space, session_id, content).await
+    }
+
+    async fn get_message_count(&self, space: Option<&str>, session_id: &str) -> RaworcResult<MessageCount> {
+        RaworcClient::get_message_count(self, space, session_id).await
+    }
+
+    async fn clear_messages(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        RaworcClient::clear_messages(self, space, session_id).await
+    }
+
+    /* Sessions (global) */
+    async fn list_all_sessions(&self) -> RaworcResult<Vec<Session>> {
+        RaworcClient::list_all_sessions(self).await
+    }
+
+    async fn create_global_session(&self, request: &CreateSessionRequest) -> RaworcResult<Session> {
+        RaworcClient::create_global_session(self, request).await
+    }
+
+    async fn get_global_session(&self, session_id: &str) -> RaworcResult<Session> {
+        RaworcClient::get_global_session(self, session_id).await
+    }
+
+    async fn update_global_session(&self, session_id: &str, request: &UpdateSessionRequest) -> RaworcResult<Session> {
+        RaworcClient::update_global_session(self, session_id, request).await
+    }
+
+    async fn update_global_session_state(&self, session_id: &str, request: &UpdateSessionStateRequest) -> RaworcResult<()> {
+        RaworcClient::update_global_session_state(self, session_id, request).await
+    }
+
+    async fn close_session(&self, session_id: &str) -> RaworcResult<()> {
+        RaworcClient::close_session(self, session_id).await
+    }
+
+    async fn restore_session(&self, session_id: &str) -> RaworcResult<()> {
+        RaworcClient::restore_session(self, session_id).await
+    }
+
+    async fn remix_session(&self, session_id: &str, request: &CreateSessionRequest) -> RaworcResult<Session> {
+        RaworcClient::remix_session(self, session_id, request).await
+    }
+
+    async fn delete_global_session(&self, session_id: &str) -> RaworcResult<()> {
+        RaworcClient::delete_global_session(self, session_id).await
+    }
+
+    async fn get_global_messages(&self, session_id: &str, limit: Option<u64>) -> RaworcResult<Vec<Message>> {
+        RaworcClient::get_global_messages(self, session_id, limit).await
+    }
+
+    async fn send_global_message(&self, session_id: &str, request: &CreateMessageRequest) -> RaworcResult<Message> {
+        RaworcClient::send_global_message(self, session_id, request).await
+    }
+
+    async fn get_global_message_count(&self, session_id: &str) -> RaworcResult<MessageCount> {
+        RaworcClient::get_global_message_count(self, session_id).await
+    }
+
+    async fn clear_global_messages(&self, session_id: &str) -> RaworcResult<()> {
+        RaworcClient::clear_global_messages(self, session_id).await
+    }
+
+    /* Agents */
+    async fn list_agents(&self, space: Option<&str>) -> RaworcResult<Vec<Agent>> {
+        RaworcClient::list_agents(self, space).await
+    }
+
+    async fn create_agent(&self, space: &str, request: &CreateAgentRequest) -> RaworcResult<Agent> {
+        RaworcClient::create_agent(self, space, request).await
+    }
+
+    async fn get_agent(&self, space: &str, agent_name: &str) -> RaworcResult<Agent> {
+        RaworcClient::get_agent(self, space, agent_name).await
+    }
+
+    async fn update_agent(&self, space: &str, agent_name: &str, request: &UpdateAgentRequest) -> RaworcResult<Agent> {
+        RaworcClient::update_agent(self, space, agent_name, request).await
+    }
+
+    async fn delete_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()> {
+        RaworcClient::delete_agent(self, space, agent_name).await
+    }
+
+    async fn get_agent_logs(&self, space: &str, agent_name: &str) -> RaworcResult<String> {
+        RaworcClient::get_agent_logs(self, space, agent_name).await
+    }
+
+    async fn get_agent_log_lines(&self, space: &str, agent_name: &str, query: &AgentLogsQuery) -> RaworcResult<Vec<String>> {
+        RaworcClient::get_agent_log_lines(self, space, agent_name, query).await
+    }
+
+    async fn update_agent_status(&self, space: &str, agent_name: &str, request: &UpdateAgentStatusRequest) -> RaworcResult<()> {
+        RaworcClient::update_agent_status(self, space, agent_name, request).await
+    }
+
+    async fn deploy_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()> {
+        RaworcClient::deploy_agent(self, space, agent_name).await
+    }
+
+    async fn stop_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()> {
+        RaworcClient::stop_agent(self, space, agent_name).await
+    }
+
+    async fn list_running_agents(&self, space: &str) -> RaworcResult<Vec<RunningAgent>> {
+        RaworcClient::list_running_agents(self, space).await
+    }
+
+    /* Secrets */
+    async fn list_secrets(&self, space: Option<&str>) -> RaworcResult<Vec<Secret>> {
+        RaworcClient::list_secrets(self, space).await
+    }
+
+    async fn get_secret(&self, space: &str, key: &str) -> RaworcResult<Secret> {
+        RaworcClient::get_secret(self, space, key).await
+    }
+
+    async fn create_secret(&self, space: &str, request: &CreateSecretRequest) -> RaworcResult<Secret> {
+        RaworcClient::create_secret(self, space, request).await
+    }
+
+    async fn set_secret(&self, space: &str, key: &str, value: &str) -> RaworcResult<Secret> {
+        RaworcClient::set_secret(self, space, key, value).await
+    }
+
+    async fn update_secret(&self, space: &str, key: &str, request: &UpdateSecretRequest) -> RaworcResult<Secret> {
+        RaworcClient::update_secret(self, space, key, request).await
+    }
+
+    async fn delete_secret(&self, space: &str, key: &str) -> RaworcResult<()> {
+        RaworcClient::delete_secret(self, space, key).await
+    }
+
+    /* Service accounts */
+    async fn list_service_accounts(&self) -> RaworcResult<Vec<ServiceAccount>> {
+        RaworcClient::list_service_accounts(self).await
+    }
+
+    async fn create_service_account(&self, request: &CreateServiceAccountRequest) -> RaworcResult<ServiceAccount> {
+        RaworcClient::create_service_account(self, request).await
+    }
+
+    async fn get_service_account(&self, id: &str) -> RaworcResult<ServiceAccount> {
+        RaworcClient::get_service_account(self, id).await
+    }
+
+    async fn update_service_account(&self, id: &str, request: &UpdateServiceAccountRequest) -> RaworcResult<ServiceAccount> {
+        RaworcClient::update_service_account(self, id, request).await
+    }
+
+    async fn delete_service_account(&self, id: &str) -> RaworcResult<()> {
+        RaworcClient::delete_service_account(self, id).await
+    }
+
+    async fn update_service_account_password(&self, id: &str, request: &UpdatePasswordRequest) -> RaworcResult<()> {
+        RaworcClient::update_service_account_password(self, id, request).await
+    }
+
+    /* Roles */
+    async fn list_roles(&self) -> RaworcResult<Vec<Role>> {
+        RaworcClient::list_roles(self).await
+    }
+
+    async fn create_role(&self, request: &CreateRoleRequest) -> RaworcResult<Role> {
+        RaworcClient::create_role(self, request).await
+    }
+
+    async fn get_role(&self, id: &str) -> RaworcResult<Role> {
+        RaworcClient::get_role(self, id).await
+    }
+
+    async fn delete_role(&self, id: &str) -> RaworcResult<()> {
+        RaworcClient::delete_role(self, id).await
+    }
+
+    /* Role bindings */
+    async fn list_role_bindings(&self) -> RaworcResult<Vec<RoleBinding>> {
+        RaworcClient::list_role_bindings(self).await
+    }
+
+    async fn create_role_binding(&self, request: &CreateRoleBindingRequest) -> RaworcResult<RoleBinding> {
+        RaworcClient::create_role_binding(self, request).await
+    }
+
+    async fn get_role_binding(&self, id: &str) -> RaworcResult<RoleBinding> {
+        RaworcClient::get_role_binding(self, id).await
+    }
+
+    async fn delete_role_binding(&self, id: &str) -> RaworcResult<()> {
+        RaworcClient::delete_role_binding(self, id).await
+    }
+
+    /* Builds */
+    async fn create_build(&self, space: &str, req: &CreateBuildRequest) -> RaworcResult<Build> {
+        RaworcClient::create_build(self, space, req).await
+    }
+
+    async fn get_latest_build(&self, space: &str) -> RaworcResult<Build> {
+        RaworcClient::get_latest_build(self, space).await
+    }
+
+    async fn get_build(&self, space: &str, build_id: &str) -> RaworcResult<Build> {
+        RaworcClient::get_build(self, space, build_id).await
+    }
+
+    fn take_request_id(&self) -> Option<String> {
+        RaworcClient::take_request_id(self)
+    }
+
+    fn set_dry_run(&self, enabled: bool) -> bool {
+        RaworcClient::set_dry_run(self, enabled);
+        true
+    }
+}
+
+/// Poll delay for the waiting helpers: starts at 500ms and doubles up to 5s
+struct Backoff {
+    delay: Duration,
+}
+
+impl Backoff {
+    const MAX: Duration = Duration::from_secs(5);
+
+    fn new() -> Self {
+        Self {
+            delay: Duration::from_millis(500),
+        }
+    }
+
+    /// Sleep for the current delay (never past `remaining`), then grow it
+    async fn wait(&mut self, remaining: Duration) {
+        tokio::time::sleep(self.delay.min(remaining)).await;
+        self.delay = (self.delay * 2).min(Self::MAX);
+    }
+}
@@ -0,0 +1,276 @@
+//! Append-only JSONL audit log of mutating tool calls.
+//! - One record per call: who, from which MCP client, what, where, and how it ended
+//! - The file rotates by size to `<path>.1`, `<path>.2`, ...; the oldest is dropped
+//! - With `hash_chain`, each record carries the SHA-256 of itself and the
+//!   previous record's hash, so edits and deletions show up in `verify`
+
+use crate::error::{RaworcError, RaworcResult};
+use crate::policy::glob_match;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where and how audit records are written
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditConfig {
+    pub path: PathBuf,
+    /// Rotate once the file would grow past this many bytes
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    /// Rotated files kept besides the current one; at least 1
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Chain records with SHA-256 hashes
+    #[serde(default)]
+    pub hash_chain: bool,
+}
+
+fn default_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+impl AuditConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes: default_max_bytes(),
+            max_files: default_max_files(),
+            hash_chain: false,
+        }
+    }
+
+    pub fn with_hash_chain(mut self) -> Self {
+        self.hash_chain = true;
+        self
+    }
+}
+
+/// One audited call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    /// `get_user_info` response, `null` when it could not be fetched
+    pub identity: Value,
+    /// `clientInfo.name` from the MCP `initialize` request
+    pub client: Option<String>,
+    pub tool: String,
+    /// Arguments with credentials and secret values redacted
+    pub arguments: Value,
+    pub space: Option<String>,
+    /// Session id, agent name, secret key, ... the call acted on
+    pub resource: Option<String>,
+    /// `ok`, `error` or `denied`
+    pub outcome: String,
+    pub error: Option<String>,
+    /// `X-Request-Id` of the API request that carried the change
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// Filters for reading recent records
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub space: Option<String>,
+    /// Tool-name glob
+    pub tool: Option<String>,
+    /// Most records returned; all when `None`
+    pub limit: Option<usize>,
+}
+
+/// Appends records to, and reads them back from, the configured file
+#[derive(Debug)]
+pub struct AuditLog {
+    config: AuditConfig,
+    /// Hash of the last record written, once known; also serializes writers
+    last_hash: Mutex<Option<String>>,
+}
+
+impl AuditLog {
+    pub fn new(config: AuditConfig) -> Self {
+        Self {
+            config,
+            last_hash: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &AuditConfig {
+        &self.config
+    }
+
+    /// Append `record`, rotating first if the file would outgrow `max_bytes`
+    pub fn append(&self, mut record: AuditRecord) -> RaworcResult<()> {
+        let mut last_hash = self.last_hash.lock().unwrap_or_else(|e| e.into_inner());
+        if self.config.hash_chain {
+            if last_hash.is_none() {
+                *last_hash = self.read_last_hash()?;
+            }
+            record.prev_hash = last_hash.clone();
+            record.hash = None;
+            record.hash = Some(record_hash(&serde_json::to_value(&record)?)?);
+        }
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+
+        let size = fs::metadata(&self.config.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.config.max_bytes {
+            self.rotate()?;
+        } else if size > 0 && !ends_with_newline(&self.config.path) {
+            // Start after a line torn by a crash instead of gluing onto it
+            line.insert(0, '\n');
+        }
+        if let Some(dir) = self.config.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| io_error(&self.config.path, e))?;
+        // Only a record that reached the file may be chained onto
+        if record.hash.is_some() {
+            *last_hash = record.hash;
+        }
+        Ok(())
+    }
+
+    /// Most recent records first, across rotated files
+    pub fn query(&self, query: &AuditQuery) -> RaworcResult<Vec<AuditRecord>> {
+        let mut found = Vec::new();
+        for path in self.files() {
+            for line in read_lines(&path)?.iter().rev() {
+                let Ok(record) = serde_json::from_str::<AuditRecord>(line) else {
+                    continue;
+                };
+                let wanted = query.space.as_ref().is_none_or(|s| record.space.as_ref() == Some(s))
+                    && query.tool.as_ref().is_none_or(|t| glob_match(t, &record.tool));
+                if wanted {
+                    found.push(record);
+                    if query.limit.is_some_and(|limit| found.len() >= limit) {
+                        return Ok(found);
+                    }
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Check the hash chain from the oldest kept record to the newest and
+    /// return how many records were verified
+    pub fn verify(&self) -> RaworcResult<usize> {
+        let mut previous: Option<String> = None;
+        let mut verified = 0;
+        for path in self.files().into_iter().rev() {
+            for (i, line) in read_lines(&path)?.iter().enumerate() {
+                let broken = |why: &str| {
+                    RaworcError::StateError(format!(
+                        "audit chain broken at {} line {}: {}",
+                        path.display(),
+                        i + 1,
+                        why
+                    ))
+                };
+                let mut value: Value = serde_json::from_str(line).map_err(|_| broken("not a JSON record"))?;
+                let map = value.as_object_mut().ok_or_else(|| broken("not a JSON record"))?;
+                let hash = map.remove("hash").and_then(|h| h.as_str().map(str::to_string));
+                let hash = hash.ok_or_else(|| broken("record has no hash"))?;
+                let prev_hash = map.get("prev_hash").and_then(Value::as_str).map(str::to_string);
+                // The first kept record may point at one that was rotated away
+                if verified > 0 && prev_hash != previous {
+                    return Err(broken("prev_hash does not match the previous record"));
+                }
+                if record_hash(&value)? != hash {
+                    return Err(broken("record was modified"));
+                }
+                previous = Some(hash);
+                verified += 1;
+            }
+        }
+        Ok(verified)
+    }
+
+    /// Current file, then rotated files from newest to oldest
+    fn files(&self) -> Vec<PathBuf> {
+        std::iter::once(self.config.path.clone())
+            .chain((1..=self.config.max_files).map(|n| rotated_path(&self.config.path, n)))
+            .filter(|p| p.is_file())
+            .collect()
+    }
+
+    fn rotate(&self) -> RaworcResult<()> {
+        let path = &self.config.path;
+        // Rotating with nowhere to rotate to would drop the live log; `Config::validate` rejects it
+        if self.config.max_files == 0 {
+            return Err(RaworcError::config_error("audit max_files must be greater than 0"));
+        }
+        for n in (1..self.config.max_files).rev() {
+            let from = rotated_path(path, n);
+            if from.is_file() {
+                fs::rename(&from, rotated_path(path, n + 1)).map_err(|e| io_error(&from, e))?;
+            }
+        }
+        fs::rename(path, rotated_path(path, 1)).map_err(|e| io_error(path, e))
+    }
+
+    /// Hash of the newest intact record. Lines that are not JSON records, such
+    /// as one torn by a crash mid-write, are skipped with a warning; `verify`
+    /// still reports them.
+    fn read_last_hash(&self) -> RaworcResult<Option<String>> {
+        for path in self.files() {
+            for line in read_lines(&path)?.iter().rev() {
+                match serde_json::from_str::<Value>(line) {
+                    Ok(record) => return Ok(record.get("hash").and_then(Value::as_str).map(str::to_string)),
+                    Err(e) => tracing::warn!("Skipping unreadable audit record in {}: {}", path.display(), e),
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Hex SHA-256 of a record without its `hash` field, serialized with sorted keys
+fn record_hash(record: &Value) -> RaworcResult<String> {
+    Ok(hex::encode(Sha256::digest(serde_json::to_string(record)?.as_bytes())))
+}
+
+/// `<path>.<n>`
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn read_lines(path: &Path) -> RaworcResult<Vec<String>> {
+    let text = fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+    Ok(text.lines().filter(|l| !l.trim().is_empty()).map(str::to_string).collect())
+}
+
+fn ends_with_newline(path: &Path) -> bool {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut last = [0u8; 1];
+    fs::File::open(path)
+        .and_then(|mut file| {
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)
+        })
+        .map_or(true, |()| last[0] == b'\n')
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> RaworcError {
+    RaworcError::InternalError(format!("audit log {}: {}", path.display(), e))
+}
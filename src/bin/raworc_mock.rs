@@ -0,0 +1,44 @@
+use anyhow::Result;
+use clap::Parser;
+use raworc_mcp::mock::{self, MockOptions, DEFAULT_REPLY};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+/// Serve the Raworc v0 API from memory for offline development
+#[derive(Parser)]
+#[command(name = "raworc-mock", version)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:9000", env = "RAWORC_MOCK_ADDR")]
+    addr: SocketAddr,
+
+    /// Scripted assistant reply; repeat to reply in order, `{content}` echoes the message
+    #[arg(long = "reply", default_value = DEFAULT_REPLY)]
+    replies: Vec<String>,
+
+    /// Milliseconds a session stays RUNNING before it replies
+    #[arg(long, default_value_t = 500)]
+    think_ms: u64,
+
+    /// Milliseconds a build takes to complete
+    #[arg(long, default_value_t = 2000)]
+    build_ms: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_writer(BoxMakeWriter::new(std::io::stderr))
+        .init();
+
+    let args = Args::parse();
+    let options = MockOptions {
+        replies: args.replies,
+        think_time: Duration::from_millis(args.think_ms),
+        build_time: Duration::from_millis(args.build_ms),
+    };
+    eprintln!("Point the MCP server at it with RAWORC_API_URL=http://{}/api/v0 RAWORC_AUTH_TOKEN=mock", args.addr);
+    mock::serve(args.addr, options).await?;
+    Ok(())
+}
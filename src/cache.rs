@@ -0,0 +1,115 @@
+//! In-process response cache for rarely-changing reads (spaces, agents, version).
+//! - Entries are raw response bodies keyed by request path, each with its own TTL
+//! - Expired entries that carried an ETag are revalidated with `If-None-Match`
+//! - Successful writes through the same client invalidate the written path,
+//!   everything below it and every collection or resource above it
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Per-resource TTLs
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheConfig {
+    /// `list_spaces` and `get_space`
+    pub spaces: Duration,
+    /// `list_agents`
+    pub agents: Duration,
+    /// `get_version`
+    pub version: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            spaces: Duration::from_secs(60),
+            agents: Duration::from_secs(30),
+            version: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Same TTL for spaces and agents; version keeps its default
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            spaces: ttl,
+            agents: ttl,
+            ..Self::default()
+        }
+    }
+}
+
+/// A cached body and how to revalidate it
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub body: String,
+    pub etag: Option<String>,
+    stored_at: Instant,
+    ttl: Duration,
+}
+
+impl CachedResponse {
+    pub fn is_fresh(&self) -> bool {
+        self.stored_at.elapsed() < self.ttl
+    }
+}
+
+/// Response cache shared by one `RaworcClient`
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    config: CacheConfig,
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Entry for `path`, fresh or not
+    pub fn get(&self, path: &str) -> Option<CachedResponse> {
+        self.lock().get(path).cloned()
+    }
+
+    pub fn put(&self, path: &str, body: String, etag: Option<String>, ttl: Duration) {
+        self.lock().insert(
+            path.to_string(),
+            CachedResponse {
+                body,
+                etag,
+                stored_at: Instant::now(),
+                ttl,
+            },
+        );
+    }
+
+    /// Restart the TTL of an entry the server confirmed unchanged (304)
+    pub fn touch(&self, path: &str) {
+        if let Some(entry) = self.lock().get_mut(path) {
+            entry.stored_at = Instant::now();
+        }
+    }
+
+    /// Drop entries affected by a write to `path`
+    pub fn invalidate(&self, path: &str) {
+        let path = path.split('?').next().unwrap_or(path).trim_matches('/');
+        let ancestors: Vec<&str> = path.match_indices('/').map(|(i, _)| &path[..i]).collect();
+        let below = format!("{}/", path);
+        self.lock().retain(|key, _| {
+            let key = key.split('?').next().unwrap_or(key);
+            key != path && !key.starts_with(&below) && !ancestors.contains(&key)
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CachedResponse>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,148 @@
+//! Record/replay of Raworc HTTP traffic.
+//! - `Record` passes requests through and appends each exchange to a JSON cassette file
+//! - `Replay` answers from the cassette without touching the network
+//! - `Authorization`, credentials and secret values are redacted before anything is written
+
+use crate::error::{RaworcError, RaworcResult};
+pub use crate::redact::{redact_value, REDACTED};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Whether to record or replay traffic, and where the cassette lives
+#[derive(Debug, Clone, PartialEq)]
+pub enum CassetteMode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+/// One request/response pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// Request as stored in a cassette; the path is relative to the API base URL
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// Response as stored in a cassette
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// A loaded cassette. Replay hands out each recorded interaction once, in order
+/// of recording, to the first request with the same method, path and body.
+pub struct Cassette {
+    mode: CassetteMode,
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// Open a cassette. Recording starts an empty file; replay loads an existing one.
+    pub fn open(mode: CassetteMode) -> RaworcResult<Self> {
+        let interactions = match &mode {
+            CassetteMode::Record(path) => {
+                write_file(path, &CassetteFile::default())?;
+                Vec::new()
+            }
+            CassetteMode::Replay(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| {
+                    RaworcError::ConfigError(format!("Cannot read cassette {}: {}", path.display(), e))
+                })?;
+                let file: CassetteFile = serde_json::from_str(&text).map_err(|e| {
+                    RaworcError::ConfigError(format!("Invalid cassette {}: {}", path.display(), e))
+                })?;
+                file.interactions.into_iter().map(|i| (i, false)).collect()
+            }
+        };
+        Ok(Self {
+            mode,
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    /// True when requests must be answered from the cassette
+    pub fn is_replay(&self) -> bool {
+        matches!(self.mode, CassetteMode::Replay(_))
+    }
+
+    /// Redacted form of a request, as it is stored and matched
+    pub fn request(method: &str, path: &str, authorization: Option<&str>, body: Option<&Value>) -> RecordedRequest {
+        RecordedRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            authorization: authorization.map(|_| format!("Bearer {}", REDACTED)),
+            body: body.map(|b| redact(path, b.clone())),
+        }
+    }
+
+    /// Append an exchange and rewrite the cassette file
+    pub fn record(&self, request: RecordedRequest, status: u16, body: &str) -> RaworcResult<()> {
+        let CassetteMode::Record(path) = &self.mode else {
+            return Ok(());
+        };
+        let response = RecordedResponse {
+            status,
+            body: redact_text(&request.path, body),
+        };
+        let mut interactions = self.interactions.lock().unwrap_or_else(|e| e.into_inner());
+        interactions.push((Interaction { request, response }, true));
+        let file = CassetteFile {
+            interactions: interactions.iter().map(|(i, _)| i.clone()).collect(),
+        };
+        write_file(path, &file)
+    }
+
+    /// Next unused recorded response for `request`
+    pub fn replay(&self, request: &RecordedRequest) -> RaworcResult<RecordedResponse> {
+        let mut interactions = self.interactions.lock().unwrap_or_else(|e| e.into_inner());
+        let (interaction, used) = interactions
+            .iter_mut()
+            .find(|(i, used)| !used && i.request.method == request.method && i.request.path == request.path && i.request.body == request.body)
+            .ok_or_else(|| {
+                RaworcError::internal_error(&format!(
+                    "No recorded interaction left for {} {}",
+                    request.method, request.path
+                ))
+            })?;
+        *used = true;
+        Ok(interaction.response.clone())
+    }
+}
+
+/// Redact credentials, tokens and secret values from a JSON value
+pub fn redact(path: &str, value: Value) -> Value {
+    let secrets = path.contains("/secrets");
+    redact_value(value, secrets)
+}
+
+fn redact_text(path: &str, body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => redact(path, value).to_string(),
+        Err(_) => body.to_string(),
+    }
+}
+
+fn write_file(path: &Path, file: &CassetteFile) -> RaworcResult<()> {
+    let text = serde_json::to_string_pretty(file)?;
+    std::fs::write(path, text).map_err(|e| {
+        RaworcError::ConfigError(format!("Cannot write cassette {}: {}", path.display(), e))
+    })
+}
@@ -3,30 +3,63 @@
 //! - Space-scoped routes for sessions/agents/secrets/builds
 //! - Uniform Bearer auth + small 401 -> re-auth -> retry safeguard
 
+use crate::cache::{CacheConfig, ResponseCache};
+use crate::cassette::Cassette;
 use crate::error::{ApiErrorResponse, RaworcError, RaworcResult};
+use crate::metrics::metrics;
 use crate::models::*;
-use reqwest::{header, Client};
+use crate::redact;
+use chrono::{DateTime, SecondsFormat, Utc};
+use regex::Regex;
+use reqwest::{header, Client, Method, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
 use url::Url;
+use uuid::Uuid;
 
 /// Raworc API client
 pub struct RaworcClient {
     http: Client,
     base_url: Url,
-    /// If set, used for Authorization: Bearer <token>
-    auth_token: Option<String>,
+    /// If set, used for Authorization: Bearer <token>; replaced on re-auth
+    auth_token: RwLock<Option<redact::Secret<String>>>,
     /// Default space used when a method allows `space: Option<&str>`
     default_space: Option<String>,
     /// Optional username/password for auto re-auth
     username: Option<String>,
-    password: Option<String>,
+    password: Option<redact::Secret<String>>,
     /// per-request timeout (seconds)
     timeout: u64,
+    /// Record/replay cassette, if `Config::cassette` is set
+    cassette: Option<Cassette>,
+    /// Response cache, if `Config::cache` is set
+    cache: Option<ResponseCache>,
+    /// Request id of the latest non-GET request, for the audit log
+    last_request_id: Mutex<Option<String>>,
+    /// Plan writes instead of sending them; see `set_dry_run`
+    dry_run: AtomicBool,
 }
 
+/// Status and body of a completed (or replayed) request
+struct RawResponse {
+    status: StatusCode,
+    body: String,
+    etag: Option<String>,
+    /// Id the server echoed in `X-Request-Id`, or the one we sent
+    request_id: String,
+}
+
+/// Header carrying the per-call correlation id
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// `User-Agent` sent with every request
+pub const USER_AGENT: &str = concat!("raworc-mcp/", env!("CARGO_PKG_VERSION"));
+
 impl RaworcClient {
     /// Create a new client from your config.
     ///
@@ -36,6 +69,9 @@ impl RaworcClient {
     /// - default_space (optional)
     /// - username/password (optional; used for authenticate() and 401 retry)
     /// - timeout_seconds (optional; default 30)
+    /// - cassette (optional; record or replay HTTP traffic)
+    /// - cache (optional; TTL cache for spaces, agents and version)
+    /// - proxy/no_proxy, ca_cert, client_cert/client_key (optional; network and TLS)
     pub fn new(config: &crate::Config) -> RaworcResult<Self> {
         // Default to cloud API if not provided
         let base_url = Url::parse(
@@ -47,34 +83,49 @@ impl RaworcClient {
         .map_err(|e| RaworcError::ConfigError(format!("Invalid API URL: {}", e)))?;
 
         let timeout = config.timeout_seconds.unwrap_or(30);
-        let http = Client::builder()
-            .timeout(Duration::from_secs(timeout))
-            .build()
-            .map_err(|e| RaworcError::ConfigError(format!("Failed to create HTTP client: {}", e)))?;
+        let http = build_http_client(config, timeout)?;
 
         Ok(Self {
             http,
             base_url,
-            auth_token: config.auth_token.clone(),
+            auth_token: RwLock::new(config.auth_token.clone()),
             default_space: config.default_space.clone(),
             username: config.username.clone(),
             password: config.password.clone(),
             timeout,
+            cassette: config.cassette.clone().map(Cassette::open).transpose()?,
+            cache: config.cache.clone().map(ResponseCache::new),
+            last_request_id: Mutex::new(None),
+            dry_run: AtomicBool::new(false),
         })
     }
 
     /// Manually set/replace the bearer token (useful if you persist it)
     pub fn set_token(&mut self, token: impl Into<String>) {
-        self.auth_token = Some(token.into());
+        self.store_token(token.into());
+    }
+
+    fn store_token(&self, token: String) {
+        *self.auth_token.write().unwrap_or_else(PoisonError::into_inner) = Some(redact::Secret::new(token));
+    }
+
+    fn token(&self) -> Option<redact::Secret<String>> {
+        self.auth_token.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Request id of the latest non-GET request, cleared by reading it
+    pub fn take_request_id(&self) -> Option<String> {
+        self.last_request_id.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+
+    /// While on, authenticated non-GET requests are not sent; they fail with
+    /// `RaworcError::DryRun` describing the request instead
+    pub fn set_dry_run(&self, enabled: bool) {
+        self.dry_run.store(enabled, Ordering::SeqCst);
     }
 
     /// Authenticate with username and password; stores the token internally.
     pub async fn authenticate(&mut self, username: &str, password: &str) -> RaworcResult<()> {
-        #[derive(Serialize)]
-        struct AuthRequest {
-            user: String,
-            pass: String,
-        }
         #[derive(Deserialize)]
         struct AuthResponseWire {
             token: String,
@@ -82,11 +133,11 @@ impl RaworcClient {
 
         let req = AuthRequest {
             user: username.to_string(),
-            pass: password.to_string(),
+            pass: password.into(),
         };
 
         let auth: AuthResponseWire = self.post_json("auth/login", &req).await?;
-        self.auth_token = Some(auth.token);
+        self.store_token(auth.token);
         Ok(())
     }
 
@@ -97,38 +148,31 @@ impl RaworcClient {
 
     /// Health (often public)
     pub async fn health_check(&self) -> RaworcResult<String> {
-        let res = self.http.get(self.build_url("health")).send().await?;
-        Ok(res.text().await.unwrap_or_default())
+        let res = self.send(Method::GET, "health", None, false).await?;
+        Ok(res.body)
     }
 
     /// Version (public)
     pub async fn get_version(&self) -> RaworcResult<VersionResponse> {
-        self.get_json("version").await
+        self.get_cached("version", |c| c.version).await
     }
 
     /* ------------------------- Spaces (org/global) ------------------------- */
 
     pub async fn list_spaces(&self) -> RaworcResult<Vec<Space>> {
-        self.get_json("spaces").await
+        self.get_cached("spaces", |c| c.spaces).await
     }
 
-    pub async fn create_space(&self, name: &str, description: Option<&str>) -> RaworcResult<Space> {
-        let req = CreateSpaceRequest {
-            name: name.to_string(),
-            description: description.map(|s| s.to_string()),
-        };
-        self.post_json("spaces", &req).await
+    pub async fn create_space(&self, request: &CreateSpaceRequest) -> RaworcResult<Space> {
+        self.post_json("spaces", request).await
     }
 
     pub async fn get_space(&self, name: &str) -> RaworcResult<Space> {
-        self.get_json(&format!("spaces/{}", name)).await
+        self.get_cached(&format!("spaces/{}", name), |c| c.spaces).await
     }
 
-    pub async fn update_space(&self, name: &str, description: Option<&str>) -> RaworcResult<Space> {
-        let req = UpdateSpaceRequest {
-            description: description.map(|s| s.to_string()),
-        };
-        self.put_json(&format!("spaces/{}", name), &req).await
+    pub async fn update_space(&self, name: &str, request: &UpdateSpaceRequest) -> RaworcResult<Space> {
+        self.put_json(&format!("spaces/{}", name), request).await
     }
 
     pub async fn delete_space(&self, name: &str) -> RaworcResult<()> {
@@ -233,6 +277,20 @@ impl RaworcClient {
             .await
     }
 
+    /// Send a message and wait for the agent's reply; see
+    /// `RaworcApi::send_message_and_wait`. Inherent, so callers of the HTTP
+    /// client need not import the trait.
+    pub async fn send_message_and_wait(
+        &self,
+        space: Option<&str>,
+        session_id: &str,
+        content: &str,
+        timeout: Duration,
+        on_progress: crate::api::ProgressFn<'_>,
+    ) -> RaworcResult<MessageExchange> {
+        <Self as crate::api::RaworcApi>::send_message_and_wait(self, space, session_id, content, timeout, on_progress).await
+    }
+
     pub async fn get_message_count(
         &self,
         space: Option<&str>,
@@ -313,7 +371,7 @@ impl RaworcClient {
 
     pub async fn list_agents(&self, space: Option<&str>) -> RaworcResult<Vec<Agent>> {
         let sp = self.space(space);
-        self.get_json(&format!("spaces/{}/agents", sp)).await
+        self.get_cached(&format!("spaces/{}/agents", sp), |c| c.agents).await
     }
 
     pub async fn create_agent(
@@ -346,16 +404,65 @@ impl RaworcClient {
     }
 
     pub async fn get_agent_logs(&self, space: &str, agent_name: &str) -> RaworcResult<String> {
-        let res = self
-            .http
-            .get(self.build_url(&format!("spaces/{}/agents/{}/logs", space, agent_name)))
-            .headers(self.build_headers())
-            .send()
-            .await?;
-        if !res.status().is_success() {
-            return self.map_error_text(res).await;
+        self.get_text(&format!("spaces/{}/agents/{}/logs", space, agent_name))
+            .await
+    }
+
+    /// Agent logs as lines, filtered by `query`. Accepts either a plain-text body
+    /// or an `AgentLogsResponse`.
+    pub async fn get_agent_log_lines(
+        &self,
+        space: &str,
+        agent_name: &str,
+        query: &AgentLogsQuery,
+    ) -> RaworcResult<Vec<String>> {
+        let mut params = Vec::new();
+        // A server-side tail before grep would drop matches, so only send it alone
+        if let (Some(n), None) = (query.tail, &query.grep) {
+            params.push(format!("tail={}", n));
+        }
+        if let Some(since) = query.since {
+            params.push(format!(
+                "since={}",
+                since.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ));
         }
-        Ok(res.text().await.unwrap_or_default())
+        let mut path = format!("spaces/{}/agents/{}/logs", space, agent_name);
+        if !params.is_empty() {
+            path.push('?');
+            path.push_str(&params.join("&"));
+        }
+
+        let body = self.get_text(&path).await?;
+        let lines = match serde_json::from_str::<AgentLogsResponse>(&body) {
+            Ok(parsed) => parsed.logs,
+            Err(_) => body.lines().map(str::to_string).collect(),
+        };
+        filter_log_lines(lines, query)
+    }
+
+    pub async fn update_agent_status(
+        &self,
+        space: &str,
+        agent_name: &str,
+        request: &UpdateAgentStatusRequest,
+    ) -> RaworcResult<()> {
+        self.put_json(&format!("spaces/{}/agents/{}/status", space, agent_name), request)
+            .await
+    }
+
+    pub async fn deploy_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()> {
+        self.post_json::<_, ()>(&format!("spaces/{}/agents/{}/deploy", space, agent_name), &())
+            .await
+    }
+
+    pub async fn stop_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()> {
+        self.post_json::<_, ()>(&format!("spaces/{}/agents/{}/stop", space, agent_name), &())
+            .await
+    }
+
+    pub async fn list_running_agents(&self, space: &str) -> RaworcResult<Vec<RunningAgent>> {
+        self.get_json(&format!("spaces/{}/agents/running", space)).await
     }
 
     /* ------------------------- Secrets (space-scoped) ---------------------- */
@@ -370,19 +477,28 @@ impl RaworcClient {
             .await
     }
 
+    pub async fn create_secret(&self, space: &str, request: &CreateSecretRequest) -> RaworcResult<Secret> {
+        self.post_json(&format!("spaces/{}/secrets", space), request)
+            .await
+    }
+
     pub async fn set_secret(&self, space: &str, key: &str, value: &str) -> RaworcResult<Secret> {
         let req = CreateSecretRequest {
+            key_name: key.to_string(),
             value: value.to_string(),
+            description: None,
         };
         self.post_json(&format!("spaces/{}/secrets/{}", space, key), &req)
             .await
     }
 
-    pub async fn update_secret(&self, space: &str, key: &str, value: &str) -> RaworcResult<Secret> {
-        let req = UpdateSecretRequest {
-            value: value.to_string(),
-        };
-        self.put_json(&format!("spaces/{}/secrets/{}", space, key), &req)
+    pub async fn update_secret(
+        &self,
+        space: &str,
+        key: &str,
+        request: &UpdateSecretRequest,
+    ) -> RaworcResult<Secret> {
+        self.put_json(&format!("spaces/{}/secrets/{}", space, key), request)
             .await
     }
 
@@ -391,6 +507,83 @@ impl RaworcClient {
             .await
     }
 
+    /* --------------------------- Service accounts --------------------------- */
+
+    pub async fn list_service_accounts(&self) -> RaworcResult<Vec<ServiceAccount>> {
+        self.get_json("service-accounts").await
+    }
+
+    pub async fn create_service_account(
+        &self,
+        request: &CreateServiceAccountRequest,
+    ) -> RaworcResult<ServiceAccount> {
+        self.post_json("service-accounts", request).await
+    }
+
+    pub async fn get_service_account(&self, id: &str) -> RaworcResult<ServiceAccount> {
+        self.get_json(&format!("service-accounts/{}", id)).await
+    }
+
+    pub async fn update_service_account(
+        &self,
+        id: &str,
+        request: &UpdateServiceAccountRequest,
+    ) -> RaworcResult<ServiceAccount> {
+        self.put_json(&format!("service-accounts/{}", id), request).await
+    }
+
+    pub async fn delete_service_account(&self, id: &str) -> RaworcResult<()> {
+        self.delete_req(&format!("service-accounts/{}", id)).await
+    }
+
+    pub async fn update_service_account_password(
+        &self,
+        id: &str,
+        request: &UpdatePasswordRequest,
+    ) -> RaworcResult<()> {
+        self.put_json(&format!("service-accounts/{}/password", id), request)
+            .await
+    }
+
+    /* -------------------------------- Roles -------------------------------- */
+
+    pub async fn list_roles(&self) -> RaworcResult<Vec<Role>> {
+        self.get_json("roles").await
+    }
+
+    pub async fn create_role(&self, request: &CreateRoleRequest) -> RaworcResult<Role> {
+        self.post_json("roles", request).await
+    }
+
+    pub async fn get_role(&self, id: &str) -> RaworcResult<Role> {
+        self.get_json(&format!("roles/{}", id)).await
+    }
+
+    pub async fn delete_role(&self, id: &str) -> RaworcResult<()> {
+        self.delete_req(&format!("roles/{}", id)).await
+    }
+
+    /* ---------------------------- Role bindings ---------------------------- */
+
+    pub async fn list_role_bindings(&self) -> RaworcResult<Vec<RoleBinding>> {
+        self.get_json("role-bindings").await
+    }
+
+    pub async fn create_role_binding(
+        &self,
+        request: &CreateRoleBindingRequest,
+    ) -> RaworcResult<RoleBinding> {
+        self.post_json("role-bindings", request).await
+    }
+
+    pub async fn get_role_binding(&self, id: &str) -> RaworcResult<RoleBinding> {
+        self.get_json(&format!("role-bindings/{}", id)).await
+    }
+
+    pub async fn delete_role_binding(&self, id: &str) -> RaworcResult<()> {
+        self.delete_req(&format!("role-bindings/{}", id)).await
+    }
+
     /* --------------------------- Builds (space) ---------------------------- */
 
     pub async fn create_build(&self, space: &str, req: &CreateBuildRequest) -> RaworcResult<Build> {
@@ -430,197 +623,449 @@ impl RaworcClient {
             header::CONTENT_TYPE,
             header::HeaderValue::from_static("application/json"),
         );
-        if let Some(token) = &self.auth_token {
-            if let Ok(v) = header::HeaderValue::from_str(&format!("Bearer {}", token)) {
+        if let Some(token) = self.token() {
+            if let Ok(mut v) = header::HeaderValue::from_str(&format!("Bearer {}", token.expose())) {
+                v.set_sensitive(true);
                 h.insert(header::AUTHORIZATION, v);
             }
         }
         h
     }
 
-    async fn get_json<T>(&self, path: &str) -> RaworcResult<T>
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+        with_auth: bool,
+    ) -> RaworcResult<RawResponse> {
+        self.send_with(method, path, body, with_auth, None).await
+    }
+
+    /// Single exit point to the network; tags the call with a fresh request id,
+    /// records or replays through the cassette and invalidates cached reads
+    /// after successful writes.
+    async fn send_with(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+        with_auth: bool,
+        if_none_match: Option<&str>,
+    ) -> RaworcResult<RawResponse> {
+        // Logging in is not a change, so it still goes through
+        if with_auth && method != Method::GET && self.dry_run.load(Ordering::SeqCst) {
+            let url = self.build_url(path);
+            return Err(RaworcError::DryRun(Box::new(PlannedRequest {
+                method: method.to_string(),
+                path: url.path().to_string(),
+                url: url.to_string(),
+                body: body.map(|b| redact::redact_value(b.clone(), true)),
+            })));
+        }
+        let request_id = Uuid::new_v4().to_string();
+        let res = self
+            .exchange(method.clone(), path, body, with_auth, if_none_match, &request_id)
+            .await?;
+        metrics().observe_api_response(method.as_str(), &route_template(path), res.status.as_u16());
+        // Fill in the `raworc.http` span opened by `with_retry`
+        let span = tracing::Span::current();
+        span.record("http.status", res.status.as_u16());
+        span.record("request_id", res.request_id.as_str());
+        if method != Method::GET {
+            *self.last_request_id.lock().unwrap_or_else(PoisonError::into_inner) = Some(res.request_id.clone());
+        }
+        if let Some(cache) = &self.cache {
+            if method != Method::GET && res.status.is_success() {
+                cache.invalidate(path);
+            }
+        }
+        Ok(res)
+    }
+
+    async fn exchange(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+        with_auth: bool,
+        if_none_match: Option<&str>,
+        request_id: &str,
+    ) -> RaworcResult<RawResponse> {
+        let token = self.token();
+        let token = token.as_ref().map(|t| t.expose().as_str()).filter(|_| with_auth);
+        let recorded = self
+            .cassette
+            .as_ref()
+            .map(|c| (c, Cassette::request(method.as_str(), path, token, body)));
+        if let Some((cassette, request)) = &recorded {
+            if cassette.is_replay() {
+                let res = cassette.replay(request)?;
+                return Ok(RawResponse {
+                    status: StatusCode::from_u16(res.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                    body: res.body,
+                    etag: None,
+                    request_id: request_id.to_string(),
+                });
+            }
+        }
+
+        let mut req = self
+            .http
+            .request(method, self.build_url(path))
+            .header(REQUEST_ID_HEADER, request_id);
+        if with_auth {
+            req = req.headers(self.build_headers());
+        }
+        if let Some(etag) = if_none_match {
+            req = req.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(body) = body {
+            req = req.json(body);
+        }
+        let res = req.send().await?;
+        let status = res.status();
+        let etag = res
+            .headers()
+            .get(header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let request_id = res
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or(request_id)
+            .to_string();
+        let text = res.text().await.unwrap_or_default();
+        if let Some((cassette, request)) = recorded {
+            cassette.record(request, status.as_u16(), &text)?;
+        }
+        Ok(RawResponse {
+            status,
+            body: text,
+            etag,
+            request_id,
+        })
+    }
+
+    /// GET through the response cache when it is enabled. `ttl` picks the
+    /// resource's TTL; a stale entry with an ETag is revalidated, not refetched.
+    async fn get_cached<T>(&self, path: &str, ttl: fn(&CacheConfig) -> Duration) -> RaworcResult<T>
     where
         T: for<'de> serde::Deserialize<'de>,
     {
-        self.with_retry(|| async {
-            let res = self
-                .http
-                .get(self.build_url(path))
-                .headers(self.build_headers())
-                .send()
-                .await?;
-            self.handle_json(res).await
+        let Some(cache) = &self.cache else {
+            return self.get_json(path).await;
+        };
+        let cached = cache.get(path);
+        if let Some(entry) = cached.as_ref().filter(|e| e.is_fresh()) {
+            return Ok(serde_json::from_str(&entry.body)?);
+        }
+        let etag = cached.as_ref().and_then(|e| e.etag.clone());
+
+        self.with_retry(Method::GET, path, || async {
+            let res = self.send_with(Method::GET, path, None, true, etag.as_deref()).await?;
+            if let (StatusCode::NOT_MODIFIED, Some(entry)) = (res.status, &cached) {
+                cache.touch(path);
+                return Ok(serde_json::from_str(&entry.body)?);
+            }
+            if res.status.is_success() {
+                cache.put(path, res.body.clone(), res.etag.clone(), ttl(cache.config()));
+            }
+            self.handle_json(res)
         })
         .await
     }
 
-    async fn post_json<B, T>(&self, path: &str, body: &B) -> RaworcResult<T>
+    async fn get_json<T>(&self, path: &str) -> RaworcResult<T>
     where
-        B: Serialize + ?Sized,
         T: for<'de> serde::Deserialize<'de>,
     {
-        self.with_retry(|| async {
-            let res = self
-                .http
-                .post(self.build_url(path))
-                .headers(self.build_headers())
-                .json(body)
-                .send()
-                .await?;
-            self.handle_json(res).await
+        self.with_retry(Method::GET, path, || async {
+            let res = self.send(Method::GET, path, None, true).await?;
+            self.handle_json(res)
         })
         .await
     }
 
-    async fn put_json<B, T>(&self, path: &str, body: &B) -> RaworcResult<T>
+    async fn get_text(&self, path: &str) -> RaworcResult<String> {
+        self.with_retry(Method::GET, path, || async {
+            let res = self.send(Method::GET, path, None, true).await?;
+            if !res.status.is_success() {
+                return self.map_error_text(res);
+            }
+            Ok(res.body)
+        })
+        .await
+    }
+
+    async fn post_json<B, T>(&self, path: &str, body: &B) -> RaworcResult<T>
     where
         B: Serialize + ?Sized,
         T: for<'de> serde::Deserialize<'de>,
     {
-        self.with_retry(|| async {
-            let res = self
-                .http
-                .put(self.build_url(path))
-                .headers(self.build_headers())
-                .json(body)
-                .send()
-                .await?;
-            self.handle_json(res).await
+        let body = serde_json::to_value(body)?;
+        self.with_retry(Method::POST, path, || async {
+            let res = self.send(Method::POST, path, Some(&body), true).await?;
+            self.handle_json(res)
         })
         .await
     }
 
-    async fn patch_json<B, T>(&self, path: &str, body: &B) -> RaworcResult<T>
+    async fn put_json<B, T>(&self, path: &str, body: &B) -> RaworcResult<T>
     where
         B: Serialize + ?Sized,
         T: for<'de> serde::Deserialize<'de>,
     {
-        self.with_retry(|| async {
-            let res = self
-                .http
-                .patch(self.build_url(path))
-                .headers(self.build_headers())
-                .json(body)
-                .send()
-                .await?;
-            self.handle_json(res).await
+        let body = serde_json::to_value(body)?;
+        self.with_retry(Method::PUT, path, || async {
+            let res = self.send(Method::PUT, path, Some(&body), true).await?;
+            self.handle_json(res)
         })
         .await
     }
 
     async fn delete_req(&self, path: &str) -> RaworcResult<()> {
-        self.with_retry(|| async {
-            let res = self
-                .http
-                .delete(self.build_url(path))
-                .headers(self.build_headers())
-                .send()
-                .await?;
-            if res.status().is_success() {
+        self.with_retry(Method::DELETE, path, || async {
+            let res = self.send(Method::DELETE, path, None, true).await?;
+            if res.status.is_success() {
                 Ok(())
             } else {
-                self.map_error_text(res).await
+                self.map_error_text(res)
             }
         })
         .await
     }
 
-    async fn handle_json<T>(&self, res: reqwest::Response) -> RaworcResult<T>
+    fn handle_json<T>(&self, res: RawResponse) -> RaworcResult<T>
     where
         T: for<'de> serde::Deserialize<'de>,
     {
-        if res.status().is_success() {
-            Ok(res.json::<T>().await?)
+        if res.status.is_success() {
+            Ok(serde_json::from_str::<T>(&res.body)?)
         } else {
-            self.map_error_text(res).await
+            self.map_error_text(res)
         }
     }
 
-    async fn map_error_text<T>(&self, res: reqwest::Response) -> RaworcResult<T> {
-        let status = res.status();
-        let text = res.text().await.unwrap_or_else(|_| "Unknown error".into());
+    fn map_error_text<T>(&self, res: RawResponse) -> RaworcResult<T> {
+        let status = res.status;
+        let text = res.body;
 
-        if status == reqwest::StatusCode::NOT_FOUND {
-            return Err(RaworcError::not_found(&text));
-        }
-        if status == reqwest::StatusCode::UNAUTHORIZED {
-            return Err(RaworcError::auth_error(&text));
+        if status == StatusCode::NOT_FOUND {
+            return Err(RaworcError::not_found(&text).with_request_id(res.request_id));
         }
-
-        if let Ok(api) = serde_json::from_str::<ApiErrorResponse>(&text) {
-            return Err(RaworcError::api_error(status.as_u16(), api.error.message));
+        if status == StatusCode::UNAUTHORIZED {
+            return Err(RaworcError::auth_error(&text).with_request_id(res.request_id));
         }
 
-        Err(RaworcError::api_error(status.as_u16(), text))
+        let message = match serde_json::from_str::<ApiErrorResponse>(&text) {
+            Ok(api) => api.error.message,
+            Err(_) => text,
+        };
+        Err(RaworcError::api_error(status.as_u16(), message).with_request_id(res.request_id))
     }
 
     /// Tiny helper: on 401, try one re-auth (if username/password present), then retry once.
-    async fn with_retry<F, Fut, T>(&self, f: F) -> RaworcResult<T>
+    /// The whole call, retry included, runs in one `raworc.http` span, and its
+    /// outcome feeds the route's consecutive-failure gauge.
+    async fn with_retry<F, Fut, T>(&self, method: Method, path: &str, f: F) -> RaworcResult<T>
     where
         F: Fn() -> Fut,
         Fut: std::future::Future<Output = RaworcResult<T>>,
         T: Sized,
     {
-        match f().await {
-            Ok(v) => Ok(v),
-            Err(e) if matches!(e, RaworcError::AuthError(_)) => {
-                if let (Some(u), Some(p)) = (&self.username, &self.password) {
-                    let token = Self::login_once(&self.http, self.base_url.clone(), u, p, self.timeout).await?;
-                    let _ = token; // available if you want to persist externally
-                    f().await
-                } else {
-                    Err(e)
+        let route = route_template(path);
+        let span = tracing::info_span!(
+            "raworc.http",
+            http.method = %method,
+            http.route = %route,
+            http.status = tracing::field::Empty,
+            request_id = tracing::field::Empty,
+            retries = 0u64,
+            latency_ms = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let result = async {
+            match f().await {
+                Ok(v) => Ok(v),
+                Err(e) if matches!(e, RaworcError::AuthError { .. }) => {
+                    if let (Some(u), Some(p)) = (&self.username, &self.password) {
+                        metrics().api_reauth.inc();
+                        let token = self.login_once(u, p.expose(), self.timeout).await?;
+                        self.store_token(token);
+                        metrics().api_retries.inc();
+                        tracing::Span::current().record("retries", 1u64);
+                        f().await
+                    } else {
+                        Err(e)
+                    }
                 }
+                Err(e) => Err(e),
             }
-            Err(e) => Err(e),
         }
+        .instrument(span.clone())
+        .await;
+        span.record("latency_ms", started.elapsed().as_millis() as u64);
+        let failed = matches!(
+            result,
+            Err(RaworcError::HttpError(_) | RaworcError::TimeoutError(_) | RaworcError::ApiError { status: 500.., .. })
+        );
+        metrics().observe_api_outcome(&route, failed);
+        result
     }
 
-    async fn login_once(
-        http: &Client,
-        base_url: Url,
-        username: &str,
-        password: &str,
-        _timeout: u64,
-    ) -> RaworcResult<String> {
-        #[derive(Serialize)]
-        struct AuthRequest {
-            user: String,
-            pass: String,
-        }
+    async fn login_once(&self, username: &str, password: &str, _timeout: u64) -> RaworcResult<String> {
         #[derive(Deserialize)]
         struct AuthResponseWire {
             token: String,
         }
 
-        let mut base = base_url.clone();
-        if !base.path().ends_with('/') {
-            base.set_path(&format!("{}/", base.path()));
-        }
-        // NOTE: no leading slash here so `/api/v0` is preserved
-        let url = base.join("auth/login").unwrap();
-
-        let res = http
-            .post(url)
-            .header(header::CONTENT_TYPE, "application/json")
-            .json(&AuthRequest {
-                user: username.to_string(),
-                pass: password.to_string(),
-            })
-            .send()
-            .await?;
+        let body = serde_json::to_value(AuthRequest {
+            user: username.to_string(),
+            pass: password.into(),
+        })?;
+        let res = self.send(Method::POST, "auth/login", Some(&body), false).await?;
 
-        if res.status().is_success() {
-            let r = res.json::<AuthResponseWire>().await?;
+        if res.status.is_success() {
+            let r = serde_json::from_str::<AuthResponseWire>(&res.body)?;
             Ok(r.token)
         } else {
-            let status = res.status();
-            let text = res.text().await.unwrap_or_default();
-            if let Ok(api) = serde_json::from_str::<ApiErrorResponse>(&text) {
-                Err(RaworcError::api_error(status.as_u16(), api.error.message))
-            } else {
-                Err(RaworcError::api_error(status.as_u16(), text))
-            }
+            let message = match serde_json::from_str::<ApiErrorResponse>(&res.body) {
+                Ok(api) => api.error.message,
+                Err(_) => res.body,
+            };
+            Err(RaworcError::api_error(res.status.as_u16(), message).with_request_id(res.request_id))
+        }
+    }
+}
+
+/// Route template of a request path, with ids replaced by placeholders, e.g.
+/// `spaces/dev/sessions/42/messages?limit=5` -> `spaces/{space}/sessions/{session}/messages`.
+/// Keeps span and metric labels low-cardinality.
+pub fn route_template(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or(path).trim_matches('/');
+    let mut out: Vec<&str> = Vec::new();
+    let mut previous = "";
+    for segment in path.split('/') {
+        let placeholder = match previous {
+            "spaces" => Some("{space}"),
+            "sessions" => Some("{session}"),
+            "agents" => Some("{agent}"),
+            "secrets" => Some("{secret}"),
+            "build" if segment != "latest" => Some("{build}"),
+            "service-accounts" => Some("{service_account}"),
+            "roles" => Some("{role}"),
+            "role-bindings" => Some("{role_binding}"),
+            _ => None,
+        };
+        out.push(placeholder.unwrap_or(segment));
+        previous = segment;
+    }
+    out.join("/")
+}
+
+/// Build the HTTP client, applying proxy and TLS settings. Certificate and key
+/// files are read and parsed here, so bad paths fail at startup.
+fn build_http_client(config: &crate::Config, timeout: u64) -> RaworcResult<Client> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(timeout))
+        .user_agent(USER_AGENT);
+
+    if let Some(proxy_url) = &config.proxy {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| RaworcError::ConfigError(format!("Invalid proxy URL {}: {}", proxy_url, e)))?
+            .no_proxy(config.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = &config.ca_cert {
+        let pem = read_config_file(path, "CA certificate")?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| RaworcError::ConfigError(format!("Invalid CA certificate {}: {}", path.display(), e)))?;
+        if certs.is_empty() {
+            return Err(RaworcError::ConfigError(format!(
+                "CA certificate {} contains no PEM certificates",
+                path.display()
+            )));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    match (&config.client_cert, &config.client_key) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = read_config_file(cert_path, "client certificate")?;
+            let key = read_config_file(key_path, "client key")?;
+            let identity = reqwest::Identity::from_pkcs8_pem(&cert, &key).map_err(|e| {
+                RaworcError::ConfigError(format!(
+                    "Invalid client certificate {} / key {} (expected PEM certificate and PKCS#8 PEM key): {}",
+                    cert_path.display(),
+                    key_path.display(),
+                    e
+                ))
+            })?;
+            builder = builder.identity(identity);
+        }
+        (Some(_), None) => {
+            return Err(RaworcError::config_error("client_cert is set but client_key is missing"));
+        }
+        (None, Some(_)) => {
+            return Err(RaworcError::config_error("client_key is set but client_cert is missing"));
         }
+        (None, None) => {}
     }
+
+    builder
+        .build()
+        .map_err(|e| RaworcError::ConfigError(format!("Failed to create HTTP client: {}", e)))
+}
+
+fn read_config_file(path: &std::path::Path, what: &str) -> RaworcResult<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| RaworcError::ConfigError(format!("Cannot read {} {}: {}", what, path.display(), e)))
+}
+
+/// Apply `since`, then `grep`, then `tail` to log lines. Lines without a leading
+/// RFC 3339 timestamp inherit the verdict of the last timestamped line, so
+/// multi-line entries stay together.
+pub fn filter_log_lines(lines: Vec<String>, query: &AgentLogsQuery) -> RaworcResult<Vec<String>> {
+    let grep = query
+        .grep
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| RaworcError::ValidationError(format!("Invalid grep pattern: {}", e)))?;
+
+    let mut keep_untimed = true;
+    let mut out: Vec<String> = lines
+        .into_iter()
+        .filter(|line| match (query.since, line_timestamp(line)) {
+            (Some(since), Some(ts)) => {
+                keep_untimed = ts >= since;
+                keep_untimed
+            }
+            (Some(_), None) => keep_untimed,
+            (None, _) => true,
+        })
+        .filter(|line| grep.as_ref().is_none_or(|re| re.is_match(line)))
+        .collect();
+
+    if let Some(n) = query.tail {
+        let n = usize::try_from(n).unwrap_or(usize::MAX);
+        out.drain(..out.len().saturating_sub(n));
+    }
+    Ok(out)
+}
+
+/// Leading `2024-05-01T12:00:00Z` or `[2024-05-01T12:00:00Z]` timestamp of a log line
+pub(crate) fn line_timestamp(line: &str) -> Option<DateTime<Utc>> {
+    let token = line.trim_start().trim_start_matches('[');
+    let end = token
+        .find(|c: char| c.is_whitespace() || c == ']')
+        .unwrap_or(token.len());
+    DateTime::parse_from_rfc3339(&token[..end])
+        .ok()
+        .map(|ts| ts.with_timezone(&Utc))
 }
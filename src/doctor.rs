@@ -0,0 +1,305 @@
+//! `raworc-mcp doctor`: configuration and connectivity diagnostics.
+//! Each check passes, warns, fails or is skipped because an earlier check
+//! failed; failures carry a hint on how to fix them.
+
+use crate::api::RaworcApi;
+use crate::client::RaworcClient;
+use crate::error::{RaworcError, RaworcResult};
+use crate::Config;
+use base64::Engine;
+use serde_json::Value;
+use std::fmt;
+
+/// API version this server is written against
+pub const SUPPORTED_API_VERSION: &str = "v0";
+
+/// Warn when the token expires sooner than this
+const TOKEN_EXPIRY_WARNING_SECS: i64 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+/// All checks, in the order they ran
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// True unless some check failed
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|c| c.status != Status::Fail)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Check> {
+        self.checks.iter().find(|c| c.name == name)
+    }
+
+    fn pass(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, Status::Pass, detail.into(), None);
+    }
+
+    fn warn(&mut self, name: &'static str, detail: impl Into<String>, hint: &str) {
+        self.push(name, Status::Warn, detail.into(), Some(hint.to_string()));
+    }
+
+    fn fail(&mut self, name: &'static str, detail: impl Into<String>, hint: &str) {
+        self.push(name, Status::Fail, detail.into(), Some(hint.to_string()));
+    }
+
+    fn skip(&mut self, name: &'static str, reason: &str) {
+        self.push(name, Status::Skip, reason.to_string(), None);
+    }
+
+    fn push(&mut self, name: &'static str, status: Status, detail: String, hint: Option<String>) {
+        self.checks.push(Check { name, status, detail, hint });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let label = match check.status {
+                Status::Pass => "PASS",
+                Status::Warn => "WARN",
+                Status::Fail => "FAIL",
+                Status::Skip => "SKIP",
+            };
+            writeln!(f, "[{}] {:<16} {}", label, check.name, check.detail)?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "       {:<16} hint: {}", "", hint)?;
+            }
+        }
+        let failed = self.checks.iter().filter(|c| c.status == Status::Fail).count();
+        if failed == 0 {
+            write!(f, "\nAll checks passed")
+        } else {
+            write!(f, "\n{} check(s) failed", failed)
+        }
+    }
+}
+
+/// Diagnose a configuration as `Config::load` returned it, building the real client
+pub async fn diagnose(config: RaworcResult<Config>) -> Report {
+    let mut report = Report::default();
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            report.fail(
+                "configuration",
+                e.to_string(),
+                "Fix the config file, RAWORC_* variables or flags named above",
+            );
+            return report;
+        }
+    };
+    match RaworcClient::new(&config) {
+        Ok(mut client) => run(&mut client, &config, report).await,
+        Err(e) => {
+            report.fail(
+                "client",
+                e.to_string(),
+                "Check RAWORC_PROXY, RAWORC_CA_CERT, RAWORC_CLIENT_CERT and RAWORC_CLIENT_KEY",
+            );
+            report
+        }
+    }
+}
+
+/// Run every check against `client`, appending to `report`
+pub async fn run<C: RaworcApi>(client: &mut C, config: &Config, mut report: Report) -> Report {
+    let api_url = config.api_url.as_deref().unwrap_or_default();
+    let url_version = match url::Url::parse(api_url) {
+        Ok(url) => {
+            let version = url
+                .path_segments()
+                .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+                .filter(|s| s.starts_with('v') && s[1..].chars().all(|c| c.is_ascii_digit()))
+                .map(str::to_string);
+            if version.is_some() {
+                report.pass("api_url", api_url);
+            } else {
+                report.warn(
+                    "api_url",
+                    format!("{} has no /api/{} suffix", api_url, SUPPORTED_API_VERSION),
+                    "RAWORC_API_URL should be the API base, e.g. https://api.remoteagent.com/api/v0",
+                );
+            }
+            version
+        }
+        Err(e) => {
+            report.fail(
+                "api_url",
+                format!("'{}' is not a URL: {}", api_url, e),
+                "Set RAWORC_API_URL to e.g. https://api.remoteagent.com/api/v0",
+            );
+            return report;
+        }
+    };
+
+    if let Err(e) = client.health_check().await {
+        report.fail(
+            "health",
+            e.to_string(),
+            "Check the host and port in RAWORC_API_URL, and proxy settings if you are behind one",
+        );
+        for name in ["version", "auth", "user", "default_space", "sessions", "agents", "secrets"] {
+            report.skip(name, "API unreachable");
+        }
+        return report;
+    }
+    report.pass("health", "API is reachable");
+
+    match client.get_version().await {
+        Ok(v) if v.api == SUPPORTED_API_VERSION && url_version.as_deref().is_none_or(|u| u == v.api) => {
+            report.pass("version", format!("server {} (API {})", v.version, v.api))
+        }
+        Ok(v) if v.api != SUPPORTED_API_VERSION => report.fail(
+            "version",
+            format!("server speaks API {}, this build supports {}", v.api, SUPPORTED_API_VERSION),
+            "Upgrade raworc-mcp or point RAWORC_API_URL at a compatible deployment",
+        ),
+        Ok(v) => report.fail(
+            "version",
+            format!("RAWORC_API_URL targets {} but the server speaks {}", url_version.unwrap_or_default(), v.api),
+            "Fix the version segment at the end of RAWORC_API_URL",
+        ),
+        Err(e) => report.warn("version", e.to_string(), "The version endpoint failed; other checks may still pass"),
+    }
+
+    let authenticated = check_auth(client, config, &mut report).await;
+    if !authenticated {
+        for name in ["user", "default_space", "sessions", "agents", "secrets"] {
+            report.skip(name, "not authenticated");
+        }
+        return report;
+    }
+
+    match client.get_user_info().await {
+        Ok(user) => report.pass("user", format!("{} ({})", user.user, user.r#type)),
+        Err(e) => report.fail("user", e.to_string(), auth_hint(config)),
+    }
+
+    let Some(space) = config.default_space.as_deref() else {
+        report.warn(
+            "default_space",
+            "not set",
+            "Set RAWORC_DEFAULT_SPACE, or pass `space` to every tool call",
+        );
+        for name in ["sessions", "agents", "secrets"] {
+            report.skip(name, "no default space");
+        }
+        return report;
+    };
+    if let Err(e) = client.get_space(space).await {
+        let hint = match e {
+            RaworcError::NotFound { .. } | RaworcError::ApiError { status: 404, .. } => {
+                "The space does not exist; `raworc-mcp call list_spaces` shows the ones you can see"
+            }
+            _ => "Check that your account has access to this space",
+        };
+        report.fail("default_space", format!("{}: {}", space, e), hint);
+        for name in ["sessions", "agents", "secrets"] {
+            report.skip(name, "default space unavailable");
+        }
+        return report;
+    }
+    report.pass("default_space", space);
+
+    let hint = "Your account may lack a role binding for this resource in the space";
+    match client.list_sessions(Some(space)).await {
+        Ok(items) => report.pass("sessions", format!("{} session(s)", items.len())),
+        Err(e) => report.fail("sessions", e.to_string(), hint),
+    }
+    match client.list_agents(Some(space)).await {
+        Ok(items) => report.pass("agents", format!("{} agent(s)", items.len())),
+        Err(e) => report.fail("agents", e.to_string(), hint),
+    }
+    match client.list_secrets(Some(space)).await {
+        Ok(items) => report.pass("secrets", format!("{} secret(s)", items.len())),
+        Err(e) => report.fail("secrets", e.to_string(), hint),
+    }
+    report
+}
+
+/// Token expiry (when it is a JWT) or a login with credentials
+async fn check_auth<C: RaworcApi>(client: &mut C, config: &Config, report: &mut Report) -> bool {
+    if let Some(token) = config.auth_token.as_ref().map(|t| t.expose().as_str()) {
+        let now = chrono::Utc::now().timestamp();
+        match token_expiry(token) {
+            Some(exp) if exp <= now => {
+                report.fail(
+                    "auth",
+                    format!("token expired {}", format_timestamp(exp)),
+                    auth_hint(config),
+                );
+                return false;
+            }
+            Some(exp) if exp - now < TOKEN_EXPIRY_WARNING_SECS => report.warn(
+                "auth",
+                format!("token expires soon ({})", format_timestamp(exp)),
+                "Issue a new token before it expires",
+            ),
+            Some(exp) => report.pass("auth", format!("token valid until {}", format_timestamp(exp))),
+            None => report.pass("auth", "token set (no expiry claim)"),
+        }
+        return true;
+    }
+    match (config.username.as_deref(), config.password.as_ref()) {
+        (Some(username), Some(password)) => match client.authenticate(username, password.expose()).await {
+            Ok(()) => {
+                report.pass("auth", format!("logged in as {}", username));
+                true
+            }
+            Err(e) => {
+                report.fail("auth", e.to_string(), auth_hint(config));
+                false
+            }
+        },
+        _ => {
+            report.fail(
+                "auth",
+                "no credentials configured",
+                "Set RAWORC_AUTH_TOKEN, or RAWORC_USERNAME and RAWORC_PASSWORD",
+            );
+            false
+        }
+    }
+}
+
+fn auth_hint(config: &Config) -> &'static str {
+    if config.auth_token.is_some() {
+        "The token was rejected; log in again (see 'Getting Your Authentication Token') and update RAWORC_AUTH_TOKEN"
+    } else {
+        "Check RAWORC_USERNAME and RAWORC_PASSWORD"
+    }
+}
+
+/// `exp` claim of a JWT, without verifying its signature
+fn token_expiry(token: &str) -> Option<i64> {
+    let payload = token.split('.').nth(1)?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    serde_json::from_slice::<Value>(&bytes).ok()?.get("exp")?.as_i64()
+}
+
+fn format_timestamp(secs: i64) -> String {
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| secs.to_string())
+}
@@ -1,5 +1,6 @@
 use thiserror::Error;
 use serde::Deserialize;
+use crate::models::PlannedRequest;
 
 /// Custom error type for Raworc MCP operations
 #[derive(Error, Debug)]
@@ -10,17 +11,27 @@ pub enum RaworcError {
     #[error("JSON serialization/deserialization failed: {0}")]
     JsonError(#[from] serde_json::Error),
 
-    #[error("Authentication failed: {0}")]
-    AuthError(String),
+    #[error("Authentication failed: {message}{}", request_id_suffix(.request_id))]
+    AuthError {
+        message: String,
+        /// `X-Request-Id` of the failing call, when it came from the API
+        request_id: Option<String>,
+    },
 
-    #[error("API error: {message}")]
+    #[error("API error: {message}{}", request_id_suffix(.request_id))]
     ApiError {
         status: u16,
         message: String,
+        /// `X-Request-Id` of the failing call, as echoed by the server
+        request_id: Option<String>,
     },
 
-    #[error("Resource not found: {0}")]
-    NotFound(String),
+    #[error("Resource not found: {message}{}", request_id_suffix(.request_id))]
+    NotFound {
+        message: String,
+        /// `X-Request-Id` of the failing call, when it came from the API
+        request_id: Option<String>,
+    },
 
     #[error("Invalid configuration: {0}")]
     ConfigError(String),
@@ -31,24 +42,66 @@ pub enum RaworcError {
     #[error("Timeout error: {0}")]
     TimeoutError(String),
 
+    #[error("Unexpected state: {0}")]
+    StateError(String),
+
     #[error("Internal error: {0}")]
     InternalError(String),
 
     #[error("MCP protocol error: {0}")]
     McpError(String),
+
+    #[error("Denied by policy: {0}")]
+    PolicyError(String),
+
+    /// A write was planned but not sent because dry-run mode is on
+    #[error("Dry run: {} {}", .0.method, .0.path)]
+    DryRun(Box<PlannedRequest>),
 }
 
 impl RaworcError {
     pub fn api_error(status: u16, message: String) -> Self {
-        Self::ApiError { status, message }
+        Self::ApiError {
+            status,
+            message,
+            request_id: None,
+        }
+    }
+
+    /// Attach a request id to an error from an API response (`ApiError`,
+    /// `NotFound`, `AuthError`); other variants are returned unchanged
+    pub fn with_request_id(mut self, id: impl Into<String>) -> Self {
+        match &mut self {
+            Self::ApiError { request_id, .. }
+            | Self::NotFound { request_id, .. }
+            | Self::AuthError { request_id, .. } => *request_id = Some(id.into()),
+            _ => {}
+        }
+        self
+    }
+
+    /// Request id of a failed API call, if known
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Self::ApiError { request_id, .. }
+            | Self::NotFound { request_id, .. }
+            | Self::AuthError { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
     }
 
     pub fn not_found(resource: &str) -> Self {
-        Self::NotFound(resource.to_string())
+        Self::NotFound {
+            message: resource.to_string(),
+            request_id: None,
+        }
     }
 
     pub fn auth_error(message: &str) -> Self {
-        Self::AuthError(message.to_string())
+        Self::AuthError {
+            message: message.to_string(),
+            request_id: None,
+        }
     }
 
     pub fn validation_error(message: &str) -> Self {
@@ -63,6 +116,10 @@ impl RaworcError {
         Self::TimeoutError(message.to_string())
     }
 
+    pub fn state_error(message: &str) -> Self {
+        Self::StateError(message.to_string())
+    }
+
     pub fn internal_error(message: &str) -> Self {
         Self::InternalError(message.to_string())
     }
@@ -70,6 +127,17 @@ impl RaworcError {
     pub fn mcp_error(message: &str) -> Self {
         Self::McpError(message.to_string())
     }
+
+    pub fn policy_error(message: &str) -> Self {
+        Self::PolicyError(message.to_string())
+    }
+}
+
+fn request_id_suffix(request_id: &Option<String>) -> String {
+    request_id
+        .as_ref()
+        .map(|id| format!(" (request id: {})", id))
+        .unwrap_or_default()
 }
 
 /// Result type for Raworc operations
@@ -0,0 +1,121 @@
+//! Local commands run before and after selected tools.
+//! - A hook gets one JSON object on stdin: the event, tool name, redacted
+//!   arguments, the caller's identity and, for `post` hooks, the result
+//! - A `pre` hook vetoes the call by exiting with status 2; its stderr (or
+//!   stdout) is the reason given to the model
+//! - Any other non-zero exit, a timeout or a command that cannot start is a
+//!   failure, handled per hook by `on_failure`
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Exit status a `pre` hook uses to veto the call
+pub const VETO_EXIT_CODE: i32 = 2;
+
+/// When a hook runs relative to the tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    Pre,
+    Post,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pre => "pre",
+            Self::Post => "post",
+        }
+    }
+}
+
+/// What a failed hook does to the call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Log the failure and carry on
+    #[default]
+    Open,
+    /// Refuse the call (`pre`) or turn its result into an error (`post`)
+    Closed,
+}
+
+/// One configured hook
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    /// Tool-name glob, e.g. `delete_*`
+    pub tool: String,
+    pub event: HookEvent,
+    /// Program and arguments; run directly, not through a shell
+    pub command: Vec<String>,
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+}
+
+fn default_timeout_seconds() -> u64 {
+    10
+}
+
+/// How a hook run ended, short of failing
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutcome {
+    Continue,
+    /// The `pre` hook refused the call, with this reason
+    Veto(String),
+}
+
+impl Hook {
+    /// Run the hook with `input` on stdin. `Err` describes a failure.
+    pub async fn run(&self, input: &Value) -> Result<HookOutcome, String> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| "hook command is empty".to_string())?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("cannot start {}: {}", program, e))?;
+
+        let mut stdin = child.stdin.take().ok_or("hook stdin unavailable")?;
+        let input = serde_json::to_vec(input).map_err(|e| e.to_string())?;
+        let write = async move {
+            // A hook may exit without reading its input; that is not a failure
+            let _ = stdin.write_all(&input).await;
+            drop(stdin);
+        };
+        // Feed stdin while draining stdout/stderr, or a hook that writes a lot
+        // before reading everything blocks on a full pipe
+        let run = async move {
+            let ((), output) = tokio::join!(write, child.wait_with_output());
+            output
+        };
+        let output = tokio::time::timeout(Duration::from_secs(self.timeout_seconds), run)
+            .await
+            .map_err(|_| format!("{} timed out after {}s", program, self.timeout_seconds))?
+            .map_err(|e| format!("{} failed: {}", program, e))?;
+
+        let message = |bytes: &[u8]| String::from_utf8_lossy(bytes).trim().to_string();
+        match output.status.code() {
+            Some(0) => Ok(HookOutcome::Continue),
+            Some(VETO_EXIT_CODE) if self.event == HookEvent::Pre => {
+                let reason = [message(&output.stderr), message(&output.stdout)]
+                    .into_iter()
+                    .find(|m| !m.is_empty())
+                    .unwrap_or_else(|| format!("vetoed by {}", program));
+                Ok(HookOutcome::Veto(reason))
+            }
+            _ => Err(format!("{} exited with {}: {}", program, output.status, message(&output.stderr))),
+        }
+    }
+}
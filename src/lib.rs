@@ -1,21 +1,78 @@
+pub mod api;
+pub mod audit;
+pub mod cache;
+pub mod cassette;
 pub mod client;
+pub mod doctor;
 pub mod error;
+pub mod hooks;
 pub mod mcp;
+pub mod metrics;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod models;
+pub mod policy;
+pub mod profile;
+pub mod redact;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod testing;
 
+pub use api::RaworcApi;
+pub use audit::AuditConfig;
+pub use cache::CacheConfig;
+pub use cassette::CassetteMode;
 pub use client::RaworcClient;
 pub use error::{RaworcError, RaworcResult};
-pub use mcp::RaworcMcpServer;
+pub use mcp::{OutputFormat, RaworcMcpServer};
+pub use policy::{Policy, ToolFilter};
+pub use profile::LoadOptions;
+
+use std::path::PathBuf;
 
 /// Configuration for the Raworc client
 #[derive(Debug, Clone)]
 pub struct Config {
     pub api_url: Option<String>,
-    pub auth_token: Option<String>,
+    pub auth_token: Option<redact::Secret<String>>,
     pub username: Option<String>,
-    pub password: Option<String>,
+    pub password: Option<redact::Secret<String>>,
     pub default_space: Option<String>,
     pub timeout_seconds: Option<u64>,
+    /// Character budget for a single tool result (default 100k); see `max_chars`
+    pub max_output_chars: Option<usize>,
+    /// Default rendering for tool results; `format` overrides it per call
+    pub output_format: Option<OutputFormat>,
+    /// Record HTTP traffic to, or replay it from, a cassette file
+    pub cassette: Option<CassetteMode>,
+    /// Cache rarely-changing reads in-process; off when `None`
+    pub cache: Option<CacheConfig>,
+    /// HTTP(S) proxy for all requests, e.g. `http://proxy.corp:3128`
+    pub proxy: Option<String>,
+    /// Comma-separated hosts/domains/CIDRs that bypass `proxy`
+    pub no_proxy: Option<String>,
+    /// PEM bundle of extra root certificates to trust
+    pub ca_cert: Option<PathBuf>,
+    /// PEM client certificate for mTLS; requires `client_key`
+    pub client_cert: Option<PathBuf>,
+    /// PKCS#8 PEM private key for `client_cert`
+    pub client_key: Option<PathBuf>,
+    /// Spaces whose secret values tools may return unmasked
+    pub reveal_secrets: Vec<String>,
+    /// Refuse every tool that changes state upstream
+    pub read_only: bool,
+    /// Describe the requests mutating tools would send instead of sending them
+    pub dry_run: bool,
+    /// Allow/deny globs for tool names
+    pub tools: ToolFilter,
+    /// Globs of the spaces tools may act on; every space when empty
+    pub allowed_spaces: Vec<String>,
+    /// Argument-aware rules evaluated before each tool call
+    pub policy: Option<Policy>,
+    /// Local commands run before and after matching tools
+    pub hooks: Vec<hooks::Hook>,
+    /// JSONL audit log of mutating tool calls; off when `None`
+    pub audit: Option<AuditConfig>,
 }
 
 impl Config {
@@ -28,19 +85,36 @@ impl Config {
             password: None,
             default_space: None,
             timeout_seconds: None,
+            max_output_chars: None,
+            output_format: None,
+            cassette: None,
+            cache: None,
+            proxy: None,
+            no_proxy: None,
+            ca_cert: None,
+            client_cert: None,
+            client_key: None,
+            reveal_secrets: Vec::new(),
+            read_only: false,
+            dry_run: false,
+            tools: ToolFilter::default(),
+            allowed_spaces: Vec::new(),
+            policy: None,
+            hooks: Vec::new(),
+            audit: None,
         }
     }
 
     /// Set authentication token
     pub fn with_auth_token(mut self, token: String) -> Self {
-        self.auth_token = Some(token);
+        self.auth_token = Some(token.into());
         self
     }
 
     /// Set username and password for authentication
     pub fn with_credentials(mut self, username: String, password: String) -> Self {
         self.username = Some(username);
-        self.password = Some(password);
+        self.password = Some(password.into());
         self
     }
 
@@ -55,6 +129,118 @@ impl Config {
         self.timeout_seconds = Some(timeout);
         self
     }
+
+    /// Set the character budget for tool results
+    pub fn with_max_output_chars(mut self, max_chars: usize) -> Self {
+        self.max_output_chars = Some(max_chars);
+        self
+    }
+
+    /// Set the default output format
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// Record or replay HTTP traffic through a cassette file
+    pub fn with_cassette(mut self, mode: CassetteMode) -> Self {
+        self.cassette = Some(mode);
+        self
+    }
+
+    /// Enable the response cache with the given TTLs
+    pub fn with_cache(mut self, cache: CacheConfig) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Route requests through a proxy, except for hosts in `no_proxy`
+    pub fn with_proxy(mut self, proxy: String, no_proxy: Option<String>) -> Self {
+        self.proxy = Some(proxy);
+        self.no_proxy = no_proxy;
+        self
+    }
+
+    /// Trust the root certificates in a PEM bundle
+    pub fn with_ca_cert(mut self, path: PathBuf) -> Self {
+        self.ca_cert = Some(path);
+        self
+    }
+
+    /// Present a client certificate (mTLS)
+    pub fn with_client_cert(mut self, cert: PathBuf, key: PathBuf) -> Self {
+        self.client_cert = Some(cert);
+        self.client_key = Some(key);
+        self
+    }
+
+    /// Show secret values from `space` unmasked
+    pub fn with_revealed_secrets(mut self, space: String) -> Self {
+        self.reveal_secrets.push(space);
+        self
+    }
+
+    /// Expose only tools that do not change state upstream
+    pub fn with_read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Restrict the exposed tools with allow/deny globs
+    pub fn with_tool_filter(mut self, tools: ToolFilter) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Whether `tool` is listed and may be called under `read_only` and `tools`
+    pub fn tool_enabled(&self, tool: &str) -> bool {
+        !(self.read_only && policy::is_mutating(tool)) && self.tools.permits(tool)
+    }
+
+    /// Limit tools to spaces matching these globs
+    pub fn with_allowed_spaces(mut self, spaces: Vec<String>) -> Self {
+        self.allowed_spaces = spaces;
+        self
+    }
+
+    /// Evaluate `policy` before every tool call
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Run a local command before or after matching tools
+    pub fn with_hook(mut self, hook: hooks::Hook) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Plan mutating tool calls without sending them
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Record mutating tool calls in an audit log
+    pub fn with_audit_log(mut self, audit: AuditConfig) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Space used when a call names none, as `RaworcClient` resolves it
+    pub fn space_or_default(&self) -> &str {
+        self.default_space.as_deref().unwrap_or("default")
+    }
+
+    /// Whether tools may act on `space`
+    pub fn space_allowed(&self, space: &str) -> bool {
+        self.allowed_spaces.is_empty() || policy::matches_any(&self.allowed_spaces, space)
+    }
+
+    /// Whether secret values from `space` may be returned unmasked
+    pub fn reveals_secrets(&self, space: &str) -> bool {
+        self.reveal_secrets.iter().any(|s| s == space)
+    }
 }
 
 /// MCP capabilities constant
@@ -96,6 +282,7 @@ pub const CAPABILITIES: &str = r#"{
                     },
                     "pass": {
                         "type": "string",
+                        "writeOnly": true,
                         "description": "Password for the service account"
                     },
                     "space": {
@@ -176,10 +363,12 @@ pub const CAPABILITIES: &str = r#"{
                     },
                     "current_password": {
                         "type": "string",
+                        "writeOnly": true,
                         "description": "Current password"
                     },
                     "new_password": {
                         "type": "string",
+                        "writeOnly": true,
                         "description": "New password"
                     }
                 },
@@ -559,6 +748,32 @@ pub const CAPABILITIES: &str = r#"{
                 "required": ["session_id", "content"]
             }
         },
+        {
+            "name": "ask_session",
+            "description": "Send a message to a session and wait for the agent's reply",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": {
+                        "type": "string",
+                        "description": "Session ID"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Message content"
+                    },
+                    "space": {
+                        "type": "string",
+                        "description": "Space name (optional)"
+                    },
+                    "timeout_seconds": {
+                        "type": "number",
+                        "description": "Maximum time to wait for a reply (default 120)"
+                    }
+                },
+                "required": ["session_id", "content"]
+            }
+        },
         {
             "name": "get_messages",
             "description": "Get messages from a session",
@@ -671,6 +886,33 @@ pub const CAPABILITIES: &str = r#"{
                 "required": ["session_id"]
             }
         },
+        {
+            "name": "wait_session",
+            "description": "Wait until a session reaches one of the given states",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": {
+                        "type": "string",
+                        "description": "Session ID"
+                    },
+                    "states": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "Target states, e.g. [\"IDLE\"] after resume_session or [\"TERMINATED\"] after terminate_session"
+                    },
+                    "space": {
+                        "type": "string",
+                        "description": "Space name (optional)"
+                    },
+                    "timeout_seconds": {
+                        "type": "number",
+                        "description": "Maximum time to wait (default 120)"
+                    }
+                },
+                "required": ["session_id", "states"]
+            }
+        },
         {
             "name": "list_agents",
             "description": "List agents in a space",
@@ -866,6 +1108,22 @@ pub const CAPABILITIES: &str = r#"{
                     "agent_name": {
                         "type": "string",
                         "description": "Agent name"
+                    },
+                    "tail": {
+                        "type": "number",
+                        "description": "Only return the last N lines (after filtering)"
+                    },
+                    "since": {
+                        "type": "string",
+                        "description": "Only lines at or after this RFC 3339 timestamp or age (e.g. 15m, 2h)"
+                    },
+                    "grep": {
+                        "type": "string",
+                        "description": "Only lines matching this regular expression"
+                    },
+                    "follow": {
+                        "type": "boolean",
+                        "description": "Only return lines added since the previous follow call for this agent"
                     }
                 },
                 "required": ["space", "agent_name"]
@@ -874,6 +1132,7 @@ pub const CAPABILITIES: &str = r#"{
         {
             "name": "list_secrets",
             "description": "List secrets in a space",
+            "_meta": { "sensitiveResultFields": ["value"] },
             "inputSchema": {
                 "type": "object",
                 "properties": {
@@ -887,6 +1146,7 @@ pub const CAPABILITIES: &str = r#"{
         {
             "name": "create_secret",
             "description": "Create a new secret",
+            "_meta": { "sensitiveResultFields": ["value"] },
             "inputSchema": {
                 "type": "object",
                 "properties": {
@@ -900,6 +1160,7 @@ pub const CAPABILITIES: &str = r#"{
                     },
                     "value": {
                         "type": "string",
+                        "writeOnly": true,
                         "description": "Secret value"
                     },
                     "description": {
@@ -913,6 +1174,7 @@ pub const CAPABILITIES: &str = r#"{
         {
             "name": "get_secret",
             "description": "Get a secret value",
+            "_meta": { "sensitiveResultFields": ["value"] },
             "inputSchema": {
                 "type": "object",
                 "properties": {
@@ -928,9 +1190,34 @@ pub const CAPABILITIES: &str = r#"{
                 "required": ["space", "key"]
             }
         },
+        {
+            "name": "set_secret",
+            "description": "Set a secret value",
+            "_meta": { "sensitiveResultFields": ["value"] },
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "space": {
+                        "type": "string",
+                        "description": "Space name"
+                    },
+                    "key": {
+                        "type": "string",
+                        "description": "Secret key"
+                    },
+                    "value": {
+                        "type": "string",
+                        "writeOnly": true,
+                        "description": "Secret value"
+                    }
+                },
+                "required": ["space", "key", "value"]
+            }
+        },
         {
             "name": "update_secret",
             "description": "Update a secret value",
+            "_meta": { "sensitiveResultFields": ["value"] },
             "inputSchema": {
                 "type": "object",
                 "properties": {
@@ -944,6 +1231,7 @@ pub const CAPABILITIES: &str = r#"{
                     },
                     "value": {
                         "type": "string",
+                        "writeOnly": true,
                         "description": "New secret value"
                     },
                     "description": {
@@ -1025,6 +1313,49 @@ pub const CAPABILITIES: &str = r#"{
                 },
                 "required": ["space", "build_id"]
             }
+        },
+        {
+            "name": "wait_for_build",
+            "description": "Wait for a build to complete or fail, streaming its logs as progress",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "space": {
+                        "type": "string",
+                        "description": "Space name"
+                    },
+                    "build_id": {
+                        "type": "string",
+                        "description": "Build ID (optional, follows the latest build if omitted)"
+                    },
+                    "timeout_seconds": {
+                        "type": "number",
+                        "description": "Maximum time to wait (default 120)"
+                    }
+                },
+                "required": ["space"]
+            }
+        },
+        {
+            "name": "audit_log",
+            "description": "Show recent audit log entries for mutating tool calls, newest first",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "space": {
+                        "type": "string",
+                        "description": "Only entries for this space"
+                    },
+                    "tool": {
+                        "type": "string",
+                        "description": "Only entries for tools matching this glob, e.g. delete_*"
+                    },
+                    "limit": {
+                        "type": "number",
+                        "description": "Maximum number of entries (default 20)"
+                    }
+                }
+            }
         }
     ]
 }"#;
@@ -1,35 +1,233 @@
 use anyhow::Result;
+use clap::{Parser, Subcommand};
 use serde_json::{json, Value};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::fmt::writer::BoxMakeWriter;
-use raworc_mcp::{Config, RaworcMcpServer};
+use tracing_subscriber::prelude::*;
+use raworc_mcp::mcp::{parse_tool_arguments, tool_list};
+use raworc_mcp::policy::{filter_tool_list, resolve_spaces};
+use raworc_mcp::audit::{AuditLog, AuditQuery};
+use raworc_mcp::{Config, LoadOptions, RaworcClient, RaworcMcpServer};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// MCP server for the Raworc platform, speaking JSON-RPC over stdio
+#[derive(Parser)]
+#[command(name = "raworc-mcp", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9464
+    #[arg(long, global = true, env = "RAWORC_METRICS_ADDR")]
+    metrics_addr: Option<SocketAddr>,
+    /// Config file with named profiles [default: ~/.config/raworc-mcp/config.toml]
+    #[arg(long, global = true, env = "RAWORC_CONFIG")]
+    config: Option<PathBuf>,
+    /// Profile to use from the config file
+    #[arg(long, global = true, env = "RAWORC_PROFILE")]
+    profile: Option<String>,
+    /// Raworc API base URL; overrides the profile and RAWORC_API_URL
+    #[arg(long, global = true)]
+    api_url: Option<String>,
+    /// Default space; overrides the profile and RAWORC_DEFAULT_SPACE
+    #[arg(long, global = true)]
+    default_space: Option<String>,
+    /// Request timeout in seconds; overrides the profile and RAWORC_TIMEOUT
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
+    /// Expose only tools that do not change anything upstream
+    #[arg(long, global = true)]
+    read_only: bool,
+    /// Show the requests mutating tools would send instead of sending them
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Policy rules file; overrides the profile and RAWORC_POLICY
+    #[arg(long, global = true)]
+    policy: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the MCP server over stdio (the default)
+    Serve,
+    /// Print every tool with its input schema
+    Tools,
+    /// Check configuration, connectivity and credentials
+    Doctor,
+    /// Work with the policy file
+    Policy {
+        #[command(subcommand)]
+        command: PolicyCommand,
+    },
+    /// Call a tool once and print its result
+    Call {
+        /// Tool name, e.g. list_sessions
+        tool: String,
+        /// Arguments as a JSON object
+        #[arg(long)]
+        args: Option<String>,
+        /// One argument as key=value; values that parse as JSON keep their type
+        #[arg(long = "arg", value_name = "KEY=VALUE")]
+        arg: Vec<String>,
+    },
+    /// Show recent audit log entries, newest first
+    Audit {
+        /// Only entries for this space
+        #[arg(long)]
+        space: Option<String>,
+        /// Only entries for tools matching this glob
+        #[arg(long)]
+        tool: Option<String>,
+        /// Most entries to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Check the hash chain instead of listing entries
+        #[arg(long)]
+        verify: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PolicyCommand {
+    /// Show what the policy decides for a call, without running it
+    Check {
+        /// Tool name, e.g. delete_agent
+        tool: String,
+        /// Arguments as a JSON object
+        #[arg(long)]
+        args: Option<String>,
+        /// One argument as key=value; values that parse as JSON keep their type
+        #[arg(long = "arg", value_name = "KEY=VALUE")]
+        arg: Vec<String>,
+    },
+}
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    // STDERR-only logging
-    tracing_subscriber::fmt()
-        .with_writer(BoxMakeWriter::new(std::io::stderr))
-        .init();
+async fn main() -> Result<ExitCode> {
+    let cli = Cli::parse();
 
-    // Create configuration from environment variables
-    let config = Config {
-        api_url: Some(std::env::var("RAWORC_API_URL")
-            .unwrap_or_else(|_| "https://api.remoteagent.com/api/v0".to_string())),
-        auth_token: std::env::var("RAWORC_AUTH_TOKEN").ok(),
-        username: std::env::var("RAWORC_USERNAME").ok(),
-        password: std::env::var("RAWORC_PASSWORD").ok(),
-        default_space: std::env::var("RAWORC_DEFAULT_SPACE").ok(),
-        timeout_seconds: std::env::var("RAWORC_TIMEOUT")
-            .ok()
-            .and_then(|s| s.parse().ok()),
+    // STDERR-only logging; with the `otel` feature, spans are also exported over OTLP
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_writer(BoxMakeWriter::new(std::io::stderr))
+        .with_filter(LevelFilter::INFO);
+    let registry = tracing_subscriber::registry().with(fmt_layer);
+    #[cfg(feature = "otel")]
+    let (registry, _telemetry) = {
+        let (layer, guard) = match raworc_mcp::telemetry::endpoint_from_env() {
+            Some(endpoint) => {
+                let (layer, guard) = raworc_mcp::telemetry::layer(&endpoint)?;
+                (Some(layer.with_filter(LevelFilter::INFO)), Some(guard))
+            }
+            None => (None, None),
+        };
+        (registry.with(layer), guard)
     };
+    registry.init();
+
+    if let Some(addr) = cli.metrics_addr {
+        tokio::spawn(async move {
+            if let Err(e) = raworc_mcp::metrics::serve(addr).await {
+                tracing::error!("Metrics server on {} failed: {}", addr, e);
+            }
+        });
+    }
+
+    // Config file profile, then RAWORC_* environment variables, then flags
+    let config = Config::load(&LoadOptions {
+        config_file: cli.config,
+        profile: cli.profile,
+        api_url: cli.api_url,
+        default_space: cli.default_space,
+        timeout_seconds: cli.timeout,
+        read_only: cli.read_only,
+        dry_run: cli.dry_run,
+        policy_file: cli.policy,
+    });
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            serve(RaworcMcpServer::new(config?)?).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Tools => {
+            let config = config?;
+            let tools = filter_tool_list(tool_list(), |name| config.tool_enabled(name));
+            println!("{}", serde_json::to_string_pretty(&tools["tools"])?);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Doctor => {
+            let report = raworc_mcp::doctor::diagnose(config).await;
+            println!("{}", report);
+            // Returned rather than `process::exit`, so telemetry still flushes
+            Ok(if report.is_ok() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+        }
+        Command::Policy { command: PolicyCommand::Check { tool, args, arg } } => {
+            let config = config?;
+            let policy = config
+                .policy
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("No policy file configured; pass --policy or set RAWORC_POLICY"))?;
+            let arguments = parse_tool_arguments(&tool, args.as_deref(), &arg)?;
+            // Only tools that name a session alone need the API to find their space
+            let client = RaworcClient::new(&config)?;
+            let spaces = resolve_spaces(&client, &tool, &arguments, config.space_or_default()).await?;
+            println!("{}", policy.evaluate_in(&tool, &arguments, &spaces));
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Call { tool, args, arg } => {
+            let mut server = RaworcMcpServer::new(config?)?;
+            server.set_client_name("raworc-mcp call");
+            let arguments = parse_tool_arguments(&tool, args.as_deref(), &arg)?;
+            let response = server.handle_tool_call(&tool, &arguments).await?;
+            for content in response.content {
+                if let Some(text) = content.text {
+                    println!("{}", text);
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Audit { space, tool, limit, verify } => {
+            let config = config?;
+            let audit = config
+                .audit
+                .map(AuditLog::new)
+                .ok_or_else(|| anyhow::anyhow!("No audit log configured; set RAWORC_AUDIT_LOG"))?;
+            if verify {
+                println!("{} record(s) verified", audit.verify()?);
+                return Ok(ExitCode::SUCCESS);
+            }
+            let query = AuditQuery { space, tool, limit: Some(limit) };
+            for record in audit.query(&query)? {
+                println!("{}", serde_json::to_string(&record)?);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
 
-    // Create MCP server
-    let mut server = RaworcMcpServer::new(config)?;
+/// JSON-RPC loop over stdin/stdout until the client closes stdin
+async fn serve(mut server: RaworcMcpServer) -> Result<()> {
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<Value>();
+    server.set_progress_sender(progress_tx);
+    let elicitor = server.elicitor();
     let mut stdin = BufReader::new(tokio::io::stdin()).lines();
     let mut stdout = tokio::io::stdout();
+    // Requests that arrived while a tool call was waiting on the host
+    let mut deferred = VecDeque::new();
+    let mut stdin_closed = false;
 
-    while let Some(line) = stdin.next_line().await? {
+    loop {
+        let line = match deferred.pop_front() {
+            Some(line) => line,
+            None if stdin_closed => break,
+            None => match stdin.next_line().await? {
+                Some(line) => line,
+                None => break,
+            },
+        };
         if line.trim().is_empty() {
             continue;
         }
@@ -44,20 +242,18 @@ async fn main() -> Result<()> {
 
         let method = msg.get("method").and_then(Value::as_str);
         let id = msg.get("id").and_then(Value::as_u64);
+        if method.is_none() && elicitor.resolve(&msg) {
+            continue;
+        }
 
         match method {
             Some("initialize") => {
+                let params = msg.get("params").cloned().unwrap_or_else(|| json!({}));
+                let result = server.handle_initialize(&params);
                 write_json(&mut stdout, json!({
                     "jsonrpc": "2.0",
                     "id": id,
-                    "result": {
-                        "protocolVersion": "2024-11-05",
-                        "capabilities": { "tools": {} },
-                        "serverInfo": {
-                            "name": "raworc-mcp",
-                            "version": env!("CARGO_PKG_VERSION")
-                        }
-                    }
+                    "result": result
                 })).await?;
                 
                 write_json(&mut stdout, json!({
@@ -68,258 +264,7 @@ async fn main() -> Result<()> {
             }
             
             Some("tools/list") => {
-                let tools = json!({
-                    "tools": [
-                        {
-                            "name": "health_check",
-                            "description": "Check Raworc API health",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {}
-                            }
-                        },
-                        {
-                            "name": "list_spaces",
-                            "description": "List all spaces",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {}
-                            }
-                        },
-                        {
-                            "name": "list_sessions",
-                            "description": "List all sessions in a space",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "space": {
-                                        "type": "string",
-                                        "description": "Space name (optional, uses default if not provided)"
-                                    }
-                                }
-                            }
-                        },
-                        {
-                            "name": "create_session",
-                            "description": "Create a new session",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "space": {
-                                        "type": "string",
-                                        "description": "Space name (optional, uses default if not provided)"
-                                    },
-                                    "metadata": {
-                                        "type": "object",
-                                        "description": "Additional metadata for the session"
-                                    }
-                                }
-                            }
-                        },
-                        {
-                            "name": "get_session",
-                            "description": "Get session details",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "session_id": {
-                                        "type": "string",
-                                        "description": "Session ID"
-                                    }
-                                },
-                                "required": ["session_id"]
-                            }
-                        },
-                        {
-                            "name": "send_message",
-                            "description": "Send a message to a session",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "session_id": {
-                                        "type": "string",
-                                        "description": "Session ID"
-                                    },
-                                    "content": {
-                                        "type": "string",
-                                        "description": "Message content"
-                                    }
-                                },
-                                "required": ["session_id", "content"]
-                            }
-                        },
-                        {
-                            "name": "get_messages",
-                            "description": "Get messages from a session",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "session_id": {
-                                        "type": "string",
-                                        "description": "Session ID"
-                                    },
-                                    "limit": {
-                                        "type": "number",
-                                        "description": "Maximum number of messages to retrieve"
-                                    }
-                                },
-                                "required": ["session_id"]
-                            }
-                        },
-                        {
-                            "name": "pause_session",
-                            "description": "Pause a session",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "session_id": {
-                                        "type": "string",
-                                        "description": "Session ID"
-                                    }
-                                },
-                                "required": ["session_id"]
-                            }
-                        },
-                        {
-                            "name": "resume_session",
-                            "description": "Resume a session",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "session_id": {
-                                        "type": "string",
-                                        "description": "Session ID"
-                                    }
-                                },
-                                "required": ["session_id"]
-                            }
-                        },
-                        {
-                            "name": "terminate_session",
-                            "description": "Terminate a session",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "session_id": {
-                                        "type": "string",
-                                        "description": "Session ID"
-                                    }
-                                },
-                                "required": ["session_id"]
-                            }
-                        },
-                        {
-                            "name": "list_agents",
-                            "description": "List agents in a space",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "space": {
-                                        "type": "string",
-                                        "description": "Space name (optional, uses default if not provided)"
-                                    }
-                                }
-                            }
-                        },
-                        {
-                            "name": "get_agent_logs",
-                            "description": "Get logs for an agent",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "space": {
-                                        "type": "string",
-                                        "description": "Space name"
-                                    },
-                                    "agent_name": {
-                                        "type": "string",
-                                        "description": "Agent name"
-                                    }
-                                },
-                                "required": ["space", "agent_name"]
-                            }
-                        },
-                        {
-                            "name": "list_secrets",
-                            "description": "List secrets in a space",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "space": {
-                                        "type": "string",
-                                        "description": "Space name (optional, uses default if not provided)"
-                                    }
-                                }
-                            }
-                        },
-                        {
-                            "name": "get_secret",
-                            "description": "Get a secret value",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "space": {
-                                        "type": "string",
-                                        "description": "Space name"
-                                    },
-                                    "key": {
-                                        "type": "string",
-                                        "description": "Secret key"
-                                    }
-                                },
-                                "required": ["space", "key"]
-                            }
-                        },
-                        {
-                            "name": "set_secret",
-                            "description": "Set a secret value",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "space": {
-                                        "type": "string",
-                                        "description": "Space name"
-                                    },
-                                    "key": {
-                                        "type": "string",
-                                        "description": "Secret key"
-                                    },
-                                    "value": {
-                                        "type": "string",
-                                        "description": "Secret value"
-                                    }
-                                },
-                                "required": ["space", "key", "value"]
-                            }
-                        },
-                        {
-                            "name": "delete_secret",
-                            "description": "Delete a secret",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "space": {
-                                        "type": "string",
-                                        "description": "Space name"
-                                    },
-                                    "key": {
-                                        "type": "string",
-                                        "description": "Secret key"
-                                    }
-                                },
-                                "required": ["space", "key"]
-                            }
-                        },
-                        {
-                            "name": "get_version",
-                            "description": "Get API version",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {}
-                            }
-                        }
-                    ]
-                });
+                let tools = server.filter_tools(tool_list());
                 if let Some(id) = id {
                     write_json(&mut stdout, json!({"jsonrpc":"2.0","id":id,"result":tools})).await?;
                 }
@@ -327,8 +272,33 @@ async fn main() -> Result<()> {
             Some("tools/call") => {
                 let name = msg.pointer("/params/name").and_then(Value::as_str).unwrap_or("");
                 let args = msg.pointer("/params/arguments").cloned().unwrap_or_else(|| json!({}));
-                
-                match server.handle_tool_call(name, &args).await {
+                let progress_token = msg.pointer("/params/_meta/progressToken").cloned();
+
+                // Flush progress notifications while the call is still running
+                let call = server.handle_tool_call_with_progress(name, &args, progress_token);
+                tokio::pin!(call);
+                let result = loop {
+                    tokio::select! {
+                        result = &mut call => break result,
+                        Some(note) = progress_rx.recv() => write_json(&mut stdout, note).await?,
+                        // Keep reading so elicitation responses reach the waiting call
+                        line = stdin.next_line(), if !stdin_closed => match line? {
+                            Some(line) => {
+                                let answered = serde_json::from_str::<Value>(&line)
+                                    .is_ok_and(|msg| elicitor.resolve(&msg));
+                                if !answered {
+                                    deferred.push_back(line);
+                                }
+                            }
+                            None => stdin_closed = true,
+                        },
+                    }
+                };
+                while let Ok(note) = progress_rx.try_recv() {
+                    write_json(&mut stdout, note).await?;
+                }
+
+                match result {
                     Ok(response) => {
                         write_json(&mut stdout, json!({
                             "jsonrpc": "2.0",
@@ -369,4 +339,3 @@ async fn write_json(stdout: &mut tokio::io::Stdout, v: Value) -> Result<()> {
     stdout.flush().await?;
     Ok(())
 }
-
@@ -1,75 +1,854 @@
-use crate::client::RaworcClient;
+use crate::api::RaworcApi;
+use crate::audit::{AuditLog, AuditQuery, AuditRecord};
+use crate::metrics::metrics;
+use crate::policy::{filter_tool_list, glob_match, is_mutating, resolve_spaces, space_target, PolicyAction, PolicyDecision, SpaceTarget};
+use crate::redact::{redact_arguments, redact_result};
+use crate::client::{filter_log_lines, line_timestamp, RaworcClient};
 use crate::error::{RaworcError, RaworcResult};
+use crate::hooks::{FailurePolicy, HookEvent, HookOutcome};
 use crate::models::*; // ensure ToolCallContent has #[serde(rename = "type")] on content_type
 use crate::Config;
-use serde_json::{self, Value};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{self, json, Value};
 use std::collections::HashMap;
-use tracing::{debug, info};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+use tracing::{debug, info, info_span, warn, Instrument};
+
+/// Default upper bound for tools that block until something happens upstream
+const DEFAULT_WAIT_TIMEOUT_SECS: u64 = 120;
+
+/// Log lines returned when a build fails
+const BUILD_LOG_TAIL_LINES: usize = 50;
+
+/// How long the host gets to answer an elicitation request before it counts as declined
+const ELICITATION_TIMEOUT_SECS: u64 = 120;
+
+/// Character budget for one tool result unless config or `max_chars` says otherwise
+const DEFAULT_MAX_OUTPUT_CHARS: usize = 100_000;
+
+/// Share of the budget given to the head of a truncated result; the rest goes to the tail
+const OUTPUT_HEAD_SHARE: f64 = 0.7;
+
+/// Entries `audit_log` returns unless `limit` says otherwise
+const DEFAULT_AUDIT_LIMIT: usize = 20;
+
+/// MCP protocol versions `initialize` accepts, newest first. The newest is
+/// also the answer to a client asking for one we do not know, and the first
+/// to define elicitation.
+pub const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Tools `run_tool` dispatches; their schemas come from `crate::CAPABILITIES`
+pub const SUPPORTED_TOOLS: &[&str] = &[
+    "health_check",
+    "get_version",
+    "list_service_accounts",
+    "create_service_account",
+    "get_service_account",
+    "update_service_account",
+    "delete_service_account",
+    "update_service_account_password",
+    "list_roles",
+    "create_role",
+    "get_role",
+    "delete_role",
+    "list_role_bindings",
+    "create_role_binding",
+    "get_role_binding",
+    "delete_role_binding",
+    "list_spaces",
+    "create_space",
+    "get_space",
+    "update_space",
+    "delete_space",
+    "list_sessions",
+    "create_session",
+    "get_session",
+    "update_session",
+    "update_session_state",
+    "close_session",
+    "restore_session",
+    "remix_session",
+    "send_message",
+    "ask_session",
+    "get_messages",
+    "get_message_count",
+    "clear_messages",
+    "pause_session",
+    "resume_session",
+    "terminate_session",
+    "wait_session",
+    "list_agents",
+    "create_agent",
+    "get_agent",
+    "update_agent",
+    "delete_agent",
+    "update_agent_status",
+    "deploy_agent",
+    "stop_agent",
+    "list_running_agents",
+    "get_agent_logs",
+    "list_secrets",
+    "create_secret",
+    "get_secret",
+    "set_secret",
+    "update_secret",
+    "delete_secret",
+    "create_build",
+    "get_latest_build",
+    "get_build",
+    "wait_for_build",
+    "audit_log",
+];
+
+/// Arguments naming the resource a call acts on, in order of preference
+const RESOURCE_ARGUMENTS: &[&str] = &["session_id", "agent_name", "key", "build_id", "id", "name", "user"];
+
+/// How tool results are rendered. Set globally with `output_format` in `Config`
+/// or per call with the `format` argument.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    /// Pretty-printed JSON
+    #[default]
+    Json,
+    /// Single-line JSON
+    CompactJson,
+    /// Tables for lists, a field/value table for objects, a transcript for messages
+    Markdown,
+    Yaml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = RaworcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "compact" | "compact_json" => Ok(Self::CompactJson),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            _ => Err(RaworcError::validation_error(
+                "format must be one of json, compact_json, markdown, yaml",
+            )),
+        }
+    }
+}
+
+/// Columns shown when a list of this model is rendered as a Markdown table
+pub trait TableColumns {
+    const COLUMNS: &'static [&'static str];
+}
+
+impl TableColumns for Session {
+    const COLUMNS: &'static [&'static str] = &["id", "state", "created_by", "created_at", "last_activity_at"];
+}
+
+impl TableColumns for Agent {
+    const COLUMNS: &'static [&'static str] = &["name", "status", "description", "source_repo", "updated_at"];
+}
+
+impl TableColumns for RunningAgent {
+    const COLUMNS: &'static [&'static str] = &["name", "status", "started_at"];
+}
+
+impl TableColumns for Secret {
+    const COLUMNS: &'static [&'static str] = &["key_name", "description", "created_by", "updated_at"];
+}
+
+impl TableColumns for Space {
+    const COLUMNS: &'static [&'static str] = &["name", "description", "active", "updated_at"];
+}
+
+impl TableColumns for RoleBinding {
+    const COLUMNS: &'static [&'static str] = &["id", "subject", "role_ref", "space", "created_at"];
+}
+
+/// Emits MCP `notifications/progress` for the tool call in flight.
+/// Silent unless the transport installed a sender and the host passed a progress token.
+#[derive(Clone, Default)]
+pub struct ProgressReporter {
+    sender: Option<UnboundedSender<Value>>,
+    token: Option<Value>,
+}
+
+impl ProgressReporter {
+    pub fn report(&self, progress: f64, total: Option<f64>, message: &str) {
+        let (Some(sender), Some(token)) = (&self.sender, &self.token) else {
+            return;
+        };
+        let mut params = json!({
+            "progressToken": token,
+            "progress": progress,
+            "message": message,
+        });
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        let _ = sender.send(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": params,
+        }));
+    }
+}
+
+/// Asks the host to confirm an action through MCP elicitation (`elicitation/create`).
+/// Declines unless the transport installed a sender and the host advertised the
+/// `elicitation` capability. The transport hands responses back with `resolve`.
+#[derive(Clone, Default)]
+pub struct Elicitor {
+    sender: Option<UnboundedSender<Value>>,
+    supported: bool,
+    pending: Arc<Mutex<HashMap<String, oneshot::Sender<Value>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Elicitor {
+    /// Whether `confirm` can reach the host at all
+    pub fn available(&self) -> bool {
+        self.sender.is_some() && self.supported
+    }
+
+    /// Ask a yes/no question; true only when the host accepts and confirms
+    pub async fn confirm(&self, message: &str) -> bool {
+        let (Some(sender), true) = (&self.sender, self.supported) else {
+            return false;
+        };
+        let id = format!("elicit-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let (tx, rx) = oneshot::channel();
+        self.lock().insert(id.clone(), tx);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "elicitation/create",
+            "params": {
+                "message": message,
+                "requestedSchema": {
+                    "type": "object",
+                    "properties": {
+                        "confirm": {"type": "boolean", "title": "Confirm"}
+                    },
+                    "required": ["confirm"]
+                }
+            }
+        });
+        if sender.send(request).is_err() {
+            self.lock().remove(&id);
+            return false;
+        }
+        let response = match tokio::time::timeout(Duration::from_secs(ELICITATION_TIMEOUT_SECS), rx).await {
+            Ok(Ok(response)) => response,
+            _ => {
+                self.lock().remove(&id);
+                return false;
+            }
+        };
+        response.pointer("/result/action").and_then(Value::as_str) == Some("accept")
+            && response.pointer("/result/content/confirm").and_then(Value::as_bool) == Some(true)
+    }
+
+    /// Deliver a JSON-RPC response from the host; false if it answers no pending request
+    pub fn resolve(&self, response: &Value) -> bool {
+        let Some(id) = response.get("id").and_then(Value::as_str) else {
+            return false;
+        };
+        match self.lock().remove(id) {
+            Some(tx) => {
+                let _ = tx.send(response.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, oneshot::Sender<Value>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Where `get_agent_logs` in follow mode left off for one agent
+#[derive(Debug, Clone, Default)]
+struct LogCursor {
+    /// Timestamp of the last timestamped line seen, sent as `since` next time
+    since: Option<DateTime<Utc>>,
+    /// Lines from `since` on that were already handed back
+    seen: usize,
+    /// The last of those lines, to tell a grown log from a rotated one
+    last_line: Option<String>,
+}
+
+impl LogCursor {
+    /// Index of the first new line in `window`. A window that does not start
+    /// with the lines already seen, ending in `last_line`, comes from a rotated
+    /// log, so all of it is new.
+    fn resume_at(&self, window: &[String]) -> usize {
+        match (self.seen, &self.last_line) {
+            (seen @ 1.., Some(last)) if window.get(seen - 1) == Some(last) => seen,
+            _ => 0,
+        }
+    }
+
+    /// Cursor after handing back `window`, fetched from `since`
+    fn after(window: Vec<String>, since: Option<DateTime<Utc>>) -> RaworcResult<Self> {
+        let since = window.iter().rev().find_map(|line| line_timestamp(line)).or(since);
+        let last_line = window.last().cloned();
+        // The part of this window that the next request, from `since`, returns again
+        let seen = filter_log_lines(window, &AgentLogsQuery { since, ..AgentLogsQuery::default() })?.len();
+        Ok(Self { since, seen, last_line })
+    }
+}
 
-/// Raworc MCP Server
-pub struct RaworcMcpServer {
-    client: RaworcClient,
+/// Raworc MCP Server, generic over the backend it talks to
+pub struct RaworcMcpServer<C = RaworcClient> {
+    client: C,
     config: Config,
+    progress: ProgressReporter,
+    elicitor: Elicitor,
+    /// Where `get_agent_logs` in follow mode left off, per `space/agent`
+    log_cursors: Mutex<HashMap<String, LogCursor>>,
+    /// Caller identity, looked up once for hooks and the audit log
+    identity: Mutex<Option<Value>>,
+    /// Audit log of mutating calls, if `Config::audit` is set
+    audit: Option<AuditLog>,
+    /// `clientInfo.name` from `initialize`
+    client_name: Option<String>,
 }
 
 impl RaworcMcpServer {
     /// Create a new MCP server
     pub fn new(config: Config) -> RaworcResult<Self> {
         let client = RaworcClient::new(&config)?;
-        Ok(Self { client, config })
+        Ok(Self::with_client(client, config))
+    }
+}
+
+impl<C: RaworcApi> RaworcMcpServer<C> {
+    /// Create an MCP server over any `RaworcApi` backend
+    pub fn with_client(client: C, config: Config) -> Self {
+        let audit = config.audit.clone().map(AuditLog::new);
+        Self {
+            client,
+            config,
+            progress: ProgressReporter::default(),
+            elicitor: Elicitor::default(),
+            log_cursors: Mutex::new(HashMap::new()),
+            identity: Mutex::new(None),
+            audit,
+            client_name: None,
+        }
+    }
+
+    /// Name the MCP client reported in `initialize`, for audit records
+    pub fn set_client_name(&mut self, name: impl Into<String>) {
+        self.client_name = Some(name.into());
+    }
+
+    /// Route progress notifications and elicitation requests to the transport;
+    /// they are written as-is
+    pub fn set_progress_sender(&mut self, sender: UnboundedSender<Value>) {
+        self.progress.sender = Some(sender.clone());
+        self.elicitor.sender = Some(sender);
+    }
+
+    /// Record whether the host advertised the `elicitation` capability in `initialize`
+    pub fn set_elicitation_supported(&mut self, supported: bool) {
+        self.elicitor.supported = supported;
+    }
+
+    /// Handle the transport uses to deliver elicitation responses
+    pub fn elicitor(&self) -> Elicitor {
+        self.elicitor.clone()
+    }
+
+    /// Answer an MCP `initialize` request: take the host's name and elicitation
+    /// capability, and agree on the protocol version it asked for if we speak
+    /// it, else our newest. Returns the JSON-RPC `result`.
+    pub fn handle_initialize(&mut self, params: &Value) -> Value {
+        self.set_elicitation_supported(params.pointer("/capabilities/elicitation").is_some());
+        if let Some(name) = params.pointer("/clientInfo/name").and_then(Value::as_str) {
+            self.set_client_name(name);
+        }
+        let requested = params.get("protocolVersion").and_then(Value::as_str);
+        let version = PROTOCOL_VERSIONS
+            .iter()
+            .find(|v| Some(**v) == requested)
+            .unwrap_or(&PROTOCOL_VERSIONS[0]);
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": {} },
+            "serverInfo": {
+                "name": "raworc-mcp",
+                "version": env!("CARGO_PKG_VERSION")
+            }
+        })
     }
 
     /// Initialize (authenticate lazily if user/pass provided and no token)
     pub async fn initialize(&mut self) -> RaworcResult<()> {
-        if self.config.username.is_some()
-            && self.config.password.is_some()
-            && self.config.auth_token.is_none()
+        if let (Some(username), Some(password), None) =
+            (&self.config.username, &self.config.password, &self.config.auth_token)
         {
-            let username = self.config.username.as_ref().unwrap();
-            let password = self.config.password.as_ref().unwrap();
             info!("Authenticating as service account");
-            self.client.authenticate(username, password).await?;
+            self.client.authenticate(username, password.expose()).await?;
             info!("Authentication successful");
         }
         Ok(())
     }
 
-    /// Dispatch a tool call by name
+    /// Dispatch a tool call, reporting progress against the host's `progressToken` if any
+    pub async fn handle_tool_call_with_progress(
+        &mut self,
+        name: &str,
+        arguments: &Value,
+        progress_token: Option<Value>,
+    ) -> RaworcResult<ToolCallResponse> {
+        self.progress.token = progress_token;
+        let result = self.handle_tool_call(name, arguments).await;
+        self.progress.token = None;
+        result
+    }
+
+    /// Dispatch a tool call by name, inside an `mcp.tool_call` span carrying the
+    /// tool name, redacted arguments and outcome
     pub async fn handle_tool_call(
         &mut self,
         name: &str,
         arguments: &Value
     ) -> RaworcResult<ToolCallResponse> {
-        debug!("Tool call: {name} args={arguments:?}");
+        let span = info_span!(
+            "mcp.tool_call",
+            tool = name,
+            arguments = %redact_arguments(name, arguments),
+            outcome = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        // Drop any request id left over from an earlier call
+        self.client.take_request_id();
+        let mut spaces = Vec::new();
+        let result = self.dispatch_tool_call(name, arguments, &mut spaces).instrument(span.clone()).await;
+        let label = if SUPPORTED_TOOLS.contains(&name) { name } else { "unknown" };
+        metrics().observe_tool_call(label, result.is_ok(), started.elapsed());
+        self.record_audit(name, arguments, spaces.first().map(String::as_str), &result).await;
+        match &result {
+            Ok(_) => span.record("outcome", "ok"),
+            Err(e) => span.record("outcome", "error").record("error", e.to_string()),
+        };
+        result
+    }
+
+    /// Run a tool call, leaving the spaces it resolved to in `spaces` for the audit record
+    async fn dispatch_tool_call(
+        &mut self,
+        name: &str,
+        arguments: &Value,
+        spaces: &mut Vec<String>,
+    ) -> RaworcResult<ToolCallResponse> {
+        debug!("Tool call: {name}");
+        self.check_tool_enabled(name)?;
 
         // Lazy auth only when needed
         self.initialize().await?;
+        *spaces = self.call_spaces(name, arguments).await?;
+        self.check_space_allowed(spaces)?;
+        let planned = self.is_dry_run(name, arguments);
+        let decision = self.check_policy(name, arguments, spaces, planned).await?;
+
+        let content = if planned {
+            self.plan_tool(name, arguments, decision).await?
+        } else {
+            self.run_pre_hooks(name, arguments).await?;
+            let result = self.run_tool(name, arguments).await;
+            self.run_post_hooks(name, arguments, result).await?
+        };
+
+        let max_chars = arguments
+            .get("max_chars")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .or(self.config.max_output_chars)
+            .unwrap_or(DEFAULT_MAX_OUTPUT_CHARS);
+        let cursor = arguments.get("cursor").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+        Ok(ToolCallResponse { content: budget_content(content, max_chars, cursor) })
+    }
 
-        let content = match name {
-            "list_sessions"   => self.handle_list_sessions(arguments).await?,
-            "create_session"  => self.handle_create_session(arguments).await?,
-            "get_session"     => self.handle_get_session(arguments).await?,
-            "send_message"    => self.handle_send_message(arguments).await?,
-            "get_messages"    => self.handle_get_messages(arguments).await?,
-            "pause_session"   => self.handle_pause_session(arguments).await?,
-            "resume_session"  => self.handle_resume_session(arguments).await?,
-            "terminate_session" => self.handle_terminate_session(arguments).await?,
-            "list_spaces"     => self.handle_list_spaces(arguments).await?,
-            "list_agents"     => self.handle_list_agents(arguments).await?,
-            "get_agent_logs"  => self.handle_get_agent_logs(arguments).await?,
-            "list_secrets"    => self.handle_list_secrets(arguments).await?,
-            "get_secret"      => self.handle_get_secret(arguments).await?,
-            "set_secret"      => self.handle_set_secret(arguments).await?,
-            "delete_secret"   => self.handle_delete_secret(arguments).await?,
-            "health_check"    => self.handle_health_check(arguments).await?,
-            "get_version"     => self.handle_get_version(arguments).await?,
-            _ => return Err(RaworcError::mcp_error(&format!("Unknown tool: {name}")))
-        };
-
-        Ok(ToolCallResponse { content })
+    /// Whether this call of a mutating tool should only be planned
+    fn is_dry_run(&self, name: &str, arguments: &Value) -> bool {
+        is_mutating(name)
+            && (self.config.dry_run || arguments.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false))
+    }
+
+    /// Run the handler with the client planning writes instead of sending them,
+    /// and describe the first write next to the target's current state and the
+    /// policy decision a real call would get. Hooks do not run, since nothing
+    /// changes. The handler stops at its first write, so steps that depend on
+    /// that write's response (e.g. `ask_session` waiting for the reply) are not
+    /// part of the plan.
+    async fn plan_tool(
+        &mut self,
+        name: &str,
+        arguments: &Value,
+        decision: Option<PolicyDecision>,
+    ) -> RaworcResult<Vec<ToolCallContent>> {
+        if !self.client.set_dry_run(true) {
+            return Err(RaworcError::validation_error("dry_run is not supported by this backend"));
+        }
+        let result = self.run_tool(name, arguments).await;
+        self.client.set_dry_run(false);
+        let request = match result {
+            Err(RaworcError::DryRun(request)) => Some(*request),
+            // The handler stopped before writing anything
+            Ok(_) => None,
+            Err(e) => return Err(e),
+        };
+        let plan = json!({
+            "dry_run": true,
+            "tool": name,
+            "request": request,
+            "current": self.current_state(name, arguments).await,
+            "policy": decision.map(|d| json!({ "action": d.action.as_str(), "rule": d.rule, "reason": d.reason })),
+        });
+        self.render(arguments, &plan)
+    }
+
+    /// What a mutating call would change, fetched with the matching `get_*`;
+    /// `null` when there is no such call or the target does not exist yet
+    async fn current_state(&self, name: &str, arguments: &Value) -> Value {
+        fn to_json<T: Serialize>(result: RaworcResult<T>) -> RaworcResult<Value> {
+            Ok(serde_json::to_value(result?)?)
+        }
+        let arg = |key: &str| arguments.get(key).and_then(|v| v.as_str());
+        let space = arg("space").unwrap_or(self.config.space_or_default());
+        let state = match (name, arg("session_id"), arg("agent_name"), arg("key")) {
+            ("close_session" | "restore_session" | "remix_session", Some(id), _, _) => {
+                to_json(self.client.get_global_session(id).await)
+            }
+            (_, Some(id), _, _) => to_json(self.client.get_session(arg("space"), id).await),
+            (_, None, Some(agent), _) => to_json(self.client.get_agent(space, agent).await),
+            (_, None, None, Some(key)) => to_json(self.client.get_secret(space, key).await.map(|mut secret| {
+                if !self.config.reveals_secrets(space) {
+                    mask_secret(&mut secret);
+                }
+                secret
+            })),
+            ("update_space" | "delete_space", None, None, None) => match arg("name") {
+                Some(space) => to_json(self.client.get_space(space).await),
+                None => return Value::Null,
+            },
+            _ => return Value::Null,
+        };
+        state.unwrap_or_else(|e| {
+            debug!(tool = name, error = %e, "No current state for dry run");
+            Value::Null
+        })
+    }
+
+    /// Run the handler for `name`
+    async fn run_tool(&mut self, name: &str, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+        match name {
+            "health_check"                    => self.handle_health_check(arguments).await,
+            "get_version"                     => self.handle_get_version(arguments).await,
+            "list_service_accounts"           => self.handle_list_service_accounts(arguments).await,
+            "create_service_account"          => self.handle_create_service_account(arguments).await,
+            "get_service_account"             => self.handle_get_service_account(arguments).await,
+            "update_service_account"          => self.handle_update_service_account(arguments).await,
+            "delete_service_account"          => self.handle_delete_service_account(arguments).await,
+            "update_service_account_password" => self.handle_update_service_account_password(arguments).await,
+            "list_roles"                      => self.handle_list_roles(arguments).await,
+            "create_role"                     => self.handle_create_role(arguments).await,
+            "get_role"                        => self.handle_get_role(arguments).await,
+            "delete_role"                     => self.handle_delete_role(arguments).await,
+            "list_role_bindings"              => self.handle_list_role_bindings(arguments).await,
+            "create_role_binding"             => self.handle_create_role_binding(arguments).await,
+            "get_role_binding"                => self.handle_get_role_binding(arguments).await,
+            "delete_role_binding"             => self.handle_delete_role_binding(arguments).await,
+            "list_spaces"                     => self.handle_list_spaces(arguments).await,
+            "create_space"                    => self.handle_create_space(arguments).await,
+            "get_space"                       => self.handle_get_space(arguments).await,
+            "update_space"                    => self.handle_update_space(arguments).await,
+            "delete_space"                    => self.handle_delete_space(arguments).await,
+            "list_sessions"                   => self.handle_list_sessions(arguments).await,
+            "create_session"                  => self.handle_create_session(arguments).await,
+            "get_session"                     => self.handle_get_session(arguments).await,
+            "update_session"                  => self.handle_update_session(arguments).await,
+            "update_session_state"            => self.handle_update_session_state(arguments).await,
+            "close_session"                   => self.handle_close_session(arguments).await,
+            "restore_session"                 => self.handle_restore_session(arguments).await,
+            "remix_session"                   => self.handle_remix_session(arguments).await,
+            "send_message"                    => self.handle_send_message(arguments).await,
+            "ask_session"                     => self.handle_ask_session(arguments).await,
+            "get_messages"                    => self.handle_get_messages(arguments).await,
+            "get_message_count"               => self.handle_get_message_count(arguments).await,
+            "clear_messages"                  => self.handle_clear_messages(arguments).await,
+            "pause_session"                   => self.handle_pause_session(arguments).await,
+            "resume_session"                  => self.handle_resume_session(arguments).await,
+            "terminate_session"               => self.handle_terminate_session(arguments).await,
+            "wait_session"                    => self.handle_wait_session(arguments).await,
+            "list_agents"                     => self.handle_list_agents(arguments).await,
+            "create_agent"                    => self.handle_create_agent(arguments).await,
+            "get_agent"                       => self.handle_get_agent(arguments).await,
+            "update_agent"                    => self.handle_update_agent(arguments).await,
+            "delete_agent"                    => self.handle_delete_agent(arguments).await,
+            "update_agent_status"             => self.handle_update_agent_status(arguments).await,
+            "deploy_agent"                    => self.handle_deploy_agent(arguments).await,
+            "stop_agent"                      => self.handle_stop_agent(arguments).await,
+            "list_running_agents"             => self.handle_list_running_agents(arguments).await,
+            "get_agent_logs"                  => self.handle_get_agent_logs(arguments).await,
+            "list_secrets"                    => self.handle_list_secrets(arguments).await,
+            "create_secret"                   => self.handle_create_secret(arguments).await,
+            "get_secret"                      => self.handle_get_secret(arguments).await,
+            "set_secret"                      => self.handle_set_secret(arguments).await,
+            "update_secret"                   => self.handle_update_secret(arguments).await,
+            "delete_secret"                   => self.handle_delete_secret(arguments).await,
+            "create_build"                    => self.handle_create_build(arguments).await,
+            "get_latest_build"                => self.handle_get_latest_build(arguments).await,
+            "get_build"                       => self.handle_get_build(arguments).await,
+            "wait_for_build"                  => self.handle_wait_for_build(arguments).await,
+            "audit_log"                       => self.handle_audit_log(arguments).await,
+            _ => Err(RaworcError::mcp_error(&format!("Unknown tool: {name}")))
+        }
+    }
+
+    /// Keep only the tools this configuration exposes in a `tools/list` payload
+    pub fn filter_tools(&self, tools: Value) -> Value {
+        filter_tool_list(tools, |name| self.config.tool_enabled(name))
     }
 
     // ---------- Helpers ----------
+    /// Refuse tools `Config::tool_enabled` leaves out of `tools/list`
+    fn check_tool_enabled(&self, name: &str) -> RaworcResult<()> {
+        if self.config.tool_enabled(name) {
+            return Ok(());
+        }
+        // The decision is `tool_enabled`'s; this only says which setting made it
+        let reason = if self.config.read_only && is_mutating(name) {
+            "changes state and the server is read-only"
+        } else {
+            "is not enabled by tools.allow/tools.deny"
+        };
+        Err(RaworcError::policy_error(&format!("{} {}", name, reason)))
+    }
+
+    /// The spaces a call acts on (see `resolve_spaces`), looked up only when
+    /// `allowed_spaces`, a policy or the audit log needs them
+    async fn call_spaces(&self, name: &str, arguments: &Value) -> RaworcResult<Vec<String>> {
+        let audited = self.audit.is_some() && is_mutating(name);
+        if self.config.allowed_spaces.is_empty() && self.config.policy.is_none() && !audited {
+            return Ok(Vec::new());
+        }
+        resolve_spaces(&self.client, name, arguments, self.config.space_or_default()).await
+    }
+
+    /// Refuse calls that would act on a space outside `allowed_spaces`
+    fn check_space_allowed(&self, spaces: &[String]) -> RaworcResult<()> {
+        if self.config.allowed_spaces.is_empty() {
+            return Ok(());
+        }
+        match spaces.iter().find(|space| !self.config.space_allowed(space)) {
+            None => Ok(()),
+            Some(space) => Err(RaworcError::policy_error(&format!(
+                "space '{}' is not in allowed_spaces ({})",
+                space,
+                self.config.allowed_spaces.join(", ")
+            ))),
+        }
+    }
+
+    /// Apply the first matching policy rule; `require_confirmation` asks the host
+    /// unless the call is only `planned`. Returns the decision, if a policy is set.
+    async fn check_policy(
+        &self,
+        name: &str,
+        arguments: &Value,
+        spaces: &[String],
+        planned: bool,
+    ) -> RaworcResult<Option<PolicyDecision>> {
+        let Some(policy) = &self.config.policy else {
+            return Ok(None);
+        };
+        let decision = policy.evaluate_in(name, arguments, spaces);
+        if decision.rule.is_some() {
+            info!(tool = name, decision = %decision, "Policy decision");
+        } else {
+            debug!(tool = name, decision = %decision, "Policy decision");
+        }
+        match decision.action {
+            PolicyAction::Allow => Ok(Some(decision)),
+            PolicyAction::Deny => Err(RaworcError::policy_error(&format!("{}: {}", name, decision))),
+            // Nothing is sent, so there is nothing to confirm; the plan shows the decision
+            PolicyAction::RequireConfirmation if planned => Ok(Some(decision)),
+            PolicyAction::RequireConfirmation => {
+                let message = format!(
+                    "Allow {}? {}",
+                    name,
+                    decision.reason.as_deref().unwrap_or("A policy rule asks for confirmation.")
+                );
+                // Say why, so a refusal here is not mistaken for a `deny` rule
+                if !self.elicitor.available() {
+                    Err(RaworcError::policy_error(&format!(
+                        "{}: not confirmed, confirmation is unavailable because the host does not support MCP elicitation ({})",
+                        name, decision
+                    )))
+                } else if self.elicitor.confirm(&message).await {
+                    info!(tool = name, "Policy confirmation given");
+                    Ok(Some(decision))
+                } else {
+                    Err(RaworcError::policy_error(&format!(
+                        "{}: not confirmed, the user declined or did not answer ({})",
+                        name, decision
+                    )))
+                }
+            }
+        }
+    }
+
+    /// The authenticated user as JSON, `null` when it cannot be looked up
+    async fn identity(&self) -> Value {
+        if let Some(identity) = self.identity.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+            return identity;
+        }
+        let identity = match self.client.get_user_info().await {
+            Ok(user) => serde_json::to_value(user).unwrap_or(Value::Null),
+            Err(e) => {
+                debug!(error = %e, "Identity lookup failed");
+                return Value::Null;
+            }
+        };
+        *self.identity.lock().unwrap_or_else(|e| e.into_inner()) = Some(identity.clone());
+        identity
+    }
+
+    /// Append an audit record for a mutating call, under the first space it
+    /// resolved to; a failed write is logged, not returned
+    async fn record_audit(
+        &self,
+        name: &str,
+        arguments: &Value,
+        space: Option<&str>,
+        result: &RaworcResult<ToolCallResponse>,
+    ) {
+        let Some(audit) = &self.audit else {
+            return;
+        };
+        if !is_mutating(name) || self.is_dry_run(name, arguments) {
+            return;
+        }
+        let request_id = self.client.take_request_id();
+        let (outcome, error, request_id) = match result {
+            Ok(_) => ("ok", None, request_id),
+            Err(e @ RaworcError::PolicyError(_)) => ("denied", Some(e.to_string()), None),
+            Err(e) => ("error", Some(e.to_string()), e.request_id().map(str::to_string).or(request_id)),
+        };
+        // A call refused before its space was resolved still names the space it asked for
+        let space = space.map(str::to_string).or_else(|| match space_target(name, arguments) {
+            SpaceTarget::Named(space) => Some(space.to_string()),
+            SpaceTarget::Default => Some(self.config.space_or_default().to_string()),
+            SpaceTarget::None | SpaceTarget::Session { .. } => None,
+        });
+        let resource = RESOURCE_ARGUMENTS
+            .iter()
+            .find_map(|key| arguments.get(*key).filter(|v| !v.is_null()))
+            .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string));
+        let record = AuditRecord {
+            timestamp: Utc::now(),
+            identity: self.identity().await,
+            client: self.client_name.clone(),
+            tool: name.to_string(),
+            arguments: redact_arguments(name, arguments),
+            space,
+            resource,
+            outcome: outcome.to_string(),
+            error,
+            request_id,
+            prev_hash: None,
+            hash: None,
+        };
+        if let Err(e) = audit.append(record) {
+            warn!(tool = name, error = %e, "Audit record not written");
+        }
+    }
+
+    /// Run the `pre` hooks matching `name`; a veto or a fail-closed failure refuses the call
+    async fn run_pre_hooks(&self, name: &str, arguments: &Value) -> RaworcResult<()> {
+        if !self.config.hooks.iter().any(|h| h.event == HookEvent::Pre && glob_match(&h.tool, name)) {
+            return Ok(());
+        }
+        let input = json!({
+            "event": HookEvent::Pre.as_str(),
+            "tool": name,
+            "arguments": redact_arguments(name, arguments),
+            "identity": self.identity().await,
+        });
+        for hook in self.config.hooks.iter().filter(|h| h.event == HookEvent::Pre && glob_match(&h.tool, name)) {
+            match hook.run(&input).await {
+                Ok(HookOutcome::Continue) => {}
+                Ok(HookOutcome::Veto(reason)) => {
+                    info!(tool = name, hook = ?hook.command, reason = %reason, "Pre hook vetoed call");
+                    return Err(RaworcError::policy_error(&format!("{} vetoed by pre hook: {}", name, reason)));
+                }
+                Err(e) => Self::hook_failed(name, hook.event, hook.on_failure, &e)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the `post` hooks matching `name` with the call's result. A fail-closed
+    /// failure turns the result into an error; the call itself has already run.
+    async fn run_post_hooks(
+        &self,
+        name: &str,
+        arguments: &Value,
+        result: RaworcResult<Vec<ToolCallContent>>,
+    ) -> RaworcResult<Vec<ToolCallContent>> {
+        if !self.config.hooks.iter().any(|h| h.event == HookEvent::Post && glob_match(&h.tool, name)) {
+            return result;
+        }
+        let outcome = match &result {
+            Ok(content) => {
+                let text: Vec<String> = content
+                    .iter()
+                    .filter_map(|c| c.text.as_deref())
+                    .map(|text| redact_result(name, text))
+                    .collect();
+                json!({ "ok": true, "content": text.join("\n") })
+            }
+            Err(e) => json!({ "ok": false, "error": e.to_string() }),
+        };
+        let input = json!({
+            "event": HookEvent::Post.as_str(),
+            "tool": name,
+            "arguments": redact_arguments(name, arguments),
+            "identity": self.identity().await,
+            "result": outcome,
+        });
+        for hook in self.config.hooks.iter().filter(|h| h.event == HookEvent::Post && glob_match(&h.tool, name)) {
+            if let Err(e) = hook.run(&input).await {
+                Self::hook_failed(name, hook.event, hook.on_failure, &e)?;
+            }
+        }
+        result
+    }
+
+    fn hook_failed(name: &str, event: HookEvent, on_failure: FailurePolicy, error: &str) -> RaworcResult<()> {
+        match on_failure {
+            FailurePolicy::Open => {
+                warn!(tool = name, event = event.as_str(), error = %error, "Hook failed; continuing");
+                Ok(())
+            }
+            FailurePolicy::Closed => Err(RaworcError::policy_error(&format!(
+                "{} {} hook failed: {}",
+                name,
+                event.as_str(),
+                error
+            ))),
+        }
+    }
+
     #[inline]
     fn text_content<S: Into<String>>(s: S) -> Vec<ToolCallContent> {
         vec![ToolCallContent {
@@ -79,12 +858,100 @@ impl RaworcMcpServer {
         }]
     }
 
+    fn parse_session_state(state: &str) -> RaworcResult<SessionState> {
+        match state.to_uppercase().as_str() {
+            "INIT" => Ok(SessionState::Init),
+            "RUNNING" => Ok(SessionState::Running),
+            "PAUSED" => Ok(SessionState::Paused),
+            "SUSPENDED" => Ok(SessionState::Suspended),
+            "TERMINATED" => Ok(SessionState::Terminated),
+            "IDLE" => Ok(SessionState::Idle),
+            "CLOSED" => Ok(SessionState::Closed),
+            _ => Err(RaworcError::validation_error("Invalid session state")),
+        }
+    }
+
+    /// `since` accepts an RFC 3339 timestamp or a relative age such as `30s`, `15m`, `2h`, `1d`
+    fn parse_since(since: &str) -> RaworcResult<DateTime<Utc>> {
+        if let Ok(ts) = DateTime::parse_from_rfc3339(since) {
+            return Ok(ts.with_timezone(&Utc));
+        }
+        let invalid = || RaworcError::validation_error("since must be RFC 3339 or an age like 15m");
+        // A negative age would be a time in the future and filter out every line
+        if since.starts_with('-') {
+            return Err(RaworcError::validation_error("since must not be a negative age"));
+        }
+        let (split, unit) = since.char_indices().last().ok_or_else(invalid)?;
+        let amount: i64 = since[..split].parse().map_err(|_| invalid())?;
+        let age = match unit {
+            's' => chrono::Duration::try_seconds(amount),
+            'm' => chrono::Duration::try_minutes(amount),
+            'h' => chrono::Duration::try_hours(amount),
+            'd' => chrono::Duration::try_days(amount),
+            _ => return Err(invalid()),
+        };
+        age.and_then(|age| Utc::now().checked_sub_signed(age)).ok_or_else(invalid)
+    }
+
+    /// Per-call `format` argument, falling back to the configured default
+    fn output_format(&self, arguments: &Value) -> RaworcResult<OutputFormat> {
+        match arguments.get("format").and_then(|v| v.as_str()) {
+            Some(format) => format.parse(),
+            None => Ok(self.config.output_format.unwrap_or_default()),
+        }
+    }
+
+    /// Render a result in the requested format
+    fn render<T: Serialize + ?Sized>(&self, arguments: &Value, value: &T) -> RaworcResult<Vec<ToolCallContent>> {
+        let format = self.output_format(arguments)?;
+        Ok(Self::text_content(render_value(format, serde_json::to_value(value)?, None)?))
+    }
+
+    /// Render a list; Markdown tables use `T::COLUMNS` unless the call passes `columns`
+    fn render_table<T: Serialize + TableColumns>(&self, arguments: &Value, items: &[T]) -> RaworcResult<Vec<ToolCallContent>> {
+        let format = self.output_format(arguments)?;
+        let columns: Vec<String> = match arguments.get("columns").and_then(|v| v.as_array()) {
+            Some(cols) => cols.iter().filter_map(|c| c.as_str()).map(|c| c.to_string()).collect(),
+            None => T::COLUMNS.iter().map(|c| c.to_string()).collect(),
+        };
+        Ok(Self::text_content(render_value(format, serde_json::to_value(items)?, Some(&columns))?))
+    }
+
+    /// Render messages; Markdown gives a chat transcript
+    fn render_messages(&self, arguments: &Value, messages: &[Message]) -> RaworcResult<Vec<ToolCallContent>> {
+        if self.output_format(arguments)? != OutputFormat::Markdown {
+            return self.render(arguments, messages);
+        }
+        let transcript = messages
+            .iter()
+            .map(|m| {
+                let role = match m.role {
+                    MessageRole::User => "user",
+                    MessageRole::Assistant => "assistant",
+                    MessageRole::System => "system",
+                };
+                format!("**{}** ({}):\n{}", role, m.created_at.to_rfc3339(), m.content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        Ok(Self::text_content(transcript))
+    }
+
+    /// `timeout_seconds` argument shared by the waiting tools
+    fn wait_timeout(arguments: &Value) -> Duration {
+        let secs = arguments
+            .get("timeout_seconds")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_WAIT_TIMEOUT_SECS);
+        Duration::from_secs(secs)
+    }
+
     // ---------- Tool handlers ----------
 
     async fn handle_list_sessions(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let space = arguments.get("space").and_then(|v| v.as_str());
         let sessions = self.client.list_sessions(space).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&sessions)?))
+        self.render_table(arguments, &sessions)
     }
 
     async fn handle_create_session(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -94,7 +961,7 @@ impl RaworcMcpServer {
             .and_then(|v| v.as_object())
             .map(|obj| obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<HashMap<String, Value>>());
         let session = self.client.create_session(space, metadata).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&session)?))
+        self.render(arguments, &session)
     }
 
     async fn handle_get_session(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -103,7 +970,7 @@ impl RaworcMcpServer {
             .ok_or_else(|| RaworcError::validation_error("session_id is required"))?;
         let space = arguments.get("space").and_then(|v| v.as_str());
         let session = self.client.get_session(space, session_id).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&session)?))
+        self.render(arguments, &session)
     }
 
     async fn handle_send_message(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -115,7 +982,29 @@ impl RaworcMcpServer {
             .ok_or_else(|| RaworcError::validation_error("content is required"))?;
         let space = arguments.get("space").and_then(|v| v.as_str());
         let message = self.client.send_message(space, session_id, content).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&message)?))
+        self.render(arguments, &message)
+    }
+
+    async fn handle_ask_session(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+        let session_id = arguments
+            .get("session_id").and_then(|v| v.as_str())
+            .ok_or_else(|| RaworcError::validation_error("session_id is required"))?;
+        let content = arguments
+            .get("content").and_then(|v| v.as_str())
+            .ok_or_else(|| RaworcError::validation_error("content is required"))?;
+        let space = arguments.get("space").and_then(|v| v.as_str());
+        let timeout = Self::wait_timeout(arguments);
+
+        let progress = self.progress.clone();
+        let total = timeout.as_secs_f64();
+        let on_progress = move |elapsed: Duration, status: &str| {
+            progress.report(elapsed.as_secs_f64(), Some(total), status)
+        };
+        let exchange = self
+            .client
+            .send_message_and_wait(space, session_id, content, timeout, &on_progress)
+            .await?;
+        self.render(arguments, &exchange)
     }
 
     async fn handle_get_messages(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -125,7 +1014,7 @@ impl RaworcMcpServer {
         let limit = arguments.get("limit").and_then(|v| v.as_u64());
         let space = arguments.get("space").and_then(|v| v.as_str());
         let messages = self.client.get_messages(space, session_id, limit).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&messages)?))
+        self.render_messages(arguments, &messages)
     }
 
     async fn handle_pause_session(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -155,15 +1044,47 @@ impl RaworcMcpServer {
         Ok(Self::text_content("Session terminated successfully"))
     }
 
-    async fn handle_list_spaces(&self, _arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
-        let spaces = self.client.list_spaces().await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&spaces)?))
+    async fn handle_wait_session(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+        let session_id = arguments
+            .get("session_id").and_then(|v| v.as_str())
+            .ok_or_else(|| RaworcError::validation_error("session_id is required"))?;
+        let space = arguments.get("space").and_then(|v| v.as_str());
+        let targets = match arguments.get("states") {
+            Some(Value::String(s)) => vec![Self::parse_session_state(s)?],
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .ok_or_else(|| RaworcError::validation_error("states must be strings"))
+                        .and_then(Self::parse_session_state)
+                })
+                .collect::<RaworcResult<Vec<_>>>()?,
+            _ => return Err(RaworcError::validation_error("states is required")),
+        };
+        let timeout = Self::wait_timeout(arguments);
+
+        let progress = self.progress.clone();
+        let total = timeout.as_secs_f64();
+        let on_progress = move |elapsed: Duration, status: &str| {
+            progress.report(elapsed.as_secs_f64(), Some(total), status)
+        };
+        let session = self
+            .client
+            .wait_for_session_state(space, session_id, &targets, timeout, &on_progress)
+            .await?;
+        self.render(arguments, &session)
+    }
+
+    async fn handle_list_spaces(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+        let mut spaces = self.client.list_spaces().await?;
+        spaces.retain(|space| self.config.space_allowed(&space.name));
+        self.render_table(arguments, &spaces)
     }
 
     async fn handle_list_agents(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let space = arguments.get("space").and_then(|v| v.as_str());
         let agents = self.client.list_agents(space).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&agents)?))
+        self.render_table(arguments, &agents)
     }
 
     async fn handle_get_agent_logs(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -171,14 +1092,58 @@ impl RaworcMcpServer {
             .ok_or_else(|| RaworcError::validation_error("space is required"))?;
         let agent_name = arguments.get("agent_name").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("agent_name is required"))?;
-        let logs = self.client.get_agent_logs(space, agent_name).await?;
-        Ok(Self::text_content(logs))
+        let query = AgentLogsQuery {
+            tail: arguments.get("tail").and_then(|v| v.as_u64()),
+            since: arguments
+                .get("since")
+                .and_then(|v| v.as_str())
+                .map(Self::parse_since)
+                .transpose()?,
+            grep: arguments.get("grep").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        };
+        let follow = arguments.get("follow").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        if !follow {
+            if query.tail.is_none() && query.since.is_none() && query.grep.is_none() {
+                let logs = self.client.get_agent_logs(space, agent_name).await?;
+                return Ok(Self::text_content(logs));
+            }
+            let lines = self.client.get_agent_log_lines(space, agent_name, &query).await?;
+            return Ok(Self::text_content(lines.join("\n")));
+        }
+
+        // Follow mode: fetch from the last timestamp seen, then skip the lines
+        // of that window already handed back
+        let key = format!("{}/{}", space, agent_name);
+        let cursor = self
+            .log_cursors
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+            .cloned()
+            .unwrap_or_default();
+        let window_query = AgentLogsQuery {
+            since: cursor.since.or(query.since),
+            ..AgentLogsQuery::default()
+        };
+        let window = self.client.get_agent_log_lines(space, agent_name, &window_query).await?;
+        let new_lines = window[cursor.resume_at(&window)..].to_vec();
+        let next = LogCursor::after(window, window_query.since)?;
+        self.log_cursors.lock().unwrap_or_else(|e| e.into_inner()).insert(key, next);
+        let lines = filter_log_lines(new_lines, &query)?;
+        if lines.is_empty() {
+            return Ok(Self::text_content("No new log lines"));
+        }
+        Ok(Self::text_content(lines.join("\n")))
     }
 
     async fn handle_list_secrets(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let space = arguments.get("space").and_then(|v| v.as_str());
-        let secrets = self.client.list_secrets(space).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&secrets)?))
+        let mut secrets = self.client.list_secrets(space).await?;
+        for secret in &mut secrets {
+            secret.value = None;
+        }
+        self.render_table(arguments, &secrets)
     }
 
     async fn handle_get_secret(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -186,8 +1151,18 @@ impl RaworcMcpServer {
             .ok_or_else(|| RaworcError::validation_error("space is required"))?;
         let key = arguments.get("key").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("key is required"))?;
-        let secret = self.client.get_secret(space, key).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&secret)?))
+        let mut secret = self.client.get_secret(space, key).await?;
+        // Unmasked only where config allows it or the user confirms in the host
+        let reveal = secret.value.is_none()
+            || self.config.reveals_secrets(space)
+            || self
+                .elicitor
+                .confirm(&format!("Show the value of secret '{}' in space '{}' to the model?", key, space))
+                .await;
+        if !reveal {
+            mask_secret(&mut secret);
+        }
+        self.render(arguments, &secret)
     }
 
     async fn handle_set_secret(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -197,8 +1172,11 @@ impl RaworcMcpServer {
             .ok_or_else(|| RaworcError::validation_error("key is required"))?;
         let value = arguments.get("value").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("value is required"))?;
-        let secret = self.client.set_secret(space, key, value).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&secret)?))
+        let mut secret = self.client.set_secret(space, key, value).await?;
+        if !self.config.reveals_secrets(space) {
+            mask_secret(&mut secret);
+        }
+        self.render(arguments, &secret)
     }
 
     async fn handle_delete_secret(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -215,15 +1193,15 @@ impl RaworcMcpServer {
         Ok(Self::text_content(health))
     }
 
-    async fn handle_get_version(&self, _arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+    async fn handle_get_version(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let version = self.client.get_version().await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&version)?))
+        self.render(arguments, &version)
     }
 
     // Service Accounts
-    async fn handle_list_service_accounts(&self, _arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+    async fn handle_list_service_accounts(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let accounts = self.client.list_service_accounts().await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&accounts)?))
+        self.render(arguments, &accounts)
     }
 
     async fn handle_create_service_account(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -236,19 +1214,19 @@ impl RaworcMcpServer {
         
         let request = CreateServiceAccountRequest {
             user: user.to_string(),
-            pass: pass.to_string(),
+            pass: pass.into(),
             space,
             description,
         };
         let account = self.client.create_service_account(&request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&account)?))
+        self.render(arguments, &account)
     }
 
     async fn handle_get_service_account(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let id = arguments.get("id").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("id is required"))?;
         let account = self.client.get_service_account(id).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&account)?))
+        self.render(arguments, &account)
     }
 
     async fn handle_update_service_account(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -264,7 +1242,7 @@ impl RaworcMcpServer {
             active,
         };
         let account = self.client.update_service_account(id, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&account)?))
+        self.render(arguments, &account)
     }
 
     async fn handle_delete_service_account(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -283,17 +1261,17 @@ impl RaworcMcpServer {
             .ok_or_else(|| RaworcError::validation_error("new_password is required"))?;
         
         let request = UpdatePasswordRequest {
-            current_password: current_password.to_string(),
-            new_password: new_password.to_string(),
+            current_password: current_password.into(),
+            new_password: new_password.into(),
         };
         self.client.update_service_account_password(id, &request).await?;
         Ok(Self::text_content("Password updated successfully"))
     }
 
     // Roles
-    async fn handle_list_roles(&self, _arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+    async fn handle_list_roles(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let roles = self.client.list_roles().await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&roles)?))
+        self.render(arguments, &roles)
     }
 
     async fn handle_create_role(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -310,14 +1288,14 @@ impl RaworcMcpServer {
             rules,
         };
         let role = self.client.create_role(&request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&role)?))
+        self.render(arguments, &role)
     }
 
     async fn handle_get_role(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let id = arguments.get("id").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("id is required"))?;
         let role = self.client.get_role(id).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&role)?))
+        self.render(arguments, &role)
     }
 
     async fn handle_delete_role(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -328,9 +1306,9 @@ impl RaworcMcpServer {
     }
 
     // Role Bindings
-    async fn handle_list_role_bindings(&self, _arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+    async fn handle_list_role_bindings(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let bindings = self.client.list_role_bindings().await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&bindings)?))
+        self.render_table(arguments, &bindings)
     }
 
     async fn handle_create_role_binding(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -346,14 +1324,14 @@ impl RaworcMcpServer {
             space,
         };
         let binding = self.client.create_role_binding(&request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&binding)?))
+        self.render(arguments, &binding)
     }
 
     async fn handle_get_role_binding(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let id = arguments.get("id").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("id is required"))?;
         let binding = self.client.get_role_binding(id).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&binding)?))
+        self.render(arguments, &binding)
     }
 
     async fn handle_delete_role_binding(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -377,14 +1355,14 @@ impl RaworcMcpServer {
             settings,
         };
         let space = self.client.create_space(&request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&space)?))
+        self.render(arguments, &space)
     }
 
     async fn handle_get_space(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let name = arguments.get("name").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("name is required"))?;
         let space = self.client.get_space(name).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&space)?))
+        self.render(arguments, &space)
     }
 
     async fn handle_update_space(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -399,7 +1377,7 @@ impl RaworcMcpServer {
             settings,
         };
         let space = self.client.update_space(name, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&space)?))
+        self.render(arguments, &space)
     }
 
     async fn handle_delete_space(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -422,7 +1400,7 @@ impl RaworcMcpServer {
             metadata,
         };
         let session = self.client.update_session(space, session_id, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&session)?))
+        self.render(arguments, &session)
     }
 
     async fn handle_update_session_state(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -432,17 +1410,8 @@ impl RaworcMcpServer {
         let state_str = arguments.get("state").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("state is required"))?;
         
-        let state = match state_str {
-            "INIT" => SessionState::Init,
-            "RUNNING" => SessionState::Running,
-            "PAUSED" => SessionState::Paused,
-            "SUSPENDED" => SessionState::Suspended,
-            "TERMINATED" => SessionState::Terminated,
-            "IDLE" => SessionState::Idle,
-            "CLOSED" => SessionState::Closed,
-            _ => return Err(RaworcError::validation_error("Invalid session state")),
-        };
-        
+        let state = Self::parse_session_state(state_str)?;
+
         self.client.update_session_state(space, session_id, state).await?;
         Ok(Self::text_content("Session state updated successfully"))
     }
@@ -471,7 +1440,7 @@ impl RaworcMcpServer {
             metadata: None,
         };
         let session = self.client.remix_session(session_id, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&session)?))
+        self.render(arguments, &session)
     }
 
     // Additional message methods
@@ -480,7 +1449,7 @@ impl RaworcMcpServer {
             .ok_or_else(|| RaworcError::validation_error("session_id is required"))?;
         let space = arguments.get("space").and_then(|v| v.as_str());
         let count = self.client.get_message_count(space, session_id).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&count)?))
+        self.render(arguments, &count)
     }
 
     async fn handle_clear_messages(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -514,7 +1483,7 @@ impl RaworcMcpServer {
             resources: None,
         };
         let agent = self.client.create_agent(space, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&agent)?))
+        self.render(arguments, &agent)
     }
 
     async fn handle_get_agent(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -523,7 +1492,7 @@ impl RaworcMcpServer {
         let agent_name = arguments.get("agent_name").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("agent_name is required"))?;
         let agent = self.client.get_agent(space, agent_name).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&agent)?))
+        self.render(arguments, &agent)
     }
 
     async fn handle_update_agent(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -545,7 +1514,7 @@ impl RaworcMcpServer {
             resources: None,
         };
         let agent = self.client.update_agent(space, agent_name, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&agent)?))
+        self.render(arguments, &agent)
     }
 
     async fn handle_delete_agent(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -601,7 +1570,7 @@ impl RaworcMcpServer {
         let space = arguments.get("space").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("space is required"))?;
         let agents = self.client.list_running_agents(space).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&agents)?))
+        self.render_table(arguments, &agents)
     }
 
     // Additional secret methods
@@ -619,8 +1588,11 @@ impl RaworcMcpServer {
             value: value.to_string(),
             description,
         };
-        let secret = self.client.create_secret(space, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&secret)?))
+        let mut secret = self.client.create_secret(space, &request).await?;
+        if !self.config.reveals_secrets(space) {
+            mask_secret(&mut secret);
+        }
+        self.render(arguments, &secret)
     }
 
     async fn handle_update_secret(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -635,8 +1607,11 @@ impl RaworcMcpServer {
             value,
             description,
         };
-        let secret = self.client.update_secret(space, key, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&secret)?))
+        let mut secret = self.client.update_secret(space, key, &request).await?;
+        if !self.config.reveals_secrets(space) {
+            mask_secret(&mut secret);
+        }
+        self.render(arguments, &secret)
     }
 
     // Build methods
@@ -651,14 +1626,14 @@ impl RaworcMcpServer {
             context,
         };
         let build = self.client.create_build(space, &request).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&build)?))
+        self.render(arguments, &build)
     }
 
     async fn handle_get_latest_build(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
         let space = arguments.get("space").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("space is required"))?;
         let build = self.client.get_latest_build(space).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&build)?))
+        self.render(arguments, &build)
     }
 
     async fn handle_get_build(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
@@ -667,6 +1642,429 @@ impl RaworcMcpServer {
         let build_id = arguments.get("build_id").and_then(|v| v.as_str())
             .ok_or_else(|| RaworcError::validation_error("build_id is required"))?;
         let build = self.client.get_build(space, build_id).await?;
-        Ok(Self::text_content(serde_json::to_string_pretty(&build)?))
+        self.render(arguments, &build)
+    }
+
+    async fn handle_audit_log(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+        let audit = self.audit.as_ref().ok_or_else(|| {
+            RaworcError::ConfigError("the audit log is not enabled; set RAWORC_AUDIT_LOG".to_string())
+        })?;
+        let query = AuditQuery {
+            space: arguments.get("space").and_then(|v| v.as_str()).map(|s| s.to_string()),
+            tool: arguments.get("tool").and_then(|v| v.as_str()).map(|s| s.to_string()),
+            limit: None,
+        };
+        let limit = arguments
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_AUDIT_LIMIT, |n| n as usize);
+        let mut records = audit.query(&query)?;
+        records.retain(|r| r.space.as_deref().is_none_or(|space| self.config.space_allowed(space)));
+        records.truncate(limit);
+        self.render(arguments, &records)
     }
+
+    async fn handle_wait_for_build(&self, arguments: &Value) -> RaworcResult<Vec<ToolCallContent>> {
+        let space = arguments.get("space").and_then(|v| v.as_str())
+            .ok_or_else(|| RaworcError::validation_error("space is required"))?;
+        let build_id = arguments.get("build_id").and_then(|v| v.as_str());
+        let timeout = Self::wait_timeout(arguments);
+
+        let progress = self.progress.clone();
+        let total = timeout.as_secs_f64();
+        let on_progress = move |elapsed: Duration, chunk: &str| {
+            progress.report(elapsed.as_secs_f64(), Some(total), chunk)
+        };
+        let started = Instant::now();
+        let build = self
+            .client
+            .wait_for_build(space, build_id, timeout, &on_progress)
+            .await?;
+        let elapsed = started.elapsed().as_secs();
+
+        if build.status == BuildStatus::Failed {
+            let logs = build.logs.as_deref().unwrap_or_default();
+            let lines: Vec<&str> = logs.lines().collect();
+            let tail = &lines[lines.len().saturating_sub(BUILD_LOG_TAIL_LINES)..];
+            return Ok(Self::text_content(format!(
+                "Build {} failed after {}s. Last {} log lines:\n{}",
+                build.id.as_deref().unwrap_or("(latest)"),
+                elapsed,
+                tail.len(),
+                tail.join("\n")
+            )));
+        }
+        let rendered = render_value(self.output_format(arguments)?, serde_json::to_value(&build)?, None)?;
+        Ok(Self::text_content(format!("Build completed after {}s\n{}", elapsed, rendered)))
+    }
+}
+
+/// Tools advertised by `tools/list` and `raworc-mcp tools`: the
+/// `SUPPORTED_TOOLS` entries of `CAPABILITIES`, with the common arguments
+pub fn tool_list() -> Value {
+    let mut tools: Value = serde_json::from_str(crate::CAPABILITIES).expect("CAPABILITIES is valid JSON");
+    if let Some(list) = tools.get_mut("tools").and_then(|t| t.as_array_mut()) {
+        list.retain(|tool| {
+            tool.get("name")
+                .and_then(|n| n.as_str())
+                .is_some_and(|name| SUPPORTED_TOOLS.contains(&name))
+        });
+    }
+    with_common_arguments(tools)
+}
+
+/// Add the per-call arguments every tool understands (`format`, `columns`,
+/// `max_chars`, `cursor`) to a `tools/list` payload
+pub fn with_common_arguments(mut tools: Value) -> Value {
+    let common = json!({
+        "format": {
+            "type": "string",
+            "enum": ["json", "compact_json", "markdown", "yaml"],
+            "description": "Output format for the result"
+        },
+        "columns": {
+            "type": "array",
+            "items": {"type": "string"},
+            "description": "Table columns when a list is rendered as markdown"
+        },
+        "max_chars": {
+            "type": "number",
+            "description": "Truncate the result to about this many characters"
+        },
+        "cursor": {
+            "type": "number",
+            "description": "Skip this many items (or lines) of the result; truncated results say which cursor to use next"
+        }
+    });
+    let dry_run = json!({
+        "type": "boolean",
+        "description": "Return the request this call would send, and the target's current state, without sending it"
+    });
+    let list = tools.get_mut("tools").and_then(|t| t.as_array_mut());
+    for tool in list.into_iter().flatten() {
+        let mutating = tool.get("name").and_then(|n| n.as_str()).is_some_and(is_mutating);
+        if let Some(props) = tool
+            .pointer_mut("/inputSchema/properties")
+            .and_then(|p| p.as_object_mut())
+        {
+            for (key, schema) in common.as_object().into_iter().flatten() {
+                props.entry(key.clone()).or_insert_with(|| schema.clone());
+            }
+            if mutating {
+                props.entry("dry_run").or_insert_with(|| dry_run.clone());
+            }
+        }
+    }
+    tools
+}
+
+/// Mask a secret's value, keeping a short prefix and the last four characters
+/// (`sk-…9f2a`); values under 12 characters are hidden entirely
+pub fn mask_secret(secret: &mut Secret) {
+    secret.value = secret.value.as_deref().map(mask_value);
+}
+
+fn mask_value(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 12 {
+        return "…".to_string();
+    }
+    let prefix_len = chars
+        .iter()
+        .take(4)
+        .position(|c| matches!(c, '-' | '_'))
+        .map_or(0, |i| i + 1);
+    let prefix: String = chars[..prefix_len].iter().collect();
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", prefix, suffix)
+}
+
+/// Build tool-call arguments for `tool` from a JSON object and `key=value`
+/// pairs, the pairs winning. Pair values are strings unless the tool's input
+/// schema types the property as a number, boolean, object or array (`limit=5`
+/// is a number, `session_id=42` stays a string); values for properties the
+/// schema does not know are parsed only when they start with `{` or `[`.
+pub fn parse_tool_arguments(tool: &str, json: Option<&str>, pairs: &[String]) -> RaworcResult<Value> {
+    let mut arguments = match json {
+        Some(text) => match serde_json::from_str(text)? {
+            Value::Object(map) => map,
+            _ => return Err(RaworcError::validation_error("--args must be a JSON object")),
+        },
+        None => serde_json::Map::new(),
+    };
+    let tools = tool_list();
+    let properties = tools
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .and_then(|list| list.iter().find(|t| t.get("name").and_then(|n| n.as_str()) == Some(tool)))
+        .and_then(|t| t.pointer("/inputSchema/properties"));
+    for pair in pairs {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| RaworcError::validation_error(&format!("Expected key=value, got '{}'", pair)))?;
+        let kind = properties
+            .and_then(|p| p.get(key))
+            .and_then(|p| p.get("type"))
+            .and_then(|t| t.as_str());
+        let typed = match kind {
+            Some("integer" | "number" | "boolean" | "object" | "array") => true,
+            Some(_) => false,
+            None => value.starts_with('{') || value.starts_with('['),
+        };
+        let value = if typed {
+            serde_json::from_str(value).map_err(|_| {
+                RaworcError::validation_error(&format!("--arg {} expects {}, got '{}'", key, kind.unwrap_or("JSON"), value))
+            })?
+        } else {
+            Value::String(value.to_string())
+        };
+        arguments.insert(key.to_string(), value);
+    }
+    Ok(Value::Object(arguments))
+}
+
+/// Apply the output budget to a whole response. Several text items are joined
+/// into the first one and fitted together, so `max_chars` bounds the response
+/// and `cursor` addresses one sequence of entries.
+fn budget_content(content: Vec<ToolCallContent>, max_chars: usize, cursor: usize) -> Vec<ToolCallContent> {
+    let mut texts = Vec::new();
+    let mut merged: Vec<ToolCallContent> = Vec::with_capacity(content.len());
+    let mut first_text = None;
+    for mut item in content {
+        if let Some(text) = item.text.take() {
+            texts.push(text);
+            if first_text.is_some() {
+                continue;
+            }
+            first_text = Some(merged.len());
+        }
+        merged.push(item);
+    }
+    if let Some(index) = first_text {
+        merged[index].text = Some(apply_output_budget(texts.join("\n"), max_chars, cursor));
+    }
+    merged
+}
+
+/// Fit a tool result into `max_chars` characters, starting at entry `cursor`.
+/// JSON arrays keep whole items and JSON objects whole fields, from the head and
+/// tail with a marker entry in between, so the result stays valid JSON; anything
+/// else is cut by lines the same way. An entry too big for the head on its own
+/// is shrunk (see `shrink_json`) and the cursor moves past it.
+pub fn apply_output_budget(text: String, max_chars: usize, cursor: usize) -> String {
+    if cursor == 0 && text.chars().count() <= max_chars {
+        return text;
+    }
+    match serde_json::from_str::<Value>(&text) {
+        Ok(Value::Array(items)) => {
+            let items: Vec<Value> = items.into_iter().skip(cursor).collect();
+            let sizes: Vec<usize> = items.iter().map(|item| json_size(item) + 2).collect();
+            let kept = fit_entries(&items, &sizes, max_chars, cursor, "items", shrink_json, Value::String);
+            serde_json::to_string_pretty(&kept.unwrap_or(items)).unwrap_or(text)
+        }
+        Ok(Value::Object(fields)) => {
+            let fields: Vec<(String, Value)> = fields.into_iter().skip(cursor).collect();
+            let sizes: Vec<usize> = fields.iter().map(|(k, v)| k.chars().count() + json_size(v) + 6).collect();
+            let kept = fit_entries(
+                &fields,
+                &sizes,
+                max_chars,
+                cursor,
+                "fields",
+                |(key, value), budget| (key.clone(), shrink_json(value, budget)),
+                |marker| ("...".to_string(), Value::String(marker)),
+            );
+            let object: serde_json::Map<String, Value> = kept.unwrap_or(fields).into_iter().collect();
+            serde_json::to_string_pretty(&object).unwrap_or(text)
+        }
+        _ => {
+            let lines: Vec<String> = text.lines().skip(cursor).map(str::to_string).collect();
+            let sizes: Vec<usize> = lines.iter().map(|l| l.chars().count() + 1).collect();
+            let kept = fit_entries(&lines, &sizes, max_chars, cursor, "lines", |line, budget| char_prefix(line, budget), |m| m);
+            kept.unwrap_or(lines).join("\n")
+        }
+    }
+}
+
+/// Head and tail entries that fit into `max_chars` with a marker entry between
+/// them, or `None` if everything fits. A first entry too big for the head is
+/// replaced by `shrink(entry, head budget)`.
+fn fit_entries<T: Clone>(
+    entries: &[T],
+    sizes: &[usize],
+    max_chars: usize,
+    cursor: usize,
+    unit: &str,
+    shrink: impl Fn(&T, usize) -> T,
+    marker: impl Fn(String) -> T,
+) -> Option<Vec<T>> {
+    let (head, tail) = split_for_budget(sizes, max_chars)?;
+    let mut kept = entries[..head].to_vec();
+    let cut = head == 0;
+    if cut {
+        kept.push(shrink(&entries[0], head_budget(max_chars)));
+    }
+    let resumed = head + usize::from(cut);
+    let omitted = entries.len() - resumed - tail;
+    kept.push(marker(truncation_marker(omitted, unit, cut, cursor, cursor + resumed)));
+    kept.extend_from_slice(&entries[entries.len() - tail..]);
+    Some(kept)
+}
+
+/// `value` cut down to about `budget` characters of pretty JSON. Arrays and
+/// objects keep whole leading entries and say how many were dropped, so no
+/// object is split; only strings are cut mid-value.
+fn shrink_json(value: &Value, budget: usize) -> Value {
+    if json_size(value) <= budget {
+        return value.clone();
+    }
+    match value {
+        Value::String(s) => Value::String(format!("{}...", char_prefix(s, budget.saturating_sub(5)))),
+        Value::Array(items) => {
+            let mut used = 2;
+            let mut kept = Vec::new();
+            for item in items {
+                let size = json_size(item) + 2;
+                if used + size > budget {
+                    if kept.is_empty() {
+                        kept.push(shrink_json(item, budget.saturating_sub(used)));
+                    }
+                    break;
+                }
+                used += size;
+                kept.push(item.clone());
+            }
+            kept.push(Value::String(format!("... {} items omitted ...", items.len() - kept.len())));
+            Value::Array(kept)
+        }
+        Value::Object(fields) => {
+            let mut used = 2;
+            let mut kept = serde_json::Map::new();
+            for (key, field) in fields {
+                let size = key.chars().count() + json_size(field) + 6;
+                if used + size > budget {
+                    if kept.is_empty() {
+                        let rest = budget.saturating_sub(used + key.chars().count() + 6);
+                        kept.insert(key.clone(), shrink_json(field, rest));
+                    }
+                    break;
+                }
+                used += size;
+                kept.insert(key.clone(), field.clone());
+            }
+            let omitted = fields.len() - kept.len();
+            kept.insert("...".to_string(), Value::String(format!("{} fields omitted", omitted)));
+            Value::Object(kept)
+        }
+        other => other.clone(),
+    }
+}
+
+fn json_size(value: &Value) -> usize {
+    serde_json::to_string_pretty(value).map_or(0, |s| s.chars().count())
+}
+
+/// Characters the head of a truncated result may use
+fn head_budget(max_chars: usize) -> usize {
+    (max_chars as f64 * OUTPUT_HEAD_SHARE) as usize
+}
+
+fn char_prefix(text: &str, chars: usize) -> String {
+    text.chars().take(chars).collect()
+}
+
+/// How many head and tail entries fit into `max_chars`, or `None` if everything
+/// fits. With no head entry the head budget stays reserved for a cut prefix of
+/// the first one, so the tail never reaches it.
+fn split_for_budget(sizes: &[usize], max_chars: usize) -> Option<(usize, usize)> {
+    if sizes.iter().sum::<usize>() <= max_chars {
+        return None;
+    }
+    let head_budget = head_budget(max_chars);
+    let mut used = 0;
+    let head = sizes
+        .iter()
+        .take_while(|&&size| {
+            used += size;
+            used <= head_budget
+        })
+        .count();
+    let mut used: usize = if head == 0 { head_budget } else { sizes[..head].iter().sum() };
+    let tail = sizes[head.max(1)..]
+        .iter()
+        .rev()
+        .take_while(|&&size| {
+            used += size;
+            used <= max_chars
+        })
+        .count();
+    Some((head, tail))
+}
+
+fn truncation_marker(omitted: usize, unit: &str, cut: bool, cut_at: usize, next_cursor: usize) -> String {
+    let cut = if cut { format!(" entry {} cut short,", cut_at) } else { String::new() };
+    format!(
+        "... truncated,{} {} {} omitted, use cursor {} to continue ...",
+        cut, omitted, unit, next_cursor
+    )
+}
+
+/// The single place results are turned into text. `columns` picks Markdown table
+/// columns for lists; without it the first item's keys are used.
+fn render_value(format: OutputFormat, value: Value, columns: Option<&[String]>) -> RaworcResult<String> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(&value)?),
+        OutputFormat::CompactJson => Ok(serde_json::to_string(&value)?),
+        OutputFormat::Yaml => serde_yaml::to_string(&value)
+            .map_err(|e| RaworcError::internal_error(&format!("YAML rendering failed: {}", e))),
+        OutputFormat::Markdown => Ok(match &value {
+            Value::Array(rows) => {
+                let columns: Vec<String> = match columns {
+                    Some(cols) => cols.to_vec(),
+                    None => rows
+                        .first()
+                        .and_then(|r| r.as_object())
+                        .map(|o| o.keys().cloned().collect())
+                        .unwrap_or_default(),
+                };
+                markdown_table(rows, &columns)
+            }
+            Value::Object(fields) => {
+                let mut out = String::from("| Field | Value |\n| --- | --- |\n");
+                for (key, v) in fields {
+                    out.push_str(&format!("| {} | {} |\n", key, markdown_cell(v)));
+                }
+                out
+            }
+            other => markdown_cell(other),
+        }),
+    }
+}
+
+fn markdown_table(rows: &[Value], columns: &[String]) -> String {
+    if rows.is_empty() {
+        return "_No results_".to_string();
+    }
+    if columns.is_empty() {
+        return rows.iter().map(markdown_cell).collect::<Vec<_>>().join("\n");
+    }
+    let mut out = format!("| {} |\n", columns.join(" | "));
+    out.push_str(&format!("|{}\n", " --- |".repeat(columns.len())));
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|c| row.get(c).map(markdown_cell).unwrap_or_default())
+            .collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out
+}
+
+fn markdown_cell(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    text.replace('|', "\\|").replace('\n', "<br>")
 }
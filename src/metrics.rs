@@ -0,0 +1,156 @@
+//! Prometheus metrics, served as text on `/metrics` when `--metrics-addr` is set.
+//! - Tool calls: count and latency by tool and outcome (`RaworcMcpServer::handle_tool_call`)
+//! - Upstream API: responses by method, route template and status, plus re-auth
+//!   and retry counts (`RaworcClient` request helpers)
+//! - Breaker state: consecutive failed calls per route template, reset by the
+//!   first call that gets an answer (`RaworcClient::with_retry`)
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Process-wide metric handles
+pub struct Metrics {
+    registry: Registry,
+    pub tool_calls: IntCounterVec,
+    pub tool_call_duration: HistogramVec,
+    pub api_responses: IntCounterVec,
+    pub api_reauth: IntCounter,
+    pub api_retries: IntCounter,
+    pub api_consecutive_failures: IntGaugeVec,
+}
+
+/// Shared metrics; created on first use
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let tool_calls = IntCounterVec::new(
+            Opts::new("raworc_mcp_tool_calls_total", "MCP tool calls by tool and outcome"),
+            &["tool", "outcome"],
+        )
+        .expect("valid metric");
+        let tool_call_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "raworc_mcp_tool_call_duration_seconds",
+                "MCP tool call latency by tool and outcome",
+            )
+            .buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]),
+            &["tool", "outcome"],
+        )
+        .expect("valid metric");
+        let api_responses = IntCounterVec::new(
+            Opts::new("raworc_api_responses_total", "Raworc API responses by method, route and status"),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let api_reauth = IntCounter::new(
+            "raworc_api_reauth_total",
+            "Re-authentications after a 401 from the Raworc API",
+        )
+        .expect("valid metric");
+        let api_retries = IntCounter::new("raworc_api_retries_total", "Retried Raworc API requests")
+            .expect("valid metric");
+        let api_consecutive_failures = IntGaugeVec::new(
+            Opts::new(
+                "raworc_api_consecutive_failures",
+                "Raworc API calls in a row that failed with a 5xx, timeout or connection error, by route",
+            ),
+            &["route"],
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(tool_calls.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(tool_call_duration.clone()),
+            Box::new(api_responses.clone()),
+            Box::new(api_reauth.clone()),
+            Box::new(api_retries.clone()),
+            Box::new(api_consecutive_failures.clone()),
+        ] {
+            registry.register(collector).expect("unique metric");
+        }
+
+        Self {
+            registry,
+            tool_calls,
+            tool_call_duration,
+            api_responses,
+            api_reauth,
+            api_retries,
+            api_consecutive_failures,
+        }
+    }
+
+    /// Record one finished tool call. `tool` must be a known tool name, or
+    /// `"unknown"`, so hosts cannot grow the label set.
+    pub fn observe_tool_call(&self, tool: &str, ok: bool, elapsed: Duration) {
+        let outcome = if ok { "ok" } else { "error" };
+        self.tool_calls.with_label_values(&[tool, outcome]).inc();
+        self.tool_call_duration
+            .with_label_values(&[tool, outcome])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record one upstream response
+    pub fn observe_api_response(&self, method: &str, route: &str, status: u16) {
+        self.api_responses
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+    }
+
+    /// Record how one upstream call ended: a failure extends the route's run of
+    /// failures, anything else ends it
+    pub fn observe_api_outcome(&self, route: &str, failed: bool) {
+        let gauge = self.api_consecutive_failures.with_label_values(&[route]);
+        if failed {
+            gauge.inc();
+        } else {
+            gauge.set(0);
+        }
+    }
+
+    /// Prometheus text exposition of all metrics
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buf);
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+/// Serve `/metrics` until the process exits. GET and HEAD are answered on
+/// `/metrics` whatever the query string; every other path is a 404.
+pub async fn serve(addr: SocketAddr) -> std::io::Result<()> {
+    let make_service = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(respond)) });
+    let server = Server::try_bind(&addr).map_err(io_error)?.serve(make_service);
+    tracing::info!("Serving metrics on http://{}/metrics", server.local_addr());
+    server.await.map_err(io_error)
+}
+
+async fn respond(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET | &Method::HEAD, "/metrics") => Response::builder()
+            .header(CONTENT_TYPE, prometheus::TEXT_FORMAT)
+            .body(Body::from(metrics().render())),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("not found\n")),
+    };
+    Ok(response.expect("static response parts are valid"))
+}
+
+fn io_error(e: hyper::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
@@ -0,0 +1,693 @@
+//! Local mock of the Raworc v0 API, backed by `testing::InMemoryRaworc`.
+//! - Serves every route `RaworcClient` calls, under `/api/v0`
+//! - Sessions start in `INIT`, settle to `IDLE`, and run while a reply is being "thought" about
+//! - Each user message gets the next scripted assistant reply (`{content}` echoes the message)
+
+use crate::api::RaworcApi;
+use crate::error::RaworcError;
+use crate::models::*;
+use crate::testing::InMemoryRaworc;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Default reply used when no script is given
+pub const DEFAULT_REPLY: &str = "Echo: {content}";
+
+/// Mock server options
+#[derive(Debug, Clone)]
+pub struct MockOptions {
+    /// Assistant replies, used in order and then cycled
+    pub replies: Vec<String>,
+    /// How long a session stays RUNNING before replying
+    pub think_time: Duration,
+    /// How long a build takes to complete
+    pub build_time: Duration,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        Self {
+            replies: vec![DEFAULT_REPLY.to_string()],
+            think_time: Duration::from_millis(500),
+            build_time: Duration::from_secs(2),
+        }
+    }
+}
+
+struct MockState {
+    backend: InMemoryRaworc,
+    options: MockOptions,
+    next_reply: AtomicUsize,
+}
+
+type Shared = Arc<MockState>;
+type ApiResult<T> = Result<Json<T>, ApiFailure>;
+
+/// Build the `/api/v0` router over a fresh in-memory backend
+pub fn router(options: MockOptions) -> Router {
+    let state = Arc::new(MockState {
+        backend: InMemoryRaworc::new(),
+        options,
+        next_reply: AtomicUsize::new(0),
+    });
+
+    let api = Router::new()
+        .route("/health", get(health))
+        .route("/version", get(version))
+        .route("/auth/login", post(login))
+        .route("/auth/me", get(me))
+        .route("/spaces", get(list_spaces).post(create_space))
+        .route("/spaces/:space", get(get_space).put(update_space).delete(delete_space))
+        .route("/spaces/:space/sessions", get(list_sessions).post(create_session))
+        .route(
+            "/spaces/:space/sessions/:id",
+            get(get_session).put(update_session).delete(terminate_session),
+        )
+        .route("/spaces/:space/sessions/:id/state", axum::routing::put(update_session_state))
+        .route("/spaces/:space/sessions/:id/pause", post(pause_session))
+        .route("/spaces/:space/sessions/:id/resume", post(resume_session))
+        .route(
+            "/spaces/:space/sessions/:id/messages",
+            get(get_messages).post(send_message).delete(clear_messages),
+        )
+        .route("/spaces/:space/sessions/:id/messages/count", get(message_count))
+        .route("/sessions", get(list_all_sessions).post(create_global_session))
+        .route(
+            "/sessions/:id",
+            get(get_global_session).put(update_global_session).delete(delete_global_session),
+        )
+        .route("/sessions/:id/state", axum::routing::put(update_global_session_state))
+        .route("/sessions/:id/close", post(close_session))
+        .route("/sessions/:id/restore", post(restore_session))
+        .route("/sessions/:id/remix", post(remix_session))
+        .route(
+            "/sessions/:id/messages",
+            get(get_global_messages).post(send_global_message).delete(clear_global_messages),
+        )
+        .route("/sessions/:id/messages/count", get(global_message_count))
+        .route("/spaces/:space/agents", get(list_agents).post(create_agent))
+        .route(
+            "/spaces/:space/agents/:name",
+            get(get_agent).put(update_agent).delete(delete_agent),
+        )
+        // Static segments win over `:name`, so `running` is not taken for an agent name
+        .route("/spaces/:space/agents/running", get(list_running_agents))
+        .route("/spaces/:space/agents/:name/logs", get(agent_logs))
+        .route("/spaces/:space/agents/:name/status", axum::routing::put(update_agent_status))
+        .route("/spaces/:space/agents/:name/deploy", post(deploy_agent))
+        .route("/spaces/:space/agents/:name/stop", post(stop_agent))
+        .route("/spaces/:space/secrets", get(list_secrets).post(create_secret))
+        .route(
+            "/spaces/:space/secrets/:key",
+            get(get_secret).post(set_secret).put(update_secret).delete(delete_secret),
+        )
+        .route("/spaces/:space/build", post(create_build))
+        .route("/spaces/:space/build/latest", get(latest_build))
+        .route("/spaces/:space/build/:id", get(get_build))
+        .route("/service-accounts", get(list_service_accounts).post(create_service_account))
+        .route(
+            "/service-accounts/:id",
+            get(get_service_account).put(update_service_account).delete(delete_service_account),
+        )
+        .route("/service-accounts/:id/password", axum::routing::put(update_service_account_password))
+        .route("/roles", get(list_roles).post(create_role))
+        .route("/roles/:id", get(get_role).delete(delete_role))
+        .route("/role-bindings", get(list_role_bindings).post(create_role_binding))
+        .route("/role-bindings/:id", get(get_role_binding).delete(delete_role_binding))
+        .layer(axum::middleware::from_fn(require_bearer))
+        .with_state(state);
+
+    Router::new().nest("/api/v0", api)
+}
+
+/// Serve the mock API until the process is stopped
+pub async fn serve(addr: SocketAddr, options: MockOptions) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("Raworc mock listening on http://{}/api/v0", listener.local_addr()?);
+    axum::serve(listener, router(options)).await
+}
+
+/* ------------------------------ Errors ------------------------------- */
+
+struct ApiFailure(RaworcError);
+
+impl From<RaworcError> for ApiFailure {
+    fn from(e: RaworcError) -> Self {
+        Self(e)
+    }
+}
+
+impl IntoResponse for ApiFailure {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            RaworcError::NotFound { .. } => StatusCode::NOT_FOUND,
+            RaworcError::AuthError { .. } => StatusCode::UNAUTHORIZED,
+            RaworcError::ValidationError(_) | RaworcError::JsonError(_) => StatusCode::BAD_REQUEST,
+            RaworcError::StateError(_) => StatusCode::CONFLICT,
+            RaworcError::ApiError { status, .. } => {
+                StatusCode::from_u16(*status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = json!({ "error": { "message": self.0.to_string() } });
+        (status, Json(body)).into_response()
+    }
+}
+
+async fn require_bearer(request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    let path = request.uri().path();
+    let public = ["/health", "/version", "/auth/login"]
+        .iter()
+        .any(|p| path.ends_with(p));
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("Bearer ") && v.len() > "Bearer ".len());
+    if public || authorized {
+        next.run(request).await
+    } else {
+        ApiFailure(RaworcError::auth_error("missing bearer token")).into_response()
+    }
+}
+
+/* --------------------------- Auth & system --------------------------- */
+
+async fn health() -> &'static str {
+    "OK"
+}
+
+async fn version(State(s): State<Shared>) -> ApiResult<VersionResponse> {
+    Ok(Json(s.backend.get_version().await?))
+}
+
+#[derive(Deserialize)]
+struct LoginBody {
+    user: String,
+    pass: String,
+}
+
+async fn login(State(s): State<Shared>, Json(body): Json<LoginBody>) -> ApiResult<AuthResponse> {
+    if body.user.is_empty() || body.pass.is_empty() {
+        return Err(RaworcError::auth_error("user and pass are required").into());
+    }
+    s.backend.set_user(&body.user);
+    Ok(Json(AuthResponse {
+        token: format!("mock-{}", Uuid::new_v4()),
+        token_type: "Bearer".to_string(),
+        expires_at: Utc::now() + ChronoDuration::hours(24),
+    }))
+}
+
+async fn me(State(s): State<Shared>) -> ApiResult<UserInfo> {
+    Ok(Json(s.backend.get_user_info().await?))
+}
+
+/* ------------------------------ Spaces ------------------------------- */
+
+async fn list_spaces(State(s): State<Shared>) -> ApiResult<Vec<Space>> {
+    Ok(Json(s.backend.list_spaces().await?))
+}
+
+async fn create_space(State(s): State<Shared>, Json(req): Json<CreateSpaceRequest>) -> ApiResult<Space> {
+    Ok(Json(s.backend.create_space(&req).await?))
+}
+
+async fn get_space(State(s): State<Shared>, Path(space): Path<String>) -> ApiResult<Space> {
+    Ok(Json(s.backend.get_space(&space).await?))
+}
+
+async fn update_space(
+    State(s): State<Shared>,
+    Path(space): Path<String>,
+    Json(req): Json<UpdateSpaceRequest>,
+) -> ApiResult<Space> {
+    Ok(Json(s.backend.update_space(&space, &req).await?))
+}
+
+async fn delete_space(State(s): State<Shared>, Path(space): Path<String>) -> ApiResult<Value> {
+    s.backend.delete_space(&space).await?;
+    Ok(Json(Value::Null))
+}
+
+/* --------------------- Sessions (space-scoped) ----------------------- */
+
+async fn list_sessions(State(s): State<Shared>, Path(space): Path<String>) -> ApiResult<Vec<Session>> {
+    Ok(Json(s.backend.list_sessions(Some(&space)).await?))
+}
+
+async fn create_session(
+    State(s): State<Shared>,
+    Path(space): Path<String>,
+    Json(req): Json<CreateSessionRequest>,
+) -> ApiResult<Session> {
+    let session = s.backend.create_session(Some(&space), req.metadata).await?;
+    Ok(Json(boot(&s, session)?))
+}
+
+async fn get_session(State(s): State<Shared>, Path((space, id)): Path<(String, String)>) -> ApiResult<Session> {
+    Ok(Json(s.backend.get_session(Some(&space), &id).await?))
+}
+
+async fn update_session(
+    State(s): State<Shared>,
+    Path((space, id)): Path<(String, String)>,
+    Json(req): Json<UpdateSessionRequest>,
+) -> ApiResult<Session> {
+    Ok(Json(s.backend.update_session(Some(&space), &id, &req).await?))
+}
+
+async fn update_session_state(
+    State(s): State<Shared>,
+    Path((space, id)): Path<(String, String)>,
+    Json(req): Json<UpdateSessionStateRequest>,
+) -> ApiResult<Value> {
+    s.backend.update_session_state(Some(&space), &id, req.state).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn pause_session(State(s): State<Shared>, Path((space, id)): Path<(String, String)>) -> ApiResult<Value> {
+    s.backend.pause_session(Some(&space), &id).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn resume_session(State(s): State<Shared>, Path((space, id)): Path<(String, String)>) -> ApiResult<Value> {
+    s.backend.resume_session(Some(&space), &id).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn terminate_session(State(s): State<Shared>, Path((space, id)): Path<(String, String)>) -> ApiResult<Value> {
+    s.backend.terminate_session(Some(&space), &id).await?;
+    Ok(Json(Value::Null))
+}
+
+/* ------------------------------ Messages ----------------------------- */
+
+#[derive(Deserialize)]
+struct LimitQuery {
+    limit: Option<u64>,
+}
+
+async fn get_messages(
+    State(s): State<Shared>,
+    Path((space, id)): Path<(String, String)>,
+    Query(q): Query<LimitQuery>,
+) -> ApiResult<Vec<Message>> {
+    Ok(Json(s.backend.get_messages(Some(&space), &id, q.limit).await?))
+}
+
+async fn send_message(
+    State(s): State<Shared>,
+    Path((space, id)): Path<(String, String)>,
+    Json(req): Json<CreateMessageRequest>,
+) -> ApiResult<Message> {
+    let message = s.backend.send_message(Some(&space), &id, &req.content).await?;
+    think(&s, &message)?;
+    Ok(Json(message))
+}
+
+async fn message_count(State(s): State<Shared>, Path((space, id)): Path<(String, String)>) -> ApiResult<MessageCount> {
+    Ok(Json(s.backend.get_message_count(Some(&space), &id).await?))
+}
+
+async fn clear_messages(State(s): State<Shared>, Path((space, id)): Path<(String, String)>) -> ApiResult<Value> {
+    s.backend.clear_messages(Some(&space), &id).await?;
+    Ok(Json(Value::Null))
+}
+
+/* ------------------------- Sessions (global) ------------------------- */
+
+async fn list_all_sessions(State(s): State<Shared>) -> ApiResult<Vec<Session>> {
+    Ok(Json(s.backend.list_all_sessions().await?))
+}
+
+async fn create_global_session(State(s): State<Shared>, Json(req): Json<CreateSessionRequest>) -> ApiResult<Session> {
+    let session = s.backend.create_global_session(&req).await?;
+    Ok(Json(boot(&s, session)?))
+}
+
+async fn get_global_session(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Session> {
+    Ok(Json(s.backend.get_global_session(&id).await?))
+}
+
+async fn update_global_session(
+    State(s): State<Shared>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateSessionRequest>,
+) -> ApiResult<Session> {
+    Ok(Json(s.backend.update_global_session(&id, &req).await?))
+}
+
+async fn update_global_session_state(
+    State(s): State<Shared>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateSessionStateRequest>,
+) -> ApiResult<Value> {
+    s.backend.update_global_session_state(&id, &req).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn close_session(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Value> {
+    s.backend.close_session(&id).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn restore_session(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Value> {
+    s.backend.restore_session(&id).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn remix_session(
+    State(s): State<Shared>,
+    Path(id): Path<String>,
+    Json(req): Json<CreateSessionRequest>,
+) -> ApiResult<Session> {
+    let session = s.backend.remix_session(&id, &req).await?;
+    Ok(Json(boot(&s, session)?))
+}
+
+async fn delete_global_session(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Value> {
+    s.backend.delete_global_session(&id).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn get_global_messages(
+    State(s): State<Shared>,
+    Path(id): Path<String>,
+    Query(q): Query<LimitQuery>,
+) -> ApiResult<Vec<Message>> {
+    Ok(Json(s.backend.get_global_messages(&id, q.limit).await?))
+}
+
+async fn send_global_message(
+    State(s): State<Shared>,
+    Path(id): Path<String>,
+    Json(req): Json<CreateMessageRequest>,
+) -> ApiResult<Message> {
+    let message = s.backend.send_global_message(&id, &req).await?;
+    think(&s, &message)?;
+    Ok(Json(message))
+}
+
+async fn global_message_count(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<MessageCount> {
+    Ok(Json(s.backend.get_global_message_count(&id).await?))
+}
+
+async fn clear_global_messages(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Value> {
+    s.backend.clear_global_messages(&id).await?;
+    Ok(Json(Value::Null))
+}
+
+/* ------------------------------- Agents ------------------------------ */
+
+async fn list_agents(State(s): State<Shared>, Path(space): Path<String>) -> ApiResult<Vec<Agent>> {
+    Ok(Json(s.backend.list_agents(Some(&space)).await?))
+}
+
+async fn create_agent(
+    State(s): State<Shared>,
+    Path(space): Path<String>,
+    Json(req): Json<CreateAgentRequest>,
+) -> ApiResult<Agent> {
+    let agent = s.backend.create_agent(&space, &req).await?;
+    let now = Utc::now().to_rfc3339();
+    s.backend.push_agent_logs(
+        &space,
+        &agent.name,
+        &[&format!("{now} INFO agent {} created", agent.name)],
+    );
+    Ok(Json(agent))
+}
+
+async fn get_agent(State(s): State<Shared>, Path((space, name)): Path<(String, String)>) -> ApiResult<Agent> {
+    Ok(Json(s.backend.get_agent(&space, &name).await?))
+}
+
+async fn update_agent(
+    State(s): State<Shared>,
+    Path((space, name)): Path<(String, String)>,
+    Json(req): Json<UpdateAgentRequest>,
+) -> ApiResult<Agent> {
+    Ok(Json(s.backend.update_agent(&space, &name, &req).await?))
+}
+
+async fn delete_agent(State(s): State<Shared>, Path((space, name)): Path<(String, String)>) -> ApiResult<Value> {
+    s.backend.delete_agent(&space, &name).await?;
+    Ok(Json(Value::Null))
+}
+
+#[derive(Deserialize)]
+struct LogsQuery {
+    tail: Option<u64>,
+    since: Option<DateTime<Utc>>,
+}
+
+async fn agent_logs(
+    State(s): State<Shared>,
+    Path((space, name)): Path<(String, String)>,
+    Query(q): Query<LogsQuery>,
+) -> Result<String, ApiFailure> {
+    let query = AgentLogsQuery {
+        tail: q.tail,
+        since: q.since,
+        grep: None,
+    };
+    Ok(s.backend.get_agent_log_lines(&space, &name, &query).await?.join("\n"))
+}
+
+async fn update_agent_status(
+    State(s): State<Shared>,
+    Path((space, name)): Path<(String, String)>,
+    Json(req): Json<UpdateAgentStatusRequest>,
+) -> ApiResult<Value> {
+    s.backend.update_agent_status(&space, &name, &req).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn deploy_agent(State(s): State<Shared>, Path((space, name)): Path<(String, String)>) -> ApiResult<Value> {
+    s.backend.deploy_agent(&space, &name).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn stop_agent(State(s): State<Shared>, Path((space, name)): Path<(String, String)>) -> ApiResult<Value> {
+    s.backend.stop_agent(&space, &name).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn list_running_agents(State(s): State<Shared>, Path(space): Path<String>) -> ApiResult<Vec<RunningAgent>> {
+    Ok(Json(s.backend.list_running_agents(&space).await?))
+}
+
+/* ------------------------------ Secrets ------------------------------ */
+
+async fn list_secrets(State(s): State<Shared>, Path(space): Path<String>) -> ApiResult<Vec<Secret>> {
+    Ok(Json(s.backend.list_secrets(Some(&space)).await?))
+}
+
+async fn create_secret(
+    State(s): State<Shared>,
+    Path(space): Path<String>,
+    Json(req): Json<CreateSecretRequest>,
+) -> ApiResult<Secret> {
+    Ok(Json(s.backend.create_secret(&space, &req).await?))
+}
+
+async fn get_secret(State(s): State<Shared>, Path((space, key)): Path<(String, String)>) -> ApiResult<Secret> {
+    Ok(Json(s.backend.get_secret(&space, &key).await?))
+}
+
+// Secret bodies vary between client versions, so only `value` is read
+async fn set_secret(
+    State(s): State<Shared>,
+    Path((space, key)): Path<(String, String)>,
+    Json(body): Json<Value>,
+) -> ApiResult<Secret> {
+    Ok(Json(s.backend.set_secret(&space, &key, secret_value(&body)?).await?))
+}
+
+async fn update_secret(
+    State(s): State<Shared>,
+    Path((space, key)): Path<(String, String)>,
+    Json(req): Json<UpdateSecretRequest>,
+) -> ApiResult<Secret> {
+    Ok(Json(s.backend.update_secret(&space, &key, &req).await?))
+}
+
+async fn delete_secret(State(s): State<Shared>, Path((space, key)): Path<(String, String)>) -> ApiResult<Value> {
+    s.backend.delete_secret(&space, &key).await?;
+    Ok(Json(Value::Null))
+}
+
+fn secret_value(body: &Value) -> Result<&str, ApiFailure> {
+    body.get("value")
+        .and_then(Value::as_str)
+        .ok_or_else(|| RaworcError::validation_error("value is required").into())
+}
+
+/* ------------------------------- Builds ------------------------------ */
+
+async fn create_build(
+    State(s): State<Shared>,
+    Path(space): Path<String>,
+    Json(req): Json<CreateBuildRequest>,
+) -> ApiResult<Build> {
+    let build = s.backend.create_build(&space, &req).await?;
+    let state = s.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(state.options.build_time).await;
+        let now = Utc::now().to_rfc3339();
+        let logs = format!(
+            "{now} Step 1/3 : FROM raworc/base\n{now} Step 2/3 : COPY . /app\n{now} Step 3/3 : RUN setup\n{now} Build complete\n"
+        );
+        let _ = state.backend.finish_build(&space, BuildStatus::Completed, &logs);
+    });
+    Ok(Json(build))
+}
+
+async fn latest_build(State(s): State<Shared>, Path(space): Path<String>) -> ApiResult<Build> {
+    Ok(Json(s.backend.get_latest_build(&space).await?))
+}
+
+async fn get_build(State(s): State<Shared>, Path((space, id)): Path<(String, String)>) -> ApiResult<Build> {
+    Ok(Json(s.backend.get_build(&space, &id).await?))
+}
+
+/* ------------------------ Service accounts --------------------------- */
+
+async fn list_service_accounts(State(s): State<Shared>) -> ApiResult<Vec<ServiceAccount>> {
+    Ok(Json(s.backend.list_service_accounts().await?))
+}
+
+async fn create_service_account(
+    State(s): State<Shared>,
+    Json(req): Json<CreateServiceAccountRequest>,
+) -> ApiResult<ServiceAccount> {
+    Ok(Json(s.backend.create_service_account(&req).await?))
+}
+
+async fn get_service_account(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<ServiceAccount> {
+    Ok(Json(s.backend.get_service_account(&id).await?))
+}
+
+async fn update_service_account(
+    State(s): State<Shared>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateServiceAccountRequest>,
+) -> ApiResult<ServiceAccount> {
+    Ok(Json(s.backend.update_service_account(&id, &req).await?))
+}
+
+async fn delete_service_account(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Value> {
+    s.backend.delete_service_account(&id).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn update_service_account_password(
+    State(s): State<Shared>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdatePasswordRequest>,
+) -> ApiResult<Value> {
+    s.backend.update_service_account_password(&id, &req).await?;
+    Ok(Json(Value::Null))
+}
+
+/* ------------------------- Roles and bindings ------------------------ */
+
+async fn list_roles(State(s): State<Shared>) -> ApiResult<Vec<Role>> {
+    Ok(Json(s.backend.list_roles().await?))
+}
+
+async fn create_role(State(s): State<Shared>, Json(req): Json<CreateRoleRequest>) -> ApiResult<Role> {
+    Ok(Json(s.backend.create_role(&req).await?))
+}
+
+async fn get_role(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Role> {
+    Ok(Json(s.backend.get_role(&id).await?))
+}
+
+async fn delete_role(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Value> {
+    s.backend.delete_role(&id).await?;
+    Ok(Json(Value::Null))
+}
+
+async fn list_role_bindings(State(s): State<Shared>) -> ApiResult<Vec<RoleBinding>> {
+    Ok(Json(s.backend.list_role_bindings().await?))
+}
+
+async fn create_role_binding(
+    State(s): State<Shared>,
+    Json(req): Json<CreateRoleBindingRequest>,
+) -> ApiResult<RoleBinding> {
+    Ok(Json(s.backend.create_role_binding(&req).await?))
+}
+
+async fn get_role_binding(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<RoleBinding> {
+    Ok(Json(s.backend.get_role_binding(&id).await?))
+}
+
+async fn delete_role_binding(State(s): State<Shared>, Path(id): Path<String>) -> ApiResult<Value> {
+    s.backend.delete_role_binding(&id).await?;
+    Ok(Json(Value::Null))
+}
+
+/* ------------------------- Session lifecycle ------------------------- */
+
+/// New sessions start in INIT and become IDLE once their container is "up"
+fn boot(s: &Shared, session: Session) -> Result<Session, ApiFailure> {
+    s.backend.set_session_state(&session.id, SessionState::Init)?;
+    let state = s.clone();
+    let id = session.id.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(state.options.think_time).await;
+        if let Ok(current) = state.backend.get_global_session(&id).await {
+            if current.state == SessionState::Init {
+                let _ = state.backend.set_session_state(&id, SessionState::Idle);
+            }
+        }
+    });
+    Ok(Session {
+        state: SessionState::Init,
+        ..session
+    })
+}
+
+/// Run the session for `think_time`, then post the next scripted reply and go IDLE.
+/// A session that is paused, closed or terminated in the meantime does not reply.
+fn think(s: &Shared, message: &Message) -> Result<(), ApiFailure> {
+    s.backend.set_session_state(&message.session_id, SessionState::Running)?;
+    let reply = next_reply(s, &message.content);
+    let state = s.clone();
+    let id = message.session_id.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(state.options.think_time).await;
+        let Ok(current) = state.backend.get_global_session(&id).await else {
+            return;
+        };
+        if current.state == SessionState::Running {
+            let _ = state.backend.push_message(&id, MessageRole::Assistant, &reply);
+            let _ = state.backend.set_session_state(&id, SessionState::Idle);
+        }
+    });
+    Ok(())
+}
+
+fn next_reply(s: &MockState, content: &str) -> String {
+    let replies = &s.options.replies;
+    if replies.is_empty() {
+        return DEFAULT_REPLY.replace("{content}", content);
+    }
+    let i = s.next_reply.fetch_add(1, Ordering::Relaxed) % replies.len();
+    replies[i].replace("{content}", content)
+}
+
@@ -1,3 +1,4 @@
+use crate::redact;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -80,6 +81,14 @@ pub struct CreateMessageRequest {
     pub content: String,
 }
 
+/// Result of sending a message and waiting for the agent to answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageExchange {
+    pub sent: Message,
+    pub reply: Option<Message>,
+    pub state: SessionState,
+}
+
 /// Message count response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageCount {
@@ -191,6 +200,26 @@ pub struct AgentLogsResponse {
     pub logs: Vec<String>,
 }
 
+/// A request a dry run stopped short of sending
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedRequest {
+    pub method: String,
+    /// URL path, e.g. `/api/v0/spaces/prod/secrets/API_KEY`
+    pub path: String,
+    pub url: String,
+    /// JSON body with credentials and secret values redacted
+    pub body: Option<serde_json::Value>,
+}
+
+/// Agent log query options. `tail` and `since` are sent to the API and
+/// re-applied locally; `grep` is a regex matched locally.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentLogsQuery {
+    pub tail: Option<u64>,
+    pub since: Option<DateTime<Utc>>,
+    pub grep: Option<String>,
+}
+
 /// Secret model
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Secret {
@@ -235,7 +264,7 @@ pub struct ServiceAccount {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateServiceAccountRequest {
     pub user: String,
-    pub pass: String,
+    pub pass: redact::Secret<String>,
     pub space: Option<String>,
     pub description: Option<String>,
 }
@@ -251,8 +280,8 @@ pub struct UpdateServiceAccountRequest {
 /// Update password request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdatePasswordRequest {
-    pub current_password: String,
-    pub new_password: String,
+    pub current_password: redact::Secret<String>,
+    pub new_password: redact::Secret<String>,
 }
 
 /// Role rule
@@ -303,7 +332,7 @@ pub struct CreateRoleBindingRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthRequest {
     pub user: String,
-    pub pass: String,
+    pub pass: redact::Secret<String>,
 }
 
 /// Authentication response
@@ -0,0 +1,387 @@
+//! Which tools the server exposes, and which spaces they may touch.
+//! - `read_only` drops every tool that changes state upstream
+//! - `tools.allow` / `tools.deny` are glob lists (`*` and `?`); deny wins over allow
+//! - Filtered tools are left out of `tools/list` and refused at dispatch
+//! - `allowed_spaces` globs limit the space each call resolves to
+//! - A policy file adds argument-aware rules that allow, deny or require confirmation
+
+use crate::api::RaworcApi;
+use crate::error::{RaworcError, RaworcResult};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Tools that change state upstream, as globs
+const MUTATING_TOOLS: &[&str] = &[
+    "create_*",
+    "update_*",
+    "delete_*",
+    "set_*",
+    "clear_*",
+    "send_message",
+    "ask_session",
+    "pause_*",
+    "resume_*",
+    "terminate_*",
+    "close_*",
+    "restore_*",
+    "remix_*",
+    "deploy_*",
+    "stop_*",
+];
+
+/// Tools that act on no particular space unless given a `space` argument
+const SPACELESS_TOOLS: &[&str] = &[
+    "health_check",
+    "get_version",
+    "list_spaces",
+    "audit_log",
+    "*service_account*",
+    "list_roles",
+    "*_role",
+    "*role_binding*",
+];
+
+/// Tools whose `name` argument is the space itself
+const SPACE_ADMIN_TOOLS: &[&str] = &["create_space", "get_space", "update_space", "delete_space"];
+
+/// Tools that address a session by id alone, whatever its space
+const GLOBAL_SESSION_TOOLS: &[&str] = &["close_session", "restore_session", "remix_session"];
+
+/// Where a tool call's space comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpaceTarget<'a> {
+    /// The call is not scoped to a space
+    None,
+    /// Named by the arguments
+    Named(&'a str),
+    /// The configured default space, as `RaworcClient::space()` resolves it
+    Default,
+    /// The space of the session with `id`, plus the `space` the arguments name
+    /// (the target of `remix_session`), if any
+    Session { id: &'a str, space: Option<&'a str> },
+}
+
+/// The space a call to `tool` with `arguments` will act on
+pub fn space_target<'a>(tool: &str, arguments: &'a Value) -> SpaceTarget<'a> {
+    let arg = |key: &str| arguments.get(key).and_then(Value::as_str);
+    if matches_any(SPACE_ADMIN_TOOLS, tool) {
+        return arg("name").map_or(SpaceTarget::None, SpaceTarget::Named);
+    }
+    // The session's own space always counts; a `space` argument cannot stand in for it
+    if matches_any(GLOBAL_SESSION_TOOLS, tool) {
+        if let Some(id) = arg("session_id") {
+            return SpaceTarget::Session { id, space: arg("space") };
+        }
+    }
+    if let Some(space) = arg("space") {
+        return SpaceTarget::Named(space);
+    }
+    if matches_any(SPACELESS_TOOLS, tool) {
+        return SpaceTarget::None;
+    }
+    SpaceTarget::Default
+}
+
+/// Every space a call to `tool` acts on, empty when it is not scoped to one.
+/// Tools that name a session are resolved through `client`: the session's
+/// space comes first, then any other space the arguments name.
+pub async fn resolve_spaces<C: RaworcApi + ?Sized>(
+    client: &C,
+    tool: &str,
+    arguments: &Value,
+    default_space: &str,
+) -> RaworcResult<Vec<String>> {
+    Ok(match space_target(tool, arguments) {
+        SpaceTarget::None => Vec::new(),
+        SpaceTarget::Named(space) => vec![space.to_string()],
+        SpaceTarget::Default => vec![default_space.to_string()],
+        SpaceTarget::Session { id, space } => {
+            let mut spaces = vec![client.get_global_session(id).await?.space];
+            if let Some(space) = space.filter(|s| *s != spaces[0]) {
+                spaces.push(space.to_string());
+            }
+            spaces
+        }
+    })
+}
+
+/// Arguments as policy rules see them: `space` is a space the call acts on
+/// (see `resolve_spaces`), whichever argument named it
+pub fn with_resolved_space(arguments: &Value, space: Option<&str>) -> Value {
+    let mut arguments = arguments.clone();
+    if let (Some(space), Some(map)) = (space, arguments.as_object_mut()) {
+        map.insert("space".to_string(), Value::String(space.to_string()));
+    }
+    arguments
+}
+
+/// Allow and deny lists for tool names
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolFilter {
+    /// Only tools matching one of these; every tool when empty
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Never tools matching one of these
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl ToolFilter {
+    pub fn permits(&self, tool: &str) -> bool {
+        (self.allow.is_empty() || matches_any(&self.allow, tool)) && !matches_any(&self.deny, tool)
+    }
+}
+
+/// True for tools that create, change or delete something
+pub fn is_mutating(tool: &str) -> bool {
+    MUTATING_TOOLS.iter().any(|pattern| glob_match(pattern, tool))
+}
+
+/// Keep only the tools `enabled` accepts in a `tools/list` payload
+pub fn filter_tool_list(mut tools: Value, enabled: impl Fn(&str) -> bool) -> Value {
+    if let Some(list) = tools.get_mut("tools").and_then(Value::as_array_mut) {
+        list.retain(|tool| tool.get("name").and_then(Value::as_str).is_some_and(&enabled));
+    }
+    tools
+}
+
+pub fn matches_any<S: AsRef<str>>(patterns: &[S], text: &str) -> bool {
+    patterns.iter().any(|p| glob_match(p.as_ref(), text))
+}
+
+/// Shell-style match: `*` is any run of characters, `?` any single one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text index it is currently absorbing up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// What a policy rule decides for a matching call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    Allow,
+    Deny,
+    /// Run only after the user confirms through the host (MCP elicitation)
+    RequireConfirmation,
+}
+
+impl PolicyAction {
+    /// Allow < RequireConfirmation < Deny
+    fn strictness(&self) -> u8 {
+        match self {
+            Self::Allow => 0,
+            Self::RequireConfirmation => 1,
+            Self::Deny => 2,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+            Self::RequireConfirmation => "require_confirmation",
+        }
+    }
+}
+
+/// Test on one argument. A bare string is shorthand for `{ matches = "..." }`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ArgCondition {
+    Glob(String),
+    Test(ArgTest),
+}
+
+/// Every field that is set must hold
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArgTest {
+    /// Glob on the value (strings as-is, anything else as JSON)
+    pub matches: Option<String>,
+    /// Value longer than this many characters
+    pub longer_than: Option<usize>,
+    /// Value equal to this
+    pub equals: Option<Value>,
+    /// Argument given (`true`) or absent (`false`)
+    pub present: Option<bool>,
+}
+
+/// One rule: a tool-name glob, argument conditions and the decision
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    pub tool: String,
+    #[serde(default)]
+    pub when: BTreeMap<String, ArgCondition>,
+    pub action: PolicyAction,
+    pub reason: Option<String>,
+}
+
+/// Rules from a policy file, evaluated in order; the first match decides and
+/// calls no rule matches are allowed.
+///
+/// ```toml
+/// [[rules]]
+/// tool = "delete_*"
+/// when = { space = "prod-*" }
+/// action = "deny"
+/// reason = "Nothing is deleted in production spaces"
+///
+/// [[rules]]
+/// tool = "send_message"
+/// when = { content = { longer_than = 10000 } }
+/// action = "require_confirmation"
+/// reason = "Very long message"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// Outcome of evaluating a call against a `Policy`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyDecision {
+    pub action: PolicyAction,
+    /// 1-based index of the deciding rule; `None` when no rule matched
+    pub rule: Option<usize>,
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for PolicyDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.action.as_str())?;
+        match self.rule {
+            Some(rule) => write!(f, " (rule {})", rule)?,
+            None => write!(f, " (no rule matched)")?,
+        }
+        if let Some(reason) = &self.reason {
+            write!(f, ": {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl Policy {
+    /// Parse a policy file
+    pub fn read(path: &Path) -> RaworcResult<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            RaworcError::ConfigError(format!("Cannot read policy file {}: {}", path.display(), e))
+        })?;
+        Self::from_toml(&text).map_err(|e| match e {
+            RaworcError::ConfigError(msg) => RaworcError::ConfigError(format!("{}: {}", path.display(), msg)),
+            other => other,
+        })
+    }
+
+    pub fn from_toml(text: &str) -> RaworcResult<Self> {
+        toml::from_str(text).map_err(|e| RaworcError::ConfigError(format!("Invalid policy: {}", e)))
+    }
+
+    /// Decision for calling `tool` with `arguments`
+    pub fn evaluate(&self, tool: &str, arguments: &Value) -> PolicyDecision {
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(tool, arguments))
+            .map(|(i, rule)| PolicyDecision {
+                action: rule.action,
+                rule: Some(i + 1),
+                reason: rule.reason.clone(),
+            })
+            .unwrap_or(PolicyDecision {
+                action: PolicyAction::Allow,
+                rule: None,
+                reason: None,
+            })
+    }
+
+    /// Decision for a call acting on `spaces` (see `resolve_spaces`): the rules
+    /// see each space in turn as the `space` argument, and the strictest
+    /// decision wins. Calls scoped to no space are evaluated as given.
+    pub fn evaluate_in(&self, tool: &str, arguments: &Value, spaces: &[String]) -> PolicyDecision {
+        if spaces.is_empty() {
+            return self.evaluate(tool, arguments);
+        }
+        spaces
+            .iter()
+            .map(|space| self.evaluate(tool, &with_resolved_space(arguments, Some(space))))
+            .reduce(|strictest, decision| {
+                if decision.action.strictness() > strictest.action.strictness() {
+                    decision
+                } else {
+                    strictest
+                }
+            })
+            .expect("spaces is not empty")
+    }
+}
+
+impl PolicyRule {
+    pub fn matches(&self, tool: &str, arguments: &Value) -> bool {
+        glob_match(&self.tool, tool)
+            && self
+                .when
+                .iter()
+                .all(|(key, condition)| condition.holds(arguments.get(key).filter(|v| !v.is_null())))
+    }
+}
+
+impl ArgCondition {
+    fn holds(&self, value: Option<&Value>) -> bool {
+        match self {
+            Self::Glob(pattern) => value.is_some_and(|v| glob_match(pattern, &value_text(v))),
+            Self::Test(test) => {
+                if let Some(present) = test.present {
+                    if present != value.is_some() {
+                        return false;
+                    }
+                }
+                let needs_value = test.matches.is_some() || test.longer_than.is_some() || test.equals.is_some();
+                let Some(value) = value else {
+                    return !needs_value;
+                };
+                test.matches.as_ref().is_none_or(|p| glob_match(p, &value_text(value)))
+                    && test.longer_than.is_none_or(|n| value_text(value).chars().count() > n)
+                    && test.equals.as_ref().is_none_or(|expected| expected == value)
+            }
+        }
+    }
+}
+
+/// Strings as-is, anything else as JSON
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
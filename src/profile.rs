@@ -0,0 +1,403 @@
+//! Named profiles from a TOML config file, and `Config::load`.
+//! Settings are layered, later layers winning:
+//! 1. built-in defaults
+//! 2. the selected profile from the config file
+//! 3. `RAWORC_*` environment variables
+//! 4. command-line flags (`LoadOptions`)
+//!
+//! ```toml
+//! default_profile = "staging"
+//!
+//! [profiles.staging]
+//! api_url = "https://staging.example.com/api/v0"
+//! auth_token = "..."
+//! default_space = "sandbox"
+//! timeout_seconds = 60
+//!
+//! [profiles.prod]
+//! api_url = "https://api.remoteagent.com/api/v0"
+//! username = "ops"
+//! password = "..."
+//!
+//! [profiles.staging.spaces.sandbox]
+//! reveal_secrets = true
+//! ```
+
+use crate::cache::CacheConfig;
+use crate::cassette::CassetteMode;
+use crate::audit::AuditConfig;
+use crate::error::{RaworcError, RaworcResult};
+use crate::hooks::Hook;
+use crate::policy::{Policy, ToolFilter};
+use crate::redact::Secret;
+use crate::Config;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// API URL used when no layer sets one
+pub const DEFAULT_API_URL: &str = "https://api.remoteagent.com/api/v0";
+
+/// Contents of `config.toml`
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Profile used when none is selected; otherwise `default`, if present
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// One named profile. Authenticate with either `auth_token` or `username`/`password`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub api_url: Option<String>,
+    pub auth_token: Option<Secret<String>>,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    pub default_space: Option<String>,
+    pub timeout_seconds: Option<u64>,
+    /// Expose only tools that do not change state upstream
+    pub read_only: Option<bool>,
+    /// `[profiles.<name>.tools]` with `allow` and `deny` globs
+    pub tools: Option<ToolFilter>,
+    /// Globs of the spaces tools may act on
+    pub allowed_spaces: Option<Vec<String>>,
+    /// Policy rules file; relative paths are resolved against the config file's directory
+    pub policy_file: Option<PathBuf>,
+    /// `[[profiles.<name>.hooks]]` entries
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+    /// `[profiles.<name>.audit]`; a relative `path` is resolved like `policy_file`
+    pub audit: Option<AuditConfig>,
+    /// Per-space settings, keyed by space name
+    #[serde(default)]
+    pub spaces: BTreeMap<String, SpaceSettings>,
+}
+
+/// Settings for one space within a profile
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpaceSettings {
+    /// Return secret values from this space unmasked
+    #[serde(default)]
+    pub reveal_secrets: bool,
+}
+
+impl ConfigFile {
+    /// Parse a config file
+    pub fn read(path: &Path) -> RaworcResult<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            RaworcError::ConfigError(format!("Cannot read config file {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&text).map_err(|e| {
+            RaworcError::ConfigError(format!("Invalid config file {}: {}", path.display(), e))
+        })
+    }
+
+    /// The profile named `name`, or the file's default profile when `name` is `None`
+    pub fn profile(&self, name: Option<&str>) -> RaworcResult<Option<&Profile>> {
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(name) => name,
+            None => return Ok(self.profiles.get("default")),
+        };
+        self.profiles.get(name).map(Some).ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            RaworcError::ConfigError(format!(
+                "Unknown profile '{}' (available: {})",
+                name,
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            ))
+        })
+    }
+}
+
+/// `$XDG_CONFIG_HOME/raworc-mcp/config.toml`, falling back to `~/.config`
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("raworc-mcp").join("config.toml"))
+}
+
+/// Inputs to `Config::load`: where to find profiles, and command-line overrides
+#[derive(Debug, Default, Clone)]
+pub struct LoadOptions {
+    /// Config file; when `None`, `default_config_path()` is used if it exists
+    pub config_file: Option<PathBuf>,
+    /// Profile to select; when `None`, the file's default profile
+    pub profile: Option<String>,
+    pub api_url: Option<String>,
+    pub default_space: Option<String>,
+    pub timeout_seconds: Option<u64>,
+    /// `--read-only`; can only turn read-only mode on
+    pub read_only: bool,
+    /// `--dry-run`; can only turn dry-run mode on
+    pub dry_run: bool,
+    /// Policy file; overrides the profile and `RAWORC_POLICY`
+    pub policy_file: Option<PathBuf>,
+}
+
+impl Config {
+    /// Build a validated configuration from the config file profile, the
+    /// environment and `options`, in that order of precedence (lowest first)
+    pub fn load(options: &LoadOptions) -> RaworcResult<Self> {
+        let file_path = options
+            .config_file
+            .clone()
+            .or_else(|| default_config_path().filter(|p| p.is_file()));
+        let file = file_path.as_deref().map(ConfigFile::read).transpose()?;
+        let profile = match &file {
+            Some(file) => file.profile(options.profile.as_deref())?.cloned(),
+            None if options.profile.is_some() => {
+                return Err(RaworcError::ConfigError(format!(
+                    "Profile '{}' requested but no config file was found",
+                    options.profile.as_deref().unwrap_or_default()
+                )));
+            }
+            None => None,
+        };
+
+        let mut config = Config::new(DEFAULT_API_URL.to_string());
+        let mut policy_file = None;
+        if let Some(mut profile) = profile {
+            let config_dir = file_path.as_deref().and_then(Path::parent).unwrap_or(Path::new(""));
+            policy_file = profile.policy_file.take().map(|p| config_dir.join(p));
+            if let Some(audit) = &mut profile.audit {
+                audit.path = config_dir.join(&audit.path);
+            }
+            config.apply_profile(profile);
+        }
+        config.apply_env()?;
+        if let Some(api_url) = &options.api_url {
+            config.api_url = Some(api_url.clone());
+        }
+        if let Some(space) = &options.default_space {
+            config.default_space = Some(space.clone());
+        }
+        if let Some(timeout) = options.timeout_seconds {
+            config.timeout_seconds = Some(timeout);
+        }
+        config.read_only |= options.read_only;
+        config.dry_run |= options.dry_run;
+        let policy_file = options
+            .policy_file
+            .clone()
+            .or_else(|| std::env::var_os("RAWORC_POLICY").filter(|v| !v.is_empty()).map(PathBuf::from))
+            .or(policy_file);
+        if let Some(path) = policy_file {
+            config.policy = Some(Policy::read(&path)?);
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that the settings are usable together
+    pub fn validate(&self) -> RaworcResult<()> {
+        let api_url = self
+            .api_url
+            .as_deref()
+            .ok_or_else(|| RaworcError::ConfigError("api_url is required".to_string()))?;
+        let url = url::Url::parse(api_url)
+            .map_err(|e| RaworcError::ConfigError(format!("Invalid api_url '{}': {}", api_url, e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(RaworcError::ConfigError(format!(
+                "api_url must be http or https, got '{}'",
+                api_url
+            )));
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(RaworcError::ConfigError("timeout_seconds must be greater than 0".to_string()));
+        }
+        if self.username.is_some() != self.password.is_some() {
+            return Err(RaworcError::ConfigError(
+                "username and password must be set together".to_string(),
+            ));
+        }
+        if let Some(hook) = self.hooks.iter().find(|h| h.command.is_empty()) {
+            return Err(RaworcError::ConfigError(format!("hook for '{}' has an empty command", hook.tool)));
+        }
+        if self.audit.as_ref().is_some_and(|a| a.max_bytes == 0) {
+            return Err(RaworcError::ConfigError("audit max_bytes must be greater than 0".to_string()));
+        }
+        if self.audit.as_ref().is_some_and(|a| a.max_files == 0) {
+            return Err(RaworcError::ConfigError("audit max_files must be greater than 0".to_string()));
+        }
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            return Err(RaworcError::ConfigError(
+                "client_cert and client_key must be set together".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn apply_profile(&mut self, profile: Profile) {
+        if profile.auth_token.is_some() || profile.username.is_some() {
+            self.auth_token = profile.auth_token;
+            self.username = profile.username;
+            self.password = profile.password;
+        }
+        if let Some(api_url) = profile.api_url {
+            self.api_url = Some(api_url);
+        }
+        if let Some(space) = profile.default_space {
+            self.default_space = Some(space);
+        }
+        if let Some(timeout) = profile.timeout_seconds {
+            self.timeout_seconds = Some(timeout);
+        }
+        if let Some(read_only) = profile.read_only {
+            self.read_only = read_only;
+        }
+        if let Some(tools) = profile.tools {
+            self.tools = tools;
+        }
+        if let Some(spaces) = profile.allowed_spaces {
+            self.allowed_spaces = spaces;
+        }
+        self.hooks = profile.hooks;
+        self.audit = profile.audit;
+        self.reveal_secrets = profile
+            .spaces
+            .into_iter()
+            .filter(|(_, settings)| settings.reveal_secrets)
+            .map(|(space, _)| space)
+            .collect();
+    }
+
+    /// Overlay `RAWORC_*` (and standard proxy) environment variables
+    fn apply_env(&mut self) -> RaworcResult<()> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if let Some(api_url) = var("RAWORC_API_URL") {
+            self.api_url = Some(api_url);
+        }
+        // Credentials from the environment replace the profile's auth method
+        // entirely; they must name exactly one method, complete
+        match (var("RAWORC_AUTH_TOKEN"), var("RAWORC_USERNAME"), var("RAWORC_PASSWORD")) {
+            (None, None, None) => {}
+            (Some(token), None, None) => {
+                self.auth_token = Some(token.into());
+                self.username = None;
+                self.password = None;
+            }
+            (None, Some(username), Some(password)) => {
+                self.auth_token = None;
+                self.username = Some(username);
+                self.password = Some(password.into());
+            }
+            (Some(_), _, _) => {
+                return Err(RaworcError::ConfigError(
+                    "Set either RAWORC_AUTH_TOKEN or RAWORC_USERNAME and RAWORC_PASSWORD, not both".to_string(),
+                ))
+            }
+            (None, Some(_), None) => {
+                return Err(RaworcError::ConfigError("RAWORC_USERNAME is set without RAWORC_PASSWORD".to_string()))
+            }
+            (None, None, Some(_)) => {
+                return Err(RaworcError::ConfigError("RAWORC_PASSWORD is set without RAWORC_USERNAME".to_string()))
+            }
+        }
+        if let Some(space) = var("RAWORC_DEFAULT_SPACE") {
+            self.default_space = Some(space);
+        }
+        if let Some(timeout) = var("RAWORC_TIMEOUT") {
+            self.timeout_seconds = Some(parse_env("RAWORC_TIMEOUT", &timeout)?);
+        }
+        if let Some(max_chars) = var("RAWORC_MAX_OUTPUT_CHARS") {
+            self.max_output_chars = Some(parse_env("RAWORC_MAX_OUTPUT_CHARS", &max_chars)?);
+        }
+        if let Some(spaces) = var("RAWORC_REVEAL_SECRETS") {
+            self.reveal_secrets.extend(split_list(&spaces));
+        }
+        if let Some(read_only) = var("RAWORC_READ_ONLY") {
+            self.read_only = matches!(read_only.as_str(), "1" | "true" | "on");
+        }
+        if let Some(dry_run) = var("RAWORC_DRY_RUN") {
+            self.dry_run = matches!(dry_run.as_str(), "1" | "true" | "on");
+        }
+        if let Some(allow) = var("RAWORC_TOOLS_ALLOW") {
+            self.tools.allow = split_list(&allow);
+        }
+        if let Some(deny) = var("RAWORC_TOOLS_DENY") {
+            self.tools.deny = split_list(&deny);
+        }
+        if let Some(spaces) = var("RAWORC_ALLOWED_SPACES") {
+            self.allowed_spaces = split_list(&spaces);
+        }
+        if let Some(path) = var("RAWORC_AUDIT_LOG") {
+            match &mut self.audit {
+                Some(audit) => audit.path = path.into(),
+                None => self.audit = Some(AuditConfig::new(path)),
+            }
+        }
+        if let Some(audit) = &mut self.audit {
+            if let Some(bytes) = var("RAWORC_AUDIT_MAX_BYTES") {
+                audit.max_bytes = parse_env("RAWORC_AUDIT_MAX_BYTES", &bytes)?;
+            }
+            if let Some(files) = var("RAWORC_AUDIT_MAX_FILES") {
+                audit.max_files = parse_env("RAWORC_AUDIT_MAX_FILES", &files)?;
+            }
+            if let Some(chain) = var("RAWORC_AUDIT_HASH_CHAIN") {
+                audit.hash_chain = matches!(chain.as_str(), "1" | "true" | "on");
+            }
+        }
+        if let Some(format) = var("RAWORC_OUTPUT_FORMAT") {
+            self.output_format = Some(format.parse()?);
+        }
+
+        // RAWORC_RECORD / RAWORC_REPLAY select a cassette file
+        self.cassette = match (var("RAWORC_RECORD"), var("RAWORC_REPLAY")) {
+            (Some(_), Some(_)) => {
+                return Err(RaworcError::ConfigError(
+                    "RAWORC_RECORD and RAWORC_REPLAY are mutually exclusive".to_string(),
+                ))
+            }
+            (Some(path), None) => Some(CassetteMode::Record(path.into())),
+            (None, Some(path)) => Some(CassetteMode::Replay(path.into())),
+            (None, None) => self.cassette.take(),
+        };
+
+        // RAWORC_CACHE=1 enables the response cache; RAWORC_CACHE_TTL also sets its TTL in seconds
+        if let Some(secs) = var("RAWORC_CACHE_TTL") {
+            let secs = parse_env("RAWORC_CACHE_TTL", &secs)?;
+            self.cache = Some(CacheConfig::with_ttl(Duration::from_secs(secs)));
+        } else if var("RAWORC_CACHE").is_some_and(|v| matches!(v.as_str(), "1" | "true" | "on")) {
+            self.cache = Some(CacheConfig::default());
+        }
+
+        if let Some(proxy) = var("RAWORC_PROXY").or_else(|| var("HTTPS_PROXY")) {
+            self.proxy = Some(proxy);
+        }
+        if let Some(no_proxy) = var("RAWORC_NO_PROXY").or_else(|| var("NO_PROXY")) {
+            self.no_proxy = Some(no_proxy);
+        }
+        if let Some(path) = var("RAWORC_CA_CERT") {
+            self.ca_cert = Some(path.into());
+        }
+        if let Some(path) = var("RAWORC_CLIENT_CERT") {
+            self.client_cert = Some(path.into());
+        }
+        if let Some(path) = var("RAWORC_CLIENT_KEY") {
+            self.client_key = Some(path.into());
+        }
+        Ok(())
+    }
+}
+
+/// Comma-separated list, ignoring blanks
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, value: &str) -> RaworcResult<T>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| RaworcError::ConfigError(format!("Invalid {} '{}': {}", name, value, e)))
+}
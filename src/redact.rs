@@ -0,0 +1,163 @@
+//! Keeping credentials out of logs, traces and `Debug` output.
+//! - `Secret<T>` wraps a credential: `Debug` prints `[REDACTED]`, serde passes the value through
+//! - `redact_value` masks credential keys anywhere in a JSON value
+//! - `redact_result` does the same for tool result text passed to hooks, plus the
+//!   result fields each tool's `_meta.sensitiveResultFields` lists
+//! - `redact_arguments` also masks the arguments each tool's schema marks `writeOnly`
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Placeholder written in place of redacted values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Body keys that are always redacted
+const SENSITIVE_KEYS: &[&str] = &["pass", "password", "current_password", "new_password", "token", "auth_token"];
+
+/// A credential that must not be logged. Read it with `expose`.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// The wrapped value; keep it out of log and error messages
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+/// Schema-declared sensitive fields of one tool
+#[derive(Default)]
+struct ToolSecrets {
+    /// Input properties marked `"writeOnly": true`
+    arguments: Vec<String>,
+    /// Result fields listed in `_meta.sensitiveResultFields`
+    result_fields: Vec<String>,
+}
+
+fn tool_secrets(tool: &str) -> Option<&'static ToolSecrets> {
+    static SECRETS: OnceLock<HashMap<String, ToolSecrets>> = OnceLock::new();
+    let secrets = SECRETS.get_or_init(|| {
+        let capabilities: Value = serde_json::from_str(crate::CAPABILITIES).expect("CAPABILITIES is valid JSON");
+        let tools = capabilities["tools"].as_array().cloned().unwrap_or_default();
+        tools
+            .iter()
+            .filter_map(|tool| {
+                let name = tool["name"].as_str()?;
+                let arguments = tool
+                    .pointer("/inputSchema/properties")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                    .filter(|(_, schema)| schema["writeOnly"] == Value::Bool(true))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                let result_fields = tool
+                    .pointer("/_meta/sensitiveResultFields")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|field| field.as_str().map(str::to_string))
+                    .collect();
+                Some((name.to_string(), ToolSecrets { arguments, result_fields }))
+            })
+            .collect()
+    });
+    secrets.get(tool)
+}
+
+/// Arguments of `tool` whose values are credentials or secrets: the properties
+/// its input schema in `CAPABILITIES` marks `"writeOnly": true`
+pub fn sensitive_arguments(tool: &str) -> &'static [String] {
+    tool_secrets(tool).map_or(&[], |s| s.arguments.as_slice())
+}
+
+/// Result fields of `tool` that hold secret values: the
+/// `_meta.sensitiveResultFields` of its `CAPABILITIES` entry
+pub fn sensitive_result_fields(tool: &str) -> &'static [String] {
+    tool_secrets(tool).map_or(&[], |s| s.result_fields.as_slice())
+}
+
+/// Tool-call arguments safe to log: the tool's sensitive arguments and any
+/// credential keys are replaced with `[REDACTED]`
+pub fn redact_arguments(tool: &str, arguments: &Value) -> Value {
+    let mut redacted = redact_value(arguments.clone(), false);
+    if let Some(map) = redacted.as_object_mut() {
+        for key in sensitive_arguments(tool) {
+            if let Some(value) = map.get_mut(key) {
+                *value = Value::String(REDACTED.to_string());
+            }
+        }
+    }
+    redacted
+}
+
+/// Tool result text safe to hand to hooks: JSON results go through
+/// `redact_value`, with the tool's `sensitive_result_fields` masked as well.
+/// Other formats (Markdown, YAML) cannot be masked field by field, so the
+/// result of a tool with sensitive fields is replaced whole.
+pub fn redact_result(tool: &str, text: &str) -> String {
+    let fields = sensitive_result_fields(tool);
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) => redact_fields(value, fields).to_string(),
+        Err(_) if !fields.is_empty() => REDACTED.to_string(),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// Redact credentials and tokens anywhere in `value`; with `secrets`, also any
+/// non-null `value` field
+pub fn redact_value(value: Value, secrets: bool) -> Value {
+    let fields: &[&str] = if secrets { &["value"] } else { &[] };
+    redact_fields(value, fields)
+}
+
+/// Redact credentials and tokens anywhere in `value`, and any non-null field
+/// named in `fields`
+fn redact_fields<S: AsRef<str>>(value: Value, fields: &[S]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let field = fields.iter().any(|f| f.as_ref() == k);
+                    if SENSITIVE_KEYS.contains(&k.as_str()) || (field && !v.is_null()) {
+                        (k, Value::String(REDACTED.to_string()))
+                    } else {
+                        (k, redact_fields(v, fields))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| redact_fields(v, fields)).collect()),
+        other => other,
+    }
+}
@@ -0,0 +1,65 @@
+//! OpenTelemetry export of tracing spans (feature `otel`).
+//! - `mcp.tool_call` spans (tool, redacted arguments, outcome) come from `RaworcMcpServer::handle_tool_call`
+//! - `raworc.http` child spans (route template, status, retries, latency) come from `RaworcClient`
+//! - Spans are batched and sent over OTLP/HTTP; the guard flushes them on drop
+
+use crate::error::{RaworcError, RaworcResult};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Collector endpoint used when only `RAWORC_OTEL=1` is set
+pub const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4318/v1/traces";
+
+/// Keeps the tracer provider alive; flushes pending spans when dropped
+pub struct TelemetryGuard {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        let _ = self.provider.shutdown();
+    }
+}
+
+/// OTLP endpoint from `RAWORC_OTLP_ENDPOINT`, then `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`,
+/// then the local default if `RAWORC_OTEL` is set; `None` disables export.
+pub fn endpoint_from_env() -> Option<String> {
+    std::env::var("RAWORC_OTLP_ENDPOINT")
+        .or_else(|_| std::env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"))
+        .ok()
+        .or_else(|| {
+            std::env::var("RAWORC_OTEL")
+                .is_ok_and(|v| matches!(v.as_str(), "1" | "true" | "on"))
+                .then(|| DEFAULT_OTLP_ENDPOINT.to_string())
+        })
+}
+
+/// Tracing layer exporting spans to `endpoint` over OTLP/HTTP
+pub fn layer<S>(endpoint: &str) -> RaworcResult<(OpenTelemetryLayer<S, Tracer>, TelemetryGuard)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| RaworcError::ConfigError(format!("Invalid OTLP endpoint {}: {}", endpoint, e)))?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name("raworc-mcp")
+                .build(),
+        )
+        .build();
+    let tracer = provider.tracer("raworc-mcp");
+    Ok((
+        tracing_opentelemetry::layer().with_tracer(tracer),
+        TelemetryGuard { provider },
+    ))
+}
@@ -0,0 +1,868 @@
+//! In-memory `RaworcApi` backend for tests and offline use.
+//! - State lives behind a mutex, so one instance can be shared by reference
+//! - A `default` space exists from the start; everything else is created through the API
+//! - Seed helpers (`push_message`, `push_agent_logs`, ...) script what a real backend would produce
+
+use crate::api::RaworcApi;
+use crate::client::filter_log_lines;
+use crate::error::{RaworcError, RaworcResult};
+use crate::models::*;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+#[derive(Default)]
+struct State {
+    user: String,
+    spaces: BTreeMap<String, Space>,
+    sessions: BTreeMap<String, Session>,
+    messages: HashMap<String, Vec<Message>>,
+    agents: BTreeMap<(String, String), Agent>,
+    agent_logs: HashMap<(String, String), Vec<String>>,
+    secrets: BTreeMap<(String, String), Secret>,
+    builds: HashMap<String, Vec<Build>>,
+    service_accounts: BTreeMap<String, ServiceAccount>,
+    roles: BTreeMap<String, Role>,
+    role_bindings: BTreeMap<String, RoleBinding>,
+    running_since: HashMap<(String, String), chrono::DateTime<Utc>>,
+}
+
+/// In-memory Raworc backend
+pub struct InMemoryRaworc {
+    state: Mutex<State>,
+    default_space: String,
+}
+
+impl Default for InMemoryRaworc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryRaworc {
+    /// Create a backend holding only the `default` space
+    pub fn new() -> Self {
+        let mut state = State {
+            user: "admin".to_string(),
+            ..Default::default()
+        };
+        state.spaces.insert("default".to_string(), new_space("default", Some("Default space")));
+        Self {
+            state: Mutex::new(state),
+            default_space: "default".to_string(),
+        }
+    }
+
+    /// Set the user reported by `get_user_info`
+    pub fn set_user(&self, user: &str) {
+        self.lock().user = user.to_string();
+    }
+
+    /// Append a message to a session, e.g. to script an assistant reply
+    pub fn push_message(&self, session_id: &str, role: MessageRole, content: &str) -> RaworcResult<Message> {
+        let mut state = self.lock();
+        let session = state
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| RaworcError::not_found(&format!("session {session_id}")))?;
+        session.last_activity_at = Some(Utc::now());
+        let message = Message {
+            id: Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            role,
+            content: content.to_string(),
+            created_at: Utc::now(),
+        };
+        state.messages.entry(session_id.to_string()).or_default().push(message.clone());
+        Ok(message)
+    }
+
+    /// Force a session into a state without going through the API
+    pub fn set_session_state(&self, session_id: &str, new_state: SessionState) -> RaworcResult<()> {
+        let mut state = self.lock();
+        let session = state
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| RaworcError::not_found(&format!("session {session_id}")))?;
+        apply_state(session, new_state);
+        Ok(())
+    }
+
+    /// Append log lines for an agent
+    pub fn push_agent_logs(&self, space: &str, agent_name: &str, lines: &[&str]) {
+        let mut state = self.lock();
+        state
+            .agent_logs
+            .entry((space.to_string(), agent_name.to_string()))
+            .or_default()
+            .extend(lines.iter().map(|l| l.to_string()));
+    }
+
+    /// Finish the latest build in a space, appending `logs` to its output
+    pub fn finish_build(&self, space: &str, status: BuildStatus, logs: &str) -> RaworcResult<Build> {
+        let mut state = self.lock();
+        let build = state
+            .builds
+            .get_mut(space)
+            .and_then(|builds| builds.last_mut())
+            .ok_or_else(|| RaworcError::not_found(&format!("build in space {space}")))?;
+        build.status = status;
+        build.completed_at = Some(Utc::now());
+        build.logs.get_or_insert_with(String::new).push_str(logs);
+        Ok(build.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn space_or_default<'a>(&'a self, space: Option<&'a str>) -> &'a str {
+        space.unwrap_or(&self.default_space)
+    }
+}
+
+fn new_space(name: &str, description: Option<&str>) -> Space {
+    let now = Utc::now();
+    Space {
+        name: name.to_string(),
+        description: description.map(|d| d.to_string()),
+        settings: None,
+        active: Some(true),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn apply_state(session: &mut Session, state: SessionState) {
+    let now = Utc::now();
+    match state {
+        SessionState::Running | SessionState::Idle if session.started_at.is_none() => {
+            session.started_at = Some(now);
+        }
+        SessionState::Terminated | SessionState::Closed => {
+            session.terminated_at = Some(now);
+        }
+        _ => {}
+    }
+    session.state = state;
+    session.last_activity_at = Some(now);
+}
+
+impl State {
+    fn require_space(&self, space: &str) -> RaworcResult<()> {
+        if self.spaces.contains_key(space) {
+            Ok(())
+        } else {
+            Err(RaworcError::not_found(&format!("space {space}")))
+        }
+    }
+
+    fn session_mut(&mut self, space: Option<&str>, session_id: &str) -> RaworcResult<&mut Session> {
+        self.sessions
+            .get_mut(session_id)
+            .filter(|s| space.is_none_or(|space| s.space == space))
+            .ok_or_else(|| RaworcError::not_found(&format!("session {session_id}")))
+    }
+
+    fn create_session(&mut self, space: &str, user: &str, metadata: Option<HashMap<String, Value>>, parent: Option<&str>) -> RaworcResult<Session> {
+        self.require_space(space)?;
+        let now = Utc::now();
+        let id = Uuid::new_v4().to_string();
+        let session = Session {
+            id: id.clone(),
+            space: space.to_string(),
+            created_by: user.to_string(),
+            state: SessionState::Idle,
+            container_id: Some(format!("container-{}", &id[..8])),
+            persistent_volume_id: Some(format!("volume-{}", &id[..8])),
+            parent_session_id: parent.map(|p| p.to_string()),
+            created_at: now,
+            started_at: Some(now),
+            last_activity_at: Some(now),
+            terminated_at: None,
+            termination_reason: None,
+            metadata: metadata.unwrap_or_default(),
+        };
+        self.sessions.insert(id, session.clone());
+        Ok(session)
+    }
+
+    fn update_session(&mut self, space: Option<&str>, session_id: &str, request: &UpdateSessionRequest) -> RaworcResult<Session> {
+        if let Some(target) = &request.space {
+            self.require_space(target)?;
+        }
+        let session = self.session_mut(space, session_id)?;
+        if let Some(target) = &request.space {
+            session.space = target.clone();
+        }
+        if let Some(metadata) = &request.metadata {
+            session.metadata.extend(metadata.clone());
+        }
+        session.last_activity_at = Some(Utc::now());
+        Ok(session.clone())
+    }
+
+    fn set_state(&mut self, space: Option<&str>, session_id: &str, state: SessionState, reason: Option<&str>) -> RaworcResult<()> {
+        let session = self.session_mut(space, session_id)?;
+        if matches!(session.state, SessionState::Terminated) && state != SessionState::Terminated {
+            return Err(RaworcError::state_error(&format!("session {session_id} is terminated")));
+        }
+        apply_state(session, state);
+        if let Some(reason) = reason {
+            session.termination_reason = Some(reason.to_string());
+        }
+        Ok(())
+    }
+
+    fn messages(&mut self, space: Option<&str>, session_id: &str, limit: Option<u64>) -> RaworcResult<Vec<Message>> {
+        self.session_mut(space, session_id)?;
+        let messages = self.messages.get(session_id).cloned().unwrap_or_default();
+        let skip = limit.map_or(0, |limit| messages.len().saturating_sub(limit as usize));
+        Ok(messages.into_iter().skip(skip).collect())
+    }
+
+    fn send_message(&mut self, space: Option<&str>, session_id: &str, content: &str) -> RaworcResult<Message> {
+        let session = self.session_mut(space, session_id)?;
+        if matches!(session.state, SessionState::Terminated | SessionState::Closed) {
+            return Err(RaworcError::state_error(&format!("session {session_id} is not accepting messages")));
+        }
+        session.last_activity_at = Some(Utc::now());
+        let message = Message {
+            id: Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            role: MessageRole::User,
+            content: content.to_string(),
+            created_at: Utc::now(),
+        };
+        self.messages.entry(session_id.to_string()).or_default().push(message.clone());
+        Ok(message)
+    }
+
+    fn message_count(&mut self, space: Option<&str>, session_id: &str) -> RaworcResult<MessageCount> {
+        self.session_mut(space, session_id)?;
+        let count = self.messages.get(session_id).map_or(0, |m| m.len()) as u64;
+        Ok(MessageCount { count })
+    }
+
+    fn clear_messages(&mut self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        self.session_mut(space, session_id)?;
+        self.messages.remove(session_id);
+        Ok(())
+    }
+
+    fn agent_mut(&mut self, space: &str, agent_name: &str) -> RaworcResult<&mut Agent> {
+        self.agents
+            .get_mut(&(space.to_string(), agent_name.to_string()))
+            .ok_or_else(|| RaworcError::not_found(&format!("agent {agent_name} in space {space}")))
+    }
+
+    fn secret_mut(&mut self, space: &str, key: &str) -> RaworcResult<&mut Secret> {
+        self.secrets
+            .get_mut(&(space.to_string(), key.to_string()))
+            .ok_or_else(|| RaworcError::not_found(&format!("secret {key} in space {space}")))
+    }
+
+    fn service_account_mut(&mut self, id: &str) -> RaworcResult<&mut ServiceAccount> {
+        self.service_accounts
+            .get_mut(id)
+            .ok_or_else(|| RaworcError::not_found(&format!("service account {id}")))
+    }
+}
+
+#[async_trait]
+impl RaworcApi for InMemoryRaworc {
+    /* Auth & system */
+    async fn authenticate(&mut self, username: &str, _password: &str) -> RaworcResult<()> {
+        self.lock().user = username.to_string();
+        Ok(())
+    }
+
+    async fn get_user_info(&self) -> RaworcResult<UserInfo> {
+        Ok(UserInfo {
+            user: self.lock().user.clone(),
+            namespace: None,
+            r#type: "admin".to_string(),
+        })
+    }
+
+    async fn health_check(&self) -> RaworcResult<String> {
+        Ok("OK".to_string())
+    }
+
+    async fn get_version(&self) -> RaworcResult<VersionResponse> {
+        Ok(VersionResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            api: "v0".to_string(),
+        })
+    }
+
+    /* Spaces */
+    async fn list_spaces(&self) -> RaworcResult<Vec<Space>> {
+        Ok(self.lock().spaces.values().cloned().collect())
+    }
+
+    async fn create_space(&self, request: &CreateSpaceRequest) -> RaworcResult<Space> {
+        let mut state = self.lock();
+        let name = &request.name;
+        if state.spaces.contains_key(name) {
+            return Err(RaworcError::api_error(409, format!("space {name} already exists")));
+        }
+        let mut space = new_space(name, request.description.as_deref());
+        space.settings = request.settings.clone();
+        state.spaces.insert(name.clone(), space.clone());
+        Ok(space)
+    }
+
+    async fn get_space(&self, name: &str) -> RaworcResult<Space> {
+        self.lock()
+            .spaces
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RaworcError::not_found(&format!("space {name}")))
+    }
+
+    async fn update_space(&self, name: &str, request: &UpdateSpaceRequest) -> RaworcResult<Space> {
+        let mut state = self.lock();
+        let space = state
+            .spaces
+            .get_mut(name)
+            .ok_or_else(|| RaworcError::not_found(&format!("space {name}")))?;
+        if let Some(description) = &request.description {
+            space.description = Some(description.clone());
+        }
+        if let Some(settings) = &request.settings {
+            space.settings.get_or_insert_with(HashMap::new).extend(settings.clone());
+        }
+        space.updated_at = Utc::now();
+        Ok(space.clone())
+    }
+
+    async fn delete_space(&self, name: &str) -> RaworcResult<()> {
+        let mut state = self.lock();
+        state.require_space(name)?;
+        if state.sessions.values().any(|s| s.space == name) {
+            return Err(RaworcError::api_error(409, format!("space {name} still has sessions")));
+        }
+        state.spaces.remove(name);
+        state.agents.retain(|(space, _), _| space != name);
+        state.secrets.retain(|(space, _), _| space != name);
+        state.builds.remove(name);
+        Ok(())
+    }
+
+    /* Sessions (space-scoped) */
+    async fn list_sessions(&self, space: Option<&str>) -> RaworcResult<Vec<Session>> {
+        let space = self.space_or_default(space);
+        let state = self.lock();
+        state.require_space(space)?;
+        Ok(state.sessions.values().filter(|s| s.space == space).cloned().collect())
+    }
+
+    async fn create_session(&self, space: Option<&str>, metadata: Option<HashMap<String, Value>>) -> RaworcResult<Session> {
+        let space = self.space_or_default(space);
+        let mut state = self.lock();
+        let user = state.user.clone();
+        state.create_session(space, &user, metadata, None)
+    }
+
+    async fn get_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<Session> {
+        let space = self.space_or_default(space);
+        Ok(self.lock().session_mut(Some(space), session_id)?.clone())
+    }
+
+    async fn update_session(&self, space: Option<&str>, session_id: &str, request: &UpdateSessionRequest) -> RaworcResult<Session> {
+        let space = self.space_or_default(space);
+        self.lock().update_session(Some(space), session_id, request)
+    }
+
+    async fn update_session_state(&self, space: Option<&str>, session_id: &str, state: SessionState) -> RaworcResult<()> {
+        let space = self.space_or_default(space);
+        self.lock().set_state(Some(space), session_id, state, None)
+    }
+
+    async fn pause_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        self.update_session_state(space, session_id, SessionState::Paused).await
+    }
+
+    async fn resume_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        self.update_session_state(space, session_id, SessionState::Idle).await
+    }
+
+    async fn terminate_session(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        let space = self.space_or_default(space);
+        self.lock()
+            .set_state(Some(space), session_id, SessionState::Terminated, Some("terminated by user"))
+    }
+
+    /* Messages (space+session) */
+    async fn get_messages(&self, space: Option<&str>, session_id: &str, limit: Option<u64>) -> RaworcResult<Vec<Message>> {
+        let space = self.space_or_default(space);
+        self.lock().messages(Some(space), session_id, limit)
+    }
+
+    async fn send_message(&self, space: Option<&str>, session_id: &str, content: &str) -> RaworcResult<Message> {
+        let space = self.space_or_default(space);
+        self.lock().send_message(Some(space), session_id, content)
+    }
+
+    async fn get_message_count(&self, space: Option<&str>, session_id: &str) -> RaworcResult<MessageCount> {
+        let space = self.space_or_default(space);
+        self.lock().message_count(Some(space), session_id)
+    }
+
+    async fn clear_messages(&self, space: Option<&str>, session_id: &str) -> RaworcResult<()> {
+        let space = self.space_or_default(space);
+        self.lock().clear_messages(Some(space), session_id)
+    }
+
+    /* Sessions (global) */
+    async fn list_all_sessions(&self) -> RaworcResult<Vec<Session>> {
+        Ok(self.lock().sessions.values().cloned().collect())
+    }
+
+    async fn create_global_session(&self, request: &CreateSessionRequest) -> RaworcResult<Session> {
+        self.create_session(request.space.as_deref(), request.metadata.clone()).await
+    }
+
+    async fn get_global_session(&self, session_id: &str) -> RaworcResult<Session> {
+        Ok(self.lock().session_mut(None, session_id)?.clone())
+    }
+
+    async fn update_global_session(&self, session_id: &str, request: &UpdateSessionRequest) -> RaworcResult<Session> {
+        self.lock().update_session(None, session_id, request)
+    }
+
+    async fn update_global_session_state(&self, session_id: &str, request: &UpdateSessionStateRequest) -> RaworcResult<()> {
+        self.lock().set_state(None, session_id, request.state.clone(), None)
+    }
+
+    async fn close_session(&self, session_id: &str) -> RaworcResult<()> {
+        self.lock().set_state(None, session_id, SessionState::Closed, Some("closed by user"))
+    }
+
+    async fn restore_session(&self, session_id: &str) -> RaworcResult<()> {
+        let mut state = self.lock();
+        let session = state.session_mut(None, session_id)?;
+        if session.state != SessionState::Closed {
+            return Err(RaworcError::state_error(&format!("session {session_id} is not closed")));
+        }
+        session.terminated_at = None;
+        session.termination_reason = None;
+        apply_state(session, SessionState::Idle);
+        Ok(())
+    }
+
+    async fn remix_session(&self, session_id: &str, request: &CreateSessionRequest) -> RaworcResult<Session> {
+        let mut state = self.lock();
+        let parent = state.session_mut(None, session_id)?.clone();
+        let space = request.space.clone().unwrap_or(parent.space);
+        let mut metadata = parent.metadata;
+        metadata.extend(request.metadata.clone().unwrap_or_default());
+        let user = state.user.clone();
+        state.create_session(&space, &user, Some(metadata), Some(session_id))
+    }
+
+    async fn delete_global_session(&self, session_id: &str) -> RaworcResult<()> {
+        let mut state = self.lock();
+        state.session_mut(None, session_id)?;
+        state.sessions.remove(session_id);
+        state.messages.remove(session_id);
+        Ok(())
+    }
+
+    async fn get_global_messages(&self, session_id: &str, limit: Option<u64>) -> RaworcResult<Vec<Message>> {
+        self.lock().messages(None, session_id, limit)
+    }
+
+    async fn send_global_message(&self, session_id: &str, request: &CreateMessageRequest) -> RaworcResult<Message> {
+        self.lock().send_message(None, session_id, &request.content)
+    }
+
+    async fn get_global_message_count(&self, session_id: &str) -> RaworcResult<MessageCount> {
+        self.lock().message_count(None, session_id)
+    }
+
+    async fn clear_global_messages(&self, session_id: &str) -> RaworcResult<()> {
+        self.lock().clear_messages(None, session_id)
+    }
+
+    /* Agents */
+    async fn list_agents(&self, space: Option<&str>) -> RaworcResult<Vec<Agent>> {
+        let space = self.space_or_default(space);
+        let state = self.lock();
+        state.require_space(space)?;
+        Ok(state
+            .agents
+            .iter()
+            .filter(|((s, _), _)| s == space)
+            .map(|(_, agent)| agent.clone())
+            .collect())
+    }
+
+    async fn create_agent(&self, space: &str, request: &CreateAgentRequest) -> RaworcResult<Agent> {
+        let mut state = self.lock();
+        state.require_space(space)?;
+        let key = (space.to_string(), request.name.clone());
+        if state.agents.contains_key(&key) {
+            return Err(RaworcError::api_error(409, format!("agent {} already exists", request.name)));
+        }
+        let now = Utc::now();
+        let agent = Agent {
+            name: request.name.clone(),
+            description: request.description.clone(),
+            purpose: request.purpose.clone(),
+            source_repo: request.source_repo.clone(),
+            source_branch: request.source_branch.clone(),
+            status: AgentStatus::Inactive,
+            created_at: now,
+            updated_at: now,
+        };
+        state.agents.insert(key, agent.clone());
+        Ok(agent)
+    }
+
+    async fn get_agent(&self, space: &str, agent_name: &str) -> RaworcResult<Agent> {
+        Ok(self.lock().agent_mut(space, agent_name)?.clone())
+    }
+
+    async fn update_agent(&self, space: &str, agent_name: &str, request: &UpdateAgentRequest) -> RaworcResult<Agent> {
+        let mut state = self.lock();
+        let agent = state.agent_mut(space, agent_name)?;
+        if let Some(description) = &request.description {
+            agent.description = Some(description.clone());
+        }
+        if let Some(purpose) = &request.purpose {
+            agent.purpose = Some(purpose.clone());
+        }
+        if let Some(source_repo) = &request.source_repo {
+            agent.source_repo = Some(source_repo.clone());
+        }
+        if let Some(source_branch) = &request.source_branch {
+            agent.source_branch = Some(source_branch.clone());
+        }
+        agent.updated_at = Utc::now();
+        Ok(agent.clone())
+    }
+
+    async fn delete_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()> {
+        let mut state = self.lock();
+        state.agent_mut(space, agent_name)?;
+        let key = (space.to_string(), agent_name.to_string());
+        state.agents.remove(&key);
+        state.agent_logs.remove(&key);
+        Ok(())
+    }
+
+    async fn get_agent_logs(&self, space: &str, agent_name: &str) -> RaworcResult<String> {
+        let lines = self.get_agent_log_lines(space, agent_name, &AgentLogsQuery::default()).await?;
+        Ok(lines.join("\n"))
+    }
+
+    async fn get_agent_log_lines(&self, space: &str, agent_name: &str, query: &AgentLogsQuery) -> RaworcResult<Vec<String>> {
+        let mut state = self.lock();
+        state.agent_mut(space, agent_name)?;
+        let lines = state
+            .agent_logs
+            .get(&(space.to_string(), agent_name.to_string()))
+            .cloned()
+            .unwrap_or_default();
+        filter_log_lines(lines, query)
+    }
+
+    async fn update_agent_status(&self, space: &str, agent_name: &str, request: &UpdateAgentStatusRequest) -> RaworcResult<()> {
+        let mut state = self.lock();
+        let agent = state.agent_mut(space, agent_name)?;
+        agent.status = request.status.clone();
+        agent.updated_at = Utc::now();
+        Ok(())
+    }
+
+    async fn deploy_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()> {
+        let mut state = self.lock();
+        let agent = state.agent_mut(space, agent_name)?;
+        agent.status = AgentStatus::Running;
+        agent.updated_at = Utc::now();
+        state.running_since.insert((space.to_string(), agent_name.to_string()), Utc::now());
+        Ok(())
+    }
+
+    async fn stop_agent(&self, space: &str, agent_name: &str) -> RaworcResult<()> {
+        let mut state = self.lock();
+        let agent = state.agent_mut(space, agent_name)?;
+        agent.status = AgentStatus::Stopped;
+        agent.updated_at = Utc::now();
+        state.running_since.remove(&(space.to_string(), agent_name.to_string()));
+        Ok(())
+    }
+
+    async fn list_running_agents(&self, space: &str) -> RaworcResult<Vec<RunningAgent>> {
+        let state = self.lock();
+        state.require_space(space)?;
+        Ok(state
+            .agents
+            .iter()
+            .filter(|((s, _), agent)| s == space && matches!(agent.status, AgentStatus::Running))
+            .map(|(key, agent)| RunningAgent {
+                name: agent.name.clone(),
+                status: agent.status.clone(),
+                started_at: state.running_since.get(key).copied().unwrap_or(agent.updated_at),
+            })
+            .collect())
+    }
+
+    /* Secrets */
+    async fn list_secrets(&self, space: Option<&str>) -> RaworcResult<Vec<Secret>> {
+        let space = self.space_or_default(space);
+        let state = self.lock();
+        state.require_space(space)?;
+        Ok(state
+            .secrets
+            .iter()
+            .filter(|((s, _), _)| s == space)
+            .map(|(_, secret)| Secret { value: None, ..secret.clone() })
+            .collect())
+    }
+
+    async fn get_secret(&self, space: &str, key: &str) -> RaworcResult<Secret> {
+        Ok(self.lock().secret_mut(space, key)?.clone())
+    }
+
+    async fn create_secret(&self, space: &str, request: &CreateSecretRequest) -> RaworcResult<Secret> {
+        let mut state = self.lock();
+        state.require_space(space)?;
+        let key = (space.to_string(), request.key_name.clone());
+        if state.secrets.contains_key(&key) {
+            return Err(RaworcError::api_error(409, format!("secret {} already exists", request.key_name)));
+        }
+        let now = Utc::now();
+        let secret = Secret {
+            key_name: request.key_name.clone(),
+            value: Some(request.value.clone()),
+            description: request.description.clone(),
+            space: Some(space.to_string()),
+            created_at: now,
+            updated_at: now,
+            created_by: Some(state.user.clone()),
+        };
+        state.secrets.insert(key, secret.clone());
+        Ok(secret)
+    }
+
+    async fn set_secret(&self, space: &str, key: &str, value: &str) -> RaworcResult<Secret> {
+        let mut state = self.lock();
+        state.require_space(space)?;
+        let now = Utc::now();
+        let user = state.user.clone();
+        let secret = state
+            .secrets
+            .entry((space.to_string(), key.to_string()))
+            .or_insert_with(|| Secret {
+                key_name: key.to_string(),
+                value: None,
+                description: None,
+                space: Some(space.to_string()),
+                created_at: now,
+                updated_at: now,
+                created_by: Some(user),
+            });
+        secret.value = Some(value.to_string());
+        secret.updated_at = now;
+        Ok(secret.clone())
+    }
+
+    async fn update_secret(&self, space: &str, key: &str, request: &UpdateSecretRequest) -> RaworcResult<Secret> {
+        let mut state = self.lock();
+        let secret = state.secret_mut(space, key)?;
+        if let Some(value) = &request.value {
+            secret.value = Some(value.clone());
+        }
+        if let Some(description) = &request.description {
+            secret.description = Some(description.clone());
+        }
+        secret.updated_at = Utc::now();
+        Ok(secret.clone())
+    }
+
+    async fn delete_secret(&self, space: &str, key: &str) -> RaworcResult<()> {
+        let mut state = self.lock();
+        state.secret_mut(space, key)?;
+        state.secrets.remove(&(space.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    /* Service accounts */
+    async fn list_service_accounts(&self) -> RaworcResult<Vec<ServiceAccount>> {
+        Ok(self.lock().service_accounts.values().cloned().collect())
+    }
+
+    async fn create_service_account(&self, request: &CreateServiceAccountRequest) -> RaworcResult<ServiceAccount> {
+        let mut state = self.lock();
+        if let Some(space) = &request.space {
+            state.require_space(space)?;
+        }
+        if state.service_accounts.values().any(|a| a.user == request.user) {
+            return Err(RaworcError::api_error(409, format!("service account {} already exists", request.user)));
+        }
+        let now = Utc::now();
+        let account = ServiceAccount {
+            id: Uuid::new_v4().to_string(),
+            user: request.user.clone(),
+            space: request.space.clone(),
+            description: request.description.clone(),
+            active: true,
+            created_at: now,
+            updated_at: now,
+            last_login_at: None,
+        };
+        state.service_accounts.insert(account.id.clone(), account.clone());
+        Ok(account)
+    }
+
+    async fn get_service_account(&self, id: &str) -> RaworcResult<ServiceAccount> {
+        Ok(self.lock().service_account_mut(id)?.clone())
+    }
+
+    async fn update_service_account(&self, id: &str, request: &UpdateServiceAccountRequest) -> RaworcResult<ServiceAccount> {
+        let mut state = self.lock();
+        if let Some(space) = &request.space {
+            state.require_space(space)?;
+        }
+        let account = state.service_account_mut(id)?;
+        if let Some(space) = &request.space {
+            account.space = Some(space.clone());
+        }
+        if let Some(description) = &request.description {
+            account.description = Some(description.clone());
+        }
+        if let Some(active) = request.active {
+            account.active = active;
+        }
+        account.updated_at = Utc::now();
+        Ok(account.clone())
+    }
+
+    async fn delete_service_account(&self, id: &str) -> RaworcResult<()> {
+        let mut state = self.lock();
+        state.service_account_mut(id)?;
+        state.service_accounts.remove(id);
+        Ok(())
+    }
+
+    async fn update_service_account_password(&self, id: &str, _request: &UpdatePasswordRequest) -> RaworcResult<()> {
+        self.lock().service_account_mut(id)?.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /* Roles */
+    async fn list_roles(&self) -> RaworcResult<Vec<Role>> {
+        Ok(self.lock().roles.values().cloned().collect())
+    }
+
+    async fn create_role(&self, request: &CreateRoleRequest) -> RaworcResult<Role> {
+        let mut state = self.lock();
+        if state.roles.contains_key(&request.id) {
+            return Err(RaworcError::api_error(409, format!("role {} already exists", request.id)));
+        }
+        let role = Role {
+            name: request.id.clone(),
+            description: request.description.clone(),
+            rules: request.rules.clone(),
+            created_at: Utc::now(),
+        };
+        state.roles.insert(request.id.clone(), role.clone());
+        Ok(role)
+    }
+
+    async fn get_role(&self, id: &str) -> RaworcResult<Role> {
+        self.lock()
+            .roles
+            .get(id)
+            .cloned()
+            .ok_or_else(|| RaworcError::not_found(&format!("role {id}")))
+    }
+
+    async fn delete_role(&self, id: &str) -> RaworcResult<()> {
+        self.lock()
+            .roles
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| RaworcError::not_found(&format!("role {id}")))
+    }
+
+    /* Role bindings */
+    async fn list_role_bindings(&self) -> RaworcResult<Vec<RoleBinding>> {
+        Ok(self.lock().role_bindings.values().cloned().collect())
+    }
+
+    async fn create_role_binding(&self, request: &CreateRoleBindingRequest) -> RaworcResult<RoleBinding> {
+        let mut state = self.lock();
+        if !state.roles.contains_key(&request.role_ref) {
+            return Err(RaworcError::not_found(&format!("role {}", request.role_ref)));
+        }
+        if let Some(space) = &request.space {
+            state.require_space(space)?;
+        }
+        let now = Utc::now();
+        let binding = RoleBinding {
+            id: Uuid::new_v4().to_string(),
+            subject: request.subject.clone(),
+            role_ref: request.role_ref.clone(),
+            space: request.space.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        state.role_bindings.insert(binding.id.clone(), binding.clone());
+        Ok(binding)
+    }
+
+    async fn get_role_binding(&self, id: &str) -> RaworcResult<RoleBinding> {
+        self.lock()
+            .role_bindings
+            .get(id)
+            .cloned()
+            .ok_or_else(|| RaworcError::not_found(&format!("role binding {id}")))
+    }
+
+    async fn delete_role_binding(&self, id: &str) -> RaworcResult<()> {
+        self.lock()
+            .role_bindings
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| RaworcError::not_found(&format!("role binding {id}")))
+    }
+
+    /* Builds */
+    async fn create_build(&self, space: &str, _req: &CreateBuildRequest) -> RaworcResult<Build> {
+        let mut state = self.lock();
+        state.require_space(space)?;
+        let build = Build {
+            id: Some(Uuid::new_v4().to_string()),
+            space: Some(space.to_string()),
+            status: BuildStatus::Pending,
+            started_at: Utc::now(),
+            completed_at: None,
+            logs: Some(String::new()),
+        };
+        state.builds.entry(space.to_string()).or_default().push(build.clone());
+        Ok(build)
+    }
+
+    async fn get_latest_build(&self, space: &str) -> RaworcResult<Build> {
+        self.lock()
+            .builds
+            .get(space)
+            .and_then(|builds| builds.last().cloned())
+            .ok_or_else(|| RaworcError::not_found(&format!("build in space {space}")))
+    }
+
+    async fn get_build(&self, space: &str, build_id: &str) -> RaworcResult<Build> {
+        self.lock()
+            .builds
+            .get(space)
+            .and_then(|builds| builds.iter().find(|b| b.id.as_deref() == Some(build_id)).cloned())
+            .ok_or_else(|| RaworcError::not_found(&format!("build {build_id}")))
+    }
+}
@@ -1,18 +1,61 @@
 use raworc_mcp::Config;
 
+fn session_json(id: &str, state: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "space": "default",
+        "created_by": "tester",
+        "state": state,
+        "container_id": null,
+        "persistent_volume_id": null,
+        "parent_session_id": null,
+        "created_at": "2025-01-01T00:00:00Z",
+        "started_at": null,
+        "last_activity_at": null,
+        "terminated_at": null,
+        "termination_reason": null,
+        "metadata": {}
+    })
+}
+
+fn space_request(name: &str) -> raworc_mcp::models::CreateSpaceRequest {
+    raworc_mcp::models::CreateSpaceRequest {
+        name: name.to_string(),
+        description: None,
+        settings: None,
+    }
+}
+
 #[test]
 fn test_config_defaults() {
     let config = Config {
         api_url: Some("https://api.remoteagent.com/api/v0".to_string()),
-        auth_token: Some("test-token".to_string()),
+        auth_token: Some("test-token".into()),
         username: None,
         password: None,
         default_space: Some("default".to_string()),
         timeout_seconds: Some(30),
+        max_output_chars: None,
+        output_format: None,
+        cassette: None,
+        cache: None,
+        proxy: None,
+        no_proxy: None,
+        ca_cert: None,
+        client_cert: None,
+        client_key: None,
+        reveal_secrets: Vec::new(),
+        read_only: false,
+        dry_run: false,
+        tools: Default::default(),
+        allowed_spaces: Vec::new(),
+        policy: None,
+        hooks: Vec::new(),
+        audit: None,
     };
 
     assert_eq!(config.api_url, Some("https://api.remoteagent.com/api/v0".to_string()));
-    assert_eq!(config.auth_token, Some("test-token".to_string()));
+    assert_eq!(config.auth_token.as_ref().map(|t| t.expose().as_str()), Some("test-token"));
     assert_eq!(config.default_space, Some("default".to_string()));
     assert_eq!(config.timeout_seconds, Some(30));
 }
@@ -40,3 +83,1969 @@ fn test_capabilities_parsing() {
     assert!(tool_names.contains(&"send_message".to_string()));
     assert!(tool_names.contains(&"health_check".to_string()));
 }
+
+#[tokio::test]
+async fn test_tool_list_matches_dispatch() {
+    use raworc_mcp::mcp::{tool_list, SUPPORTED_TOOLS};
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::json;
+
+    let tools = tool_list();
+    let listed: Vec<&str> = tools["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|t| t["name"].as_str())
+        .collect();
+    assert_eq!(listed.len(), SUPPORTED_TOOLS.len(), "every supported tool needs a CAPABILITIES entry");
+
+    let mut server = RaworcMcpServer::with_client(InMemoryRaworc::new(), Config::new("memory://".to_string()));
+    for name in &listed {
+        if let Err(e) = server.handle_tool_call(name, &json!({})).await {
+            assert!(!e.to_string().contains("Unknown tool"), "{} is listed but not dispatched", name);
+        }
+    }
+
+    // Session tools can target a non-default space
+    for name in ["ask_session", "wait_session", "send_message", "pause_session"] {
+        let tool = tools["tools"].as_array().unwrap().iter().find(|t| t["name"] == name).unwrap();
+        assert!(tool.pointer("/inputSchema/properties/space").is_some(), "{} lacks space", name);
+    }
+}
+
+#[tokio::test]
+async fn test_send_message_and_wait_returns_reply() {
+    use raworc_mcp::models::MessageRole;
+    use raworc_mcp::RaworcClient;
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    let message = |id: &str, role: &str, content: &str, at: &str| {
+        json!({ "id": id, "session_id": "s1", "role": role, "content": content, "created_at": at })
+    };
+    let earlier = message("m0", "assistant", "an earlier answer", "2024-12-31T23:00:00Z");
+    let sent = message("m1", "user", "hello", "2025-01-01T00:00:00Z");
+    let reply = message("m2", "assistant", "hi there", "2025-01-01T00:00:05Z");
+
+    Mock::given(method("POST"))
+        .and(path("/api/v0/spaces/default/sessions/s1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&sent))
+        .mount(&server)
+        .await;
+    // The first poll sees the history before the reply, the next one after it
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/sessions/s1/messages/count"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "count": 2 })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/sessions/s1/messages/count"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "count": 3 })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/sessions/s1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([earlier, sent])))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    // Newest first, to show the listing order is not relied on
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/sessions/s1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([reply, sent, earlier])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/sessions/s1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(session_json("s1", "RUNNING")))
+        .mount(&server)
+        .await;
+
+    // Inherent on the HTTP client: no `RaworcApi` import needed
+    let config = Config::new(format!("{}/api/v0", server.uri()));
+    let client = RaworcClient::new(&config).unwrap();
+    let exchange = client
+        .send_message_and_wait(None, "s1", "hello", Duration::from_secs(5), &|_, _| {})
+        .await
+        .unwrap();
+
+    let reply = exchange.reply.expect("assistant reply");
+    assert_eq!(reply.role, MessageRole::Assistant);
+    assert_eq!(reply.content, "hi there");
+}
+
+#[tokio::test]
+async fn test_send_message_and_wait_timeout_names_the_sent_message() {
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcApi, RaworcError};
+    use std::time::Duration;
+
+    let backend = InMemoryRaworc::new();
+    let session = backend.create_session(None, None).await.unwrap();
+    let result = backend
+        .send_message_and_wait(None, &session.id, "hello", Duration::from_millis(50), &|_, _| {})
+        .await;
+    let Err(RaworcError::TimeoutError(message)) = result else {
+        panic!("expected a timeout, got {:?}", result);
+    };
+    let sent = backend.get_messages(None, &session.id, None).await.unwrap().remove(0);
+    assert!(message.contains(&format!("message {} was sent", sent.id)), "{}", message);
+}
+
+#[tokio::test]
+async fn test_wait_for_session_state_reaches_target() {
+    use raworc_mcp::models::SessionState;
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::RaworcApi;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let backend = Arc::new(InMemoryRaworc::new());
+    let session = backend.create_session(None, None).await.unwrap();
+    backend.set_session_state(&session.id, SessionState::Running).unwrap();
+
+    let flip = {
+        let backend = backend.clone();
+        let id = session.id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            backend.set_session_state(&id, SessionState::Idle).unwrap();
+        })
+    };
+    let polls = AtomicUsize::new(0);
+    let progress = |_: Duration, status: &str| {
+        assert_eq!(status, "Session is Running");
+        polls.fetch_add(1, Ordering::SeqCst);
+    };
+    let reached = backend
+        .wait_for_session_state(None, &session.id, &[SessionState::Idle], Duration::from_secs(10), &progress)
+        .await
+        .unwrap();
+    flip.await.unwrap();
+    assert_eq!(reached.state, SessionState::Idle);
+    assert!(polls.load(Ordering::SeqCst) >= 1);
+}
+
+#[tokio::test]
+async fn test_wait_for_session_state_fails_early_when_terminated() {
+    use raworc_mcp::models::SessionState;
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcApi, RaworcError};
+    use std::time::{Duration, Instant};
+
+    let backend = InMemoryRaworc::new();
+    let session = backend.create_session(None, None).await.unwrap();
+    backend.terminate_session(None, &session.id).await.unwrap();
+
+    let started = Instant::now();
+    let err = backend
+        .wait_for_session_state(None, &session.id, &[SessionState::Idle], Duration::from_secs(30), &|_, _| {})
+        .await
+        .unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5), "should not wait for the timeout");
+    assert!(
+        matches!(&err, RaworcError::StateError(msg) if msg.contains("Terminated") && msg.contains("terminated by user")),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_wait_for_build_streams_log_chunks_as_progress() {
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let build = |status: &str, logs: &str| {
+        json!({
+            "id": "b1",
+            "space": "web",
+            "status": status,
+            "started_at": "2025-01-01T00:00:00Z",
+            "completed_at": null,
+            "logs": logs
+        })
+    };
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/web/build/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(build("building", "step 1\n")))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/web/build/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(build("completed", "step 1\nstep 2\n")))
+        .mount(&server)
+        .await;
+
+    let config = Config::new(format!("{}/api/v0", server.uri())).with_auth_token("t".to_string());
+    let mut mcp = RaworcMcpServer::new(config).unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    mcp.set_progress_sender(tx);
+    let response = mcp
+        .handle_tool_call_with_progress("wait_for_build", &json!({"space": "web"}), Some(json!("tok")))
+        .await
+        .unwrap();
+    assert!(response.content[0].text.as_deref().unwrap().starts_with("Build completed"));
+
+    let mut chunks = Vec::new();
+    while let Ok(note) = rx.try_recv() {
+        assert_eq!(note["method"], "notifications/progress");
+        assert_eq!(note["params"]["progressToken"], "tok");
+        chunks.push(note["params"]["message"].as_str().unwrap().to_string());
+    }
+    assert_eq!(chunks, ["step 1\n", "step 2\n"]);
+}
+
+#[tokio::test]
+async fn test_wait_for_build_returns_log_tail_on_failure() {
+    use raworc_mcp::models::{BuildStatus, CreateBuildRequest};
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcApi, RaworcMcpServer};
+    use serde_json::json;
+
+    let backend = InMemoryRaworc::new();
+    let request = CreateBuildRequest { dockerfile: None, context: None };
+    backend.create_build("default", &request).await.unwrap();
+    let logs: String = (1..=80).map(|i| format!("line {i}\n")).collect();
+    backend.finish_build("default", BuildStatus::Failed, &logs).unwrap();
+
+    let mut mcp = RaworcMcpServer::with_client(backend, Config::new("memory://".to_string()));
+    let response = mcp.handle_tool_call("wait_for_build", &json!({"space": "default"})).await.unwrap();
+    let text = response.content[0].text.as_deref().unwrap();
+    assert!(text.contains("failed after"), "{}", text);
+    assert!(text.contains("Last 50 log lines"));
+    assert!(text.contains("line 31\n") && text.ends_with("line 80"));
+    assert!(!text.contains("line 30\n"));
+}
+
+#[tokio::test]
+async fn test_get_agent_logs_since_rejects_bad_ages() {
+    use raworc_mcp::models::CreateAgentRequest;
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcApi, RaworcError, RaworcMcpServer};
+    use serde_json::json;
+
+    let backend = InMemoryRaworc::new();
+    let request = CreateAgentRequest {
+        name: "bot".to_string(),
+        description: None,
+        purpose: None,
+        source_repo: None,
+        source_branch: None,
+        image: None,
+        command: None,
+        env: None,
+        resources: None,
+    };
+    backend.create_agent("default", &request).await.unwrap();
+    let mut server = RaworcMcpServer::with_client(backend, Config::new("memory://".to_string()));
+
+    for since in ["15m", "2h", "30s", "1d", "2025-01-01T00:00:00Z"] {
+        let args = json!({"space": "default", "agent_name": "bot", "since": since});
+        assert!(server.handle_tool_call("get_agent_logs", &args).await.is_ok(), "{} rejected", since);
+    }
+    for since in ["15µ", "µ", "15分", "", "m", "1.5h", "15w"] {
+        let args = json!({"space": "default", "agent_name": "bot", "since": since});
+        let err = server.handle_tool_call("get_agent_logs", &args).await.unwrap_err();
+        assert!(err.to_string().contains("since must be RFC 3339"), "{}: {}", since, err);
+    }
+    for since in ["-5m", "-1d"] {
+        let args = json!({"space": "default", "agent_name": "bot", "since": since});
+        let err = server.handle_tool_call("get_agent_logs", &args).await.unwrap_err();
+        assert!(matches!(&err, RaworcError::ValidationError(msg) if msg.contains("negative")), "{}: {}", since, err);
+    }
+    // Ages too large to subtract from now are an argument error, not a panic
+    let args = json!({"space": "default", "agent_name": "bot", "since": "9999999999999d"});
+    assert!(server.handle_tool_call("get_agent_logs", &args).await.is_err());
+}
+
+#[tokio::test]
+async fn test_get_agent_logs_follow_sends_since_and_detects_rotation() {
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let api = MockServer::start().await;
+    let grown = ["2025-01-01T10:00:01Z a1", "2025-01-01T10:00:02Z a2", "2025-01-01T10:00:02Z a3"];
+    // Rotated to a longer log: the old line count would skip all but the last line
+    let rotated = [
+        "2025-01-01T10:00:05Z b1",
+        "2025-01-01T10:00:06Z b2",
+        "2025-01-01T10:00:07Z b3",
+        "2025-01-01T10:00:08Z b4",
+        "2025-01-01T10:00:08Z b5",
+    ];
+    // The API answers with the whole log each time, as if it ignored `since`
+    for logs in [&grown[..2], &grown[..], &rotated[..4], &rotated[..]] {
+        Mock::given(method("GET"))
+            .and(path("/api/v0/spaces/default/agents/bot/logs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "logs": logs })))
+            .up_to_n_times(1)
+            .mount(&api)
+            .await;
+    }
+    let config = Config::new(format!("{}/api/v0", api.uri())).with_auth_token("t".to_string());
+    let mut server = RaworcMcpServer::new(config).unwrap();
+    let follow = json!({"space": "default", "agent_name": "bot", "follow": true});
+    let mut next = async || {
+        let content = server.handle_tool_call("get_agent_logs", &follow).await.unwrap().content;
+        content[0].text.clone().unwrap()
+    };
+
+    assert_eq!(next().await, grown[..2].join("\n"));
+    assert_eq!(next().await, grown[2]);
+    assert_eq!(next().await, rotated[..4].join("\n"));
+    assert_eq!(next().await, rotated[4]);
+
+    // After the first call, only lines since the last timestamp seen are asked for
+    let queries: Vec<Option<String>> = api
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| r.url.query().map(str::to_string))
+        .collect();
+    let since = |ts: &str| Some(format!("since={ts}"));
+    assert_eq!(
+        queries,
+        [None, since("2025-01-01T10:00:02Z"), since("2025-01-01T10:00:02Z"), since("2025-01-01T10:00:08Z")]
+    );
+}
+
+#[test]
+fn test_filter_log_lines() {
+    use raworc_mcp::client::filter_log_lines;
+    use raworc_mcp::models::AgentLogsQuery;
+
+    let lines: Vec<String> = [
+        "2025-01-01T00:00:00Z INFO starting",
+        "2025-01-01T00:05:00Z ERROR boom",
+        "  at frame 1",
+        "2025-01-01T00:10:00Z INFO recovered",
+        "2025-01-01T00:15:00Z ERROR again",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+
+    let query = AgentLogsQuery {
+        tail: Some(1),
+        since: Some("2025-01-01T00:05:00Z".parse().unwrap()),
+        grep: Some("ERROR".to_string()),
+    };
+    let filtered = filter_log_lines(lines.clone(), &query).unwrap();
+    assert_eq!(filtered, vec!["2025-01-01T00:15:00Z ERROR again".to_string()]);
+
+    let query = AgentLogsQuery {
+        since: Some("2025-01-01T00:05:00Z".parse().unwrap()),
+        ..Default::default()
+    };
+    let filtered = filter_log_lines(lines, &query).unwrap();
+    assert_eq!(filtered.len(), 4);
+    assert_eq!(filtered[1], "  at frame 1");
+}
+
+#[test]
+fn test_output_budget_truncates_arrays_with_cursor() {
+    use raworc_mcp::mcp::apply_output_budget;
+
+    let items: Vec<serde_json::Value> = (0..100)
+        .map(|i| serde_json::json!({ "id": i, "name": format!("session-{i}") }))
+        .collect();
+    let text = serde_json::to_string_pretty(&items).unwrap();
+
+    let out = apply_output_budget(text.clone(), 1_000, 0);
+    let parsed: Vec<serde_json::Value> = serde_json::from_str(&out).unwrap();
+    assert!(parsed.len() < items.len());
+    let marker = parsed.iter().find_map(|v| v.as_str()).expect("truncation marker");
+    assert!(marker.contains("items omitted"));
+    assert_eq!(parsed[0]["id"], 0);
+    assert_eq!(parsed.last().unwrap()["id"], 99);
+
+    let untouched = apply_output_budget(text.clone(), usize::MAX, 0);
+    assert_eq!(untouched, text);
+
+    let paged = apply_output_budget(text, usize::MAX, 90);
+    let parsed: Vec<serde_json::Value> = serde_json::from_str(&paged).unwrap();
+    assert_eq!(parsed.len(), 10);
+    assert_eq!(parsed[0]["id"], 90);
+}
+
+#[test]
+fn test_output_budget_moves_past_oversized_entries() {
+    use raworc_mcp::mcp::apply_output_budget;
+
+    // Lines: the first is bigger than the whole budget
+    let text = format!("{}\nsecond\nthird", "é".repeat(500));
+    let out = apply_output_budget(text.clone(), 100, 0);
+    assert!(out.chars().count() <= 200);
+    assert!(out.starts_with(&"é".repeat(70)));
+    assert!(out.contains("entry 0 cut short"));
+    assert!(out.contains("use cursor 1 to continue"));
+    assert!(out.ends_with("second\nthird"));
+    assert_eq!(apply_output_budget(text, 100, 1), "second\nthird");
+
+    // Array items: the oversized item keeps its shape, only its string is cut
+    let items = serde_json::json!([{ "log": "x".repeat(500) }, { "id": 1 }, { "id": 2 }]);
+    let out = apply_output_budget(serde_json::to_string_pretty(&items).unwrap(), 100, 0);
+    let parsed: Vec<serde_json::Value> = serde_json::from_str(&out).unwrap();
+    assert!(parsed[0]["log"].as_str().unwrap().starts_with("xxx"));
+    assert!(parsed[1].as_str().unwrap().contains("use cursor 1 to continue"));
+    assert_eq!(parsed.last().unwrap()["id"], 2);
+
+    // Objects are cut between fields and stay valid JSON
+    let build = serde_json::json!({ "id": "b1", "logs": "y".repeat(400), "status": "failed" });
+    let out = apply_output_budget(serde_json::to_string_pretty(&build).unwrap(), 120, 0);
+    let parsed: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(parsed["id"], "b1");
+    assert!(parsed["..."].as_str().unwrap().contains("fields omitted"));
+    assert!(out.chars().count() < 240);
+
+    // Multi-byte text within budget by characters is left alone
+    let wide = "日本語".repeat(30);
+    assert_eq!(apply_output_budget(wide.clone(), 90, 0), wide);
+}
+
+#[tokio::test]
+async fn test_list_sessions_markdown_table() {
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/sessions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            session_json("s1", "RUNNING"),
+            session_json("s2", "IDLE")
+        ])))
+        .mount(&server)
+        .await;
+
+    let config = Config::new(format!("{}/api/v0", server.uri())).with_auth_token("t".to_string());
+    let mut mcp = RaworcMcpServer::new(config).unwrap();
+    let response = mcp
+        .handle_tool_call("list_sessions", &json!({ "format": "markdown", "columns": ["id", "state"] }))
+        .await
+        .unwrap();
+    let text = response.content[0].text.clone().unwrap();
+    assert_eq!(text, "| id | state |\n| --- | --- |\n| s1 | RUNNING |\n| s2 | IDLE |\n");
+
+    let bad = mcp
+        .handle_tool_call("list_sessions", &json!({ "format": "xml" }))
+        .await;
+    assert!(bad.is_err());
+}
+
+#[tokio::test]
+async fn test_server_over_in_memory_backend() {
+    use raworc_mcp::models::MessageRole;
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcApi, RaworcMcpServer};
+    use serde_json::json;
+
+    let backend = InMemoryRaworc::new();
+    let session = backend.create_session(None, None).await.unwrap();
+    backend.push_message(&session.id, MessageRole::Assistant, "ready").unwrap();
+
+    let mut mcp = RaworcMcpServer::with_client(backend, Config::new("memory://".to_string()));
+    let response = mcp
+        .handle_tool_call("get_messages", &json!({ "session_id": session.id, "format": "json" }))
+        .await
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_str(response.content[0].text.as_ref().unwrap()).unwrap();
+    assert_eq!(messages[0]["content"], "ready");
+
+    let missing = mcp
+        .handle_tool_call("get_session", &json!({ "session_id": "nope" }))
+        .await;
+    assert!(missing.is_err());
+}
+
+#[tokio::test]
+async fn test_admin_tools_over_in_memory_backend() {
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::json;
+
+    let mut mcp = RaworcMcpServer::with_client(InMemoryRaworc::new(), Config::new("memory://".to_string()));
+    let mut call = async |tool: &str, args: serde_json::Value| -> serde_json::Value {
+        let mut args = args;
+        args["format"] = json!("json");
+        let response = mcp.handle_tool_call(tool, &args).await.unwrap();
+        let text = response.content[0].text.clone().unwrap();
+        serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text))
+    };
+
+    let space = call("create_space", json!({ "name": "team-b", "settings": { "region": "eu" } })).await;
+    assert_eq!(space["settings"]["region"], "eu");
+
+    let account = call("create_service_account", json!({ "user": "ci", "pass": "hunter2", "space": "team-b" })).await;
+    assert_eq!(account["user"], "ci");
+    let role = call(
+        "create_role",
+        json!({ "id": "reader", "rules": [{ "resources": ["sessions"], "verbs": ["get"], "scope": "space" }] }),
+    )
+    .await;
+    assert_eq!(role["name"], "reader");
+    let binding = call("create_role_binding", json!({ "subject": "ci", "role_ref": "reader", "space": "team-b" })).await;
+    assert_eq!(binding["role_ref"], "reader");
+    assert_eq!(call("list_role_bindings", json!({})).await.as_array().unwrap().len(), 1);
+
+    call("create_agent", json!({ "space": "team-b", "name": "builder" })).await;
+    call("deploy_agent", json!({ "space": "team-b", "agent_name": "builder" })).await;
+    let running = call("list_running_agents", json!({ "space": "team-b" })).await;
+    assert_eq!(running[0]["name"], "builder");
+    call("stop_agent", json!({ "space": "team-b", "agent_name": "builder" })).await;
+    assert_eq!(call("list_running_agents", json!({ "space": "team-b" })).await, json!([]));
+
+    call("create_secret", json!({ "space": "team-b", "key_name": "TOKEN", "value": "v1" })).await;
+    let secret = call("update_secret", json!({ "space": "team-b", "key": "TOKEN", "description": "deploy token" })).await;
+    assert_eq!(secret["description"], "deploy token");
+    assert_ne!(secret["value"], "v1", "secret values stay masked");
+
+    // Running agents render as a table like other lists, honouring `columns`
+    call("deploy_agent", json!({ "space": "team-b", "agent_name": "builder" })).await;
+    let args = json!({ "space": "team-b", "format": "markdown", "columns": ["name", "status"] });
+    let table = mcp.handle_tool_call("list_running_agents", &args).await.unwrap();
+    assert_eq!(table.content[0].text.as_deref(), Some("| name | status |\n| --- | --- |\n| builder | running |\n"));
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_server_session_lifecycle() {
+    use raworc_mcp::mock::{self, MockOptions};
+    use raworc_mcp::models::{MessageRole, SessionState};
+    use raworc_mcp::{RaworcApi, RaworcClient};
+    use std::time::Duration;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let options = MockOptions {
+        replies: vec!["first: {content}".to_string(), "second".to_string()],
+        think_time: Duration::from_millis(50),
+        build_time: Duration::from_millis(50),
+    };
+    tokio::spawn(async move { axum::serve(listener, mock::router(options)).await });
+
+    let config = Config::new(format!("http://{addr}/api/v0"));
+    let mut client = RaworcClient::new(&config).unwrap();
+    assert!(client.list_spaces().await.is_err());
+    client.authenticate("admin", "secret").await.unwrap();
+    assert_eq!(client.get_user_info().await.unwrap().user, "admin");
+
+    let session = client.create_session(None, None).await.unwrap();
+    assert_eq!(session.state, SessionState::Init);
+    client
+        .wait_for_session_state(None, &session.id, &[SessionState::Idle], Duration::from_secs(5), &|_, _| {})
+        .await
+        .unwrap();
+
+    let exchange = client
+        .send_message_and_wait(None, &session.id, "hello", Duration::from_secs(5), &|_, _| {})
+        .await
+        .unwrap();
+    let reply = exchange.reply.expect("assistant reply");
+    assert_eq!(reply.role, MessageRole::Assistant);
+    assert_eq!(reply.content, "first: hello");
+    assert_eq!(client.get_message_count(None, &session.id).await.unwrap().count, 2);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_every_tool_runs_against_the_mock_server() {
+    use raworc_mcp::mock::{self, MockOptions};
+    use raworc_mcp::{AuditConfig, RaworcMcpServer};
+    use serde_json::{json, Value};
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let options = MockOptions {
+        think_time: Duration::from_millis(20),
+        build_time: Duration::from_millis(20),
+        ..MockOptions::default()
+    };
+    tokio::spawn(async move { axum::serve(listener, mock::router(options)).await });
+
+    let dir = std::env::temp_dir().join(format!("raworc-mock-tools-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let config = Config::new(format!("http://{addr}/api/v0"))
+        .with_credentials("admin".to_string(), "secret".to_string())
+        .with_audit_log(AuditConfig::new(dir.join("audit.jsonl")));
+    let mut mcp = RaworcMcpServer::new(config).unwrap();
+    let mut called = BTreeSet::new();
+    let mut call = async |tool: &str, mut args: Value| -> Value {
+        args["format"] = json!("json");
+        let response = mcp.handle_tool_call(tool, &args).await;
+        let response = response.unwrap_or_else(|e| panic!("{} failed against the mock: {}", tool, e));
+        called.insert(tool.to_string());
+        let text = response.content[0].text.clone().unwrap();
+        serde_json::from_str(&text).unwrap_or(Value::String(text))
+    };
+
+    call("health_check", json!({})).await;
+    call("get_version", json!({})).await;
+
+    call("create_space", json!({ "name": "team" })).await;
+    call("create_space", json!({ "name": "scratch" })).await;
+    call("list_spaces", json!({})).await;
+    call("get_space", json!({ "name": "scratch" })).await;
+    call("update_space", json!({ "name": "scratch", "description": "throwaway" })).await;
+    call("delete_space", json!({ "name": "scratch" })).await;
+
+    let account = call("create_service_account", json!({ "user": "ci", "pass": "pw1", "space": "team" })).await;
+    let account = json!({ "id": account["id"] });
+    call("list_service_accounts", json!({})).await;
+    call("get_service_account", account.clone()).await;
+    call("update_service_account", json!({ "id": account["id"], "description": "CI" })).await;
+    let password = json!({ "id": account["id"], "current_password": "pw1", "new_password": "pw2" });
+    call("update_service_account_password", password).await;
+
+    let rules = json!([{ "resources": ["sessions"], "verbs": ["get"], "scope": "space" }]);
+    call("create_role", json!({ "id": "reader", "rules": rules })).await;
+    call("list_roles", json!({})).await;
+    call("get_role", json!({ "id": "reader" })).await;
+    let binding = call("create_role_binding", json!({ "subject": "ci", "role_ref": "reader", "space": "team" })).await;
+    let binding = json!({ "id": binding["id"] });
+    call("list_role_bindings", json!({})).await;
+    call("get_role_binding", binding.clone()).await;
+
+    let session = call("create_session", json!({ "space": "team" })).await;
+    let id = json!({ "session_id": session["id"], "space": "team" });
+    let with = |extra: Value| {
+        let mut args = id.clone();
+        args.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        args
+    };
+    call("wait_session", with(json!({ "states": ["IDLE"], "timeout_seconds": 5 }))).await;
+    call("list_sessions", json!({ "space": "team" })).await;
+    call("get_session", id.clone()).await;
+    call("update_session", with(json!({ "metadata": { "k": "v" } }))).await;
+    call("ask_session", with(json!({ "content": "hello", "timeout_seconds": 5 }))).await;
+    call("send_message", with(json!({ "content": "again" }))).await;
+    call("get_messages", id.clone()).await;
+    call("get_message_count", id.clone()).await;
+    call("clear_messages", id.clone()).await;
+    call("update_session_state", with(json!({ "state": "IDLE" }))).await;
+    call("pause_session", id.clone()).await;
+    call("resume_session", id.clone()).await;
+    call("remix_session", id.clone()).await;
+    call("close_session", json!({ "session_id": session["id"] })).await;
+    call("restore_session", json!({ "session_id": session["id"] })).await;
+    call("terminate_session", id.clone()).await;
+
+    let agent = json!({ "space": "team", "agent_name": "builder" });
+    call("create_agent", json!({ "space": "team", "name": "builder" })).await;
+    call("list_agents", json!({ "space": "team" })).await;
+    call("get_agent", agent.clone()).await;
+    call("update_agent", json!({ "space": "team", "agent_name": "builder", "purpose": "build" })).await;
+    call("update_agent_status", json!({ "space": "team", "agent_name": "builder", "status": "active" })).await;
+    call("deploy_agent", agent.clone()).await;
+    let running = call("list_running_agents", json!({ "space": "team" })).await;
+    assert_eq!(running[0]["name"], "builder");
+    call("stop_agent", agent.clone()).await;
+    call("get_agent_logs", agent.clone()).await;
+
+    call("create_secret", json!({ "space": "team", "key_name": "TOKEN", "value": "v1" })).await;
+    call("list_secrets", json!({ "space": "team" })).await;
+    call("get_secret", json!({ "space": "team", "key": "TOKEN" })).await;
+    call("set_secret", json!({ "space": "team", "key": "OTHER", "value": "v2" })).await;
+    call("update_secret", json!({ "space": "team", "key": "TOKEN", "description": "deploy" })).await;
+    call("delete_secret", json!({ "space": "team", "key": "OTHER" })).await;
+
+    let build = call("create_build", json!({ "space": "team" })).await;
+    call("get_build", json!({ "space": "team", "build_id": build["id"] })).await;
+    call("wait_for_build", json!({ "space": "team", "timeout_seconds": 5 })).await;
+    call("get_latest_build", json!({ "space": "team" })).await;
+    call("audit_log", json!({})).await;
+
+    call("delete_agent", agent).await;
+    call("delete_role_binding", binding).await;
+    call("delete_role", json!({ "id": "reader" })).await;
+    call("delete_service_account", account).await;
+    std::fs::remove_dir_all(&dir).ok();
+
+    let capabilities: Value = serde_json::from_str(raworc_mcp::CAPABILITIES).unwrap();
+    let advertised: BTreeSet<String> = capabilities["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(called, advertised, "every advertised tool runs against the mock");
+}
+
+#[tokio::test]
+async fn test_cassette_record_then_replay() {
+    use raworc_mcp::cassette::REDACTED;
+    use raworc_mcp::{CassetteMode, RaworcClient};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/secrets/API_KEY"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "key_name": "API_KEY",
+            "value": "sk-live-123",
+            "description": null,
+            "space": "default",
+            "created_at": "2025-01-01T00:00:00Z",
+            "updated_at": "2025-01-01T00:00:00Z",
+            "created_by": "tester"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/missing"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("space missing"))
+        .mount(&server)
+        .await;
+
+    let cassette = std::env::temp_dir().join(format!("raworc-cassette-{}.json", uuid::Uuid::new_v4()));
+    let config = Config::new(format!("{}/api/v0", server.uri()))
+        .with_auth_token("super-secret-token".to_string())
+        .with_cassette(CassetteMode::Record(cassette.clone()));
+    let client = RaworcClient::new(&config).unwrap();
+    assert_eq!(client.get_secret("default", "API_KEY").await.unwrap().value.as_deref(), Some("sk-live-123"));
+    assert!(client.get_space("missing").await.is_err());
+
+    let text = std::fs::read_to_string(&cassette).unwrap();
+    assert!(!text.contains("sk-live-123"));
+    assert!(!text.contains("super-secret-token"));
+    assert!(text.contains(REDACTED));
+
+    // Replay never reaches the (now unreachable) server
+    let config = Config::new("http://127.0.0.1:9/api/v0".to_string())
+        .with_cassette(CassetteMode::Replay(cassette.clone()));
+    let client = RaworcClient::new(&config).unwrap();
+    let secret = client.get_secret("default", "API_KEY").await.unwrap();
+    assert_eq!(secret.key_name, "API_KEY");
+    assert_eq!(secret.value.as_deref(), Some(REDACTED));
+    assert!(matches!(client.get_space("missing").await, Err(raworc_mcp::RaworcError::NotFound { .. })));
+    // Each recorded interaction is replayed once
+    assert!(matches!(client.get_space("missing").await, Err(raworc_mcp::RaworcError::InternalError(_))));
+    let _ = std::fs::remove_file(cassette);
+}
+
+#[test]
+fn test_cache_invalidates_every_ancestor_of_a_write() {
+    use raworc_mcp::cache::{CacheConfig, ResponseCache};
+    use std::time::Duration;
+
+    let cache = ResponseCache::new(CacheConfig::default());
+    let ttl = Duration::from_secs(60);
+    for path in [
+        "spaces",
+        "spaces/x",
+        "spaces/x/agents",
+        "spaces/x/agents/bot",
+        "spaces/x/agents/bot/status",
+        "spaces/x/agents/bot/logs?tail=5",
+        "spaces/x/agents/other",
+        "spaces/y/agents",
+        "version",
+    ] {
+        cache.put(path, "{}".to_string(), None, ttl);
+    }
+
+    cache.invalidate("/spaces/x/agents/bot/status");
+    for gone in ["spaces", "spaces/x", "spaces/x/agents", "spaces/x/agents/bot", "spaces/x/agents/bot/status"] {
+        assert!(cache.get(gone).is_none(), "{} still cached", gone);
+    }
+    for kept in ["spaces/x/agents/bot/logs?tail=5", "spaces/x/agents/other", "spaces/y/agents", "version"] {
+        assert!(cache.get(kept).is_some(), "{} was dropped", kept);
+    }
+}
+
+#[tokio::test]
+async fn test_cache_serves_reads_and_invalidates_on_write() {
+    use raworc_mcp::{CacheConfig, RaworcClient};
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let agent = json!({
+        "name": "bot",
+        "description": null,
+        "purpose": null,
+        "source_repo": null,
+        "source_branch": null,
+        "status": "active",
+        "created_at": "2025-01-01T00:00:00Z",
+        "updated_at": "2025-01-01T00:00:00Z"
+    });
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/agents"))
+        .and(header("if-none-match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/default/agents"))
+        .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"v1\"").set_body_json(json!([agent])))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/api/v0/spaces/default/agents/bot"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&agent))
+        .mount(&server)
+        .await;
+
+    let ttl = std::time::Duration::from_millis(200);
+    let config = Config::new(format!("{}/api/v0", server.uri()))
+        .with_auth_token("t".to_string())
+        .with_cache(CacheConfig::with_ttl(ttl));
+    let client = RaworcClient::new(&config).unwrap();
+
+    // Miss, then hit
+    assert_eq!(client.list_agents(None).await.unwrap().len(), 1);
+    assert_eq!(client.list_agents(None).await.unwrap().len(), 1);
+    // Expired: revalidated with the ETag and answered by a 304
+    tokio::time::sleep(ttl * 2).await;
+    assert_eq!(client.list_agents(None).await.unwrap().len(), 1);
+    // A write invalidates the list, so the next read goes back to the server
+    let update = serde_json::from_value(json!({ "description": "new" })).unwrap();
+    client.update_agent("default", "bot", &update).await.unwrap();
+    assert_eq!(client.list_agents(None).await.unwrap().len(), 1);
+}
+
+#[test]
+fn test_tls_settings_are_validated_at_startup() {
+    use raworc_mcp::{RaworcClient, RaworcError};
+
+    let base = Config::new("https://api.example.com/api/v0".to_string());
+
+    let missing = base.clone().with_ca_cert("/nonexistent/ca.pem".into());
+    match RaworcClient::new(&missing) {
+        Err(RaworcError::ConfigError(msg)) => assert!(msg.contains("/nonexistent/ca.pem")),
+        other => panic!("expected ConfigError, got {:?}", other.err()),
+    }
+
+    let garbage = std::env::temp_dir().join(format!("raworc-ca-{}.pem", uuid::Uuid::new_v4()));
+    std::fs::write(&garbage, "not a certificate").unwrap();
+    let invalid = base.clone().with_ca_cert(garbage.clone());
+    assert!(matches!(RaworcClient::new(&invalid), Err(RaworcError::ConfigError(_))));
+    let _ = std::fs::remove_file(garbage);
+
+    let mut half_mtls = base.clone();
+    half_mtls.client_cert = Some("/tmp/cert.pem".into());
+    assert!(matches!(RaworcClient::new(&half_mtls), Err(RaworcError::ConfigError(_))));
+
+    let proxied = base.with_proxy("http://proxy.corp:3128".to_string(), Some("localhost,.internal".to_string()));
+    assert!(RaworcClient::new(&proxied).is_ok());
+}
+
+#[test]
+fn test_load_layers_profile_under_flags() {
+    use raworc_mcp::{LoadOptions, RaworcError};
+
+    let path = std::env::temp_dir().join(format!("raworc-config-{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        r#"
+default_profile = "staging"
+
+[profiles.staging]
+api_url = "https://staging.example.com/api/v0"
+auth_token = "staging-token"
+default_space = "sandbox"
+timeout_seconds = 60
+
+[profiles.prod]
+api_url = "https://api.example.com/api/v0"
+username = "ops"
+password = "secret"
+"#,
+    )
+    .unwrap();
+
+    let options = LoadOptions {
+        config_file: Some(path.clone()),
+        ..Default::default()
+    };
+    let staging = Config::load(&options).unwrap();
+    assert_eq!(staging.api_url.as_deref(), Some("https://staging.example.com/api/v0"));
+    assert_eq!(staging.default_space.as_deref(), Some("sandbox"));
+    assert_eq!(staging.timeout_seconds, Some(60));
+
+    let prod = Config::load(&LoadOptions {
+        profile: Some("prod".to_string()),
+        default_space: Some("default".to_string()),
+        ..options.clone()
+    })
+    .unwrap();
+    assert_eq!(prod.username.as_deref(), Some("ops"));
+    assert_eq!(prod.default_space.as_deref(), Some("default"));
+
+    let unknown = Config::load(&LoadOptions {
+        profile: Some("qa".to_string()),
+        ..options.clone()
+    });
+    assert!(matches!(unknown, Err(RaworcError::ConfigError(msg)) if msg.contains("prod, staging")));
+
+    let zero = Config::load(&LoadOptions {
+        timeout_seconds: Some(0),
+        ..options.clone()
+    });
+    assert!(matches!(zero, Err(RaworcError::ConfigError(_))));
+
+    // Credentials from the environment replace the profile's token, so login is not skipped
+    std::env::set_var("RAWORC_USERNAME", "env-user");
+    std::env::set_var("RAWORC_PASSWORD", "env-pass");
+    let env_login = Config::load(&options);
+    std::env::remove_var("RAWORC_USERNAME");
+    std::env::remove_var("RAWORC_PASSWORD");
+    let env_login = env_login.unwrap();
+    assert_eq!(env_login.username.as_deref(), Some("env-user"));
+    assert!(env_login.auth_token.is_none());
+    assert_eq!(env_login.api_url.as_deref(), Some("https://staging.example.com/api/v0"));
+
+    // Half a login, or a token next to a login, is refused instead of guessed at
+    std::env::set_var("RAWORC_USERNAME", "env-user");
+    let half = Config::load(&options);
+    std::env::set_var("RAWORC_AUTH_TOKEN", "env-token");
+    std::env::set_var("RAWORC_PASSWORD", "env-pass");
+    let both = Config::load(&options);
+    std::env::remove_var("RAWORC_USERNAME");
+    std::env::remove_var("RAWORC_PASSWORD");
+    std::env::remove_var("RAWORC_AUTH_TOKEN");
+    assert!(matches!(half, Err(RaworcError::ConfigError(msg)) if msg.contains("without RAWORC_PASSWORD")));
+    assert!(matches!(both, Err(RaworcError::ConfigError(msg)) if msg.contains("not both")));
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_parse_tool_arguments_from_cli() {
+    use raworc_mcp::mcp::parse_tool_arguments;
+
+    let pairs = vec!["max_chars=5".to_string(), "space=prod".to_string(), "verbose=true".to_string()];
+    let args = parse_tool_arguments("list_sessions", Some(r#"{"space": "dev", "state": "idle"}"#), &pairs).unwrap();
+    assert_eq!(args, serde_json::json!({"space": "prod", "state": "idle", "max_chars": 5, "verbose": "true"}));
+
+    // Schema string properties stay strings even when they look like JSON
+    let pairs = vec!["session_id=42".to_string(), "content=null".to_string(), "columns=[\"id\"]".to_string()];
+    let args = parse_tool_arguments("get_session", None, &pairs).unwrap();
+    assert_eq!(args, serde_json::json!({"session_id": "42", "content": "null", "columns": ["id"]}));
+    assert!(parse_tool_arguments("list_sessions", None, &["max_chars=lots".to_string()]).is_err());
+
+    assert!(parse_tool_arguments("list_sessions", Some("[1, 2]"), &[]).is_err());
+    assert!(parse_tool_arguments("list_sessions", None, &["no-equals".to_string()]).is_err());
+}
+
+#[tokio::test]
+async fn test_doctor_reports_expired_token_and_missing_space() {
+    use base64::Engine;
+    use raworc_mcp::doctor::{self, Report, Status};
+    use raworc_mcp::testing::InMemoryRaworc;
+
+    let jwt = |exp: i64| {
+        let claims = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(format!(r#"{{"sub":"ops","exp":{}}}"#, exp));
+        format!("eyJhbGciOiJIUzI1NiJ9.{}.sig", claims)
+    };
+    let base = Config::new("https://api.example.com/api/v0".to_string());
+    let now = chrono::Utc::now().timestamp();
+
+    let expired = base.clone().with_auth_token(jwt(now - 60));
+    let report = doctor::run(&mut InMemoryRaworc::new(), &expired, Report::default()).await;
+    assert!(!report.is_ok());
+    assert_eq!(report.get("health").unwrap().status, Status::Pass);
+    assert_eq!(report.get("auth").unwrap().status, Status::Fail);
+    assert_eq!(report.get("secrets").unwrap().status, Status::Skip);
+
+    let wrong_space = base.clone().with_auth_token(jwt(now + 7 * 86400)).with_default_space("nope".to_string());
+    let report = doctor::run(&mut InMemoryRaworc::new(), &wrong_space, Report::default()).await;
+    let space = report.get("default_space").unwrap();
+    assert_eq!(space.status, Status::Fail);
+    assert!(space.hint.as_deref().unwrap().contains("list_spaces"));
+
+    let healthy = base.with_auth_token(jwt(now + 7 * 86400)).with_default_space("default".to_string());
+    let report = doctor::run(&mut InMemoryRaworc::new(), &healthy, Report::default()).await;
+    assert!(report.is_ok(), "{}", report);
+    assert_eq!(report.get("agents").unwrap().status, Status::Pass);
+}
+
+#[tokio::test]
+async fn test_secret_values_are_masked_unless_revealed() {
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::{json, Value};
+
+    let value = "sk-live-4c1d8e7b9f2a";
+    let secret_value = |response: raworc_mcp::models::ToolCallResponse| -> Value {
+        let text = response.content[0].text.clone().unwrap();
+        serde_json::from_str::<Value>(&text).unwrap()["value"].clone()
+    };
+    let set = json!({"space": "default", "key": "API_KEY", "value": value, "format": "json"});
+    let get = json!({"space": "default", "key": "API_KEY", "format": "json"});
+
+    let mut masked = RaworcMcpServer::with_client(InMemoryRaworc::new(), Config::new("memory://".to_string()));
+    assert_eq!(secret_value(masked.handle_tool_call("set_secret", &set).await.unwrap()), "sk-…9f2a");
+    assert_eq!(secret_value(masked.handle_tool_call("get_secret", &get).await.unwrap()), "sk-…9f2a");
+    let listed = masked.handle_tool_call("list_secrets", &json!({"space": "default"})).await.unwrap();
+    assert!(!listed.content[0].text.as_ref().unwrap().contains("9f2a"));
+
+    // The host confirms through elicitation
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    masked.set_progress_sender(tx);
+    masked.set_elicitation_supported(true);
+    let elicitor = masked.elicitor();
+    let host = tokio::spawn(async move {
+        let request = rx.recv().await.unwrap();
+        assert_eq!(request["method"], "elicitation/create");
+        elicitor.resolve(&json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {"action": "accept", "content": {"confirm": true}}
+        }))
+    });
+    assert_eq!(secret_value(masked.handle_tool_call("get_secret", &get).await.unwrap()), value);
+    assert!(host.await.unwrap());
+
+    let config = Config::new("memory://".to_string()).with_revealed_secrets("default".to_string());
+    let mut revealed = RaworcMcpServer::with_client(InMemoryRaworc::new(), config);
+    assert_eq!(secret_value(revealed.handle_tool_call("set_secret", &set).await.unwrap()), value);
+}
+
+#[tokio::test]
+async fn test_initialize_negotiates_version_and_enables_elicitation() {
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::{json, Value};
+
+    let mut server = RaworcMcpServer::with_client(InMemoryRaworc::new(), Config::new("memory://".to_string()));
+    let old = server.handle_initialize(&json!({"protocolVersion": "2024-11-05", "capabilities": {}}));
+    assert_eq!(old["protocolVersion"], "2024-11-05");
+    let unknown = server.handle_initialize(&json!({"protocolVersion": "1999-01-01", "capabilities": {}}));
+    assert_eq!(unknown["protocolVersion"], "2025-06-18");
+
+    let result = server.handle_initialize(&json!({
+        "protocolVersion": "2025-06-18",
+        "capabilities": {"elicitation": {}},
+        "clientInfo": {"name": "test-host", "version": "1.0"}
+    }));
+    assert_eq!(result["protocolVersion"], "2025-06-18");
+    assert_eq!(result["serverInfo"]["name"], "raworc-mcp");
+
+    let set = json!({"space": "default", "key": "API_KEY", "value": "sk-live-4c1d8e7b9f2a"});
+    server.handle_tool_call("set_secret", &set).await.unwrap();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    server.set_progress_sender(tx);
+    let elicitor = server.elicitor();
+    let host = tokio::spawn(async move {
+        let request = rx.recv().await.unwrap();
+        assert_eq!(request["method"], "elicitation/create");
+        elicitor.resolve(&json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {"action": "accept", "content": {"confirm": true}}
+        }))
+    });
+    let get = json!({"space": "default", "key": "API_KEY", "format": "json"});
+    let response = server.handle_tool_call("get_secret", &get).await.unwrap();
+    let secret: Value = serde_json::from_str(response.content[0].text.as_ref().unwrap()).unwrap();
+    assert_eq!(secret["value"], "sk-live-4c1d8e7b9f2a");
+    assert!(host.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_reauth_retries_with_the_new_token() {
+    use raworc_mcp::RaworcClient;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v0/auth/login"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "token": "fresh" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces"))
+        .and(header("authorization", "Bearer fresh"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces"))
+        .and(header("authorization", "Bearer stale"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("token expired"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = Config::new(format!("{}/api/v0", server.uri()))
+        .with_auth_token("stale".to_string())
+        .with_credentials("ops".to_string(), "hunter2".to_string());
+    let client = RaworcClient::new(&config).unwrap();
+    let retries = raworc_mcp::metrics::metrics().api_retries.get();
+    assert!(client.list_spaces().await.unwrap().is_empty());
+    assert!(raworc_mcp::metrics::metrics().api_retries.get() > retries);
+    // The refreshed token is kept, so the next call needs no second login
+    assert!(client.list_spaces().await.unwrap().is_empty());
+}
+
+#[test]
+fn test_credentials_never_reach_debug_output() {
+    use raworc_mcp::models::{AuthRequest, UpdatePasswordRequest};
+    use raworc_mcp::redact::{redact_arguments, REDACTED};
+    use serde_json::json;
+
+    let config = Config::new("https://api.example.com/api/v0".to_string())
+        .with_auth_token("tok-123456".to_string())
+        .with_credentials("ops".to_string(), "hunter2".to_string());
+    let debug = format!("{:?}", config);
+    assert!(!debug.contains("tok-123456") && !debug.contains("hunter2"));
+    assert!(debug.contains("ops"));
+
+    let login = AuthRequest { user: "ops".to_string(), pass: "hunter2".into() };
+    assert!(!format!("{:?}", login).contains("hunter2"));
+    assert_eq!(serde_json::to_value(&login).unwrap()["pass"], "hunter2");
+
+    let change = UpdatePasswordRequest { current_password: "old-pw".into(), new_password: "new-pw".into() };
+    let debug = format!("{:?}", change);
+    assert!(!debug.contains("old-pw") && !debug.contains("new-pw"));
+
+    let args = json!({"space": "dev", "key": "API_KEY", "value": "sk-live-1"});
+    assert_eq!(
+        redact_arguments("set_secret", &args),
+        json!({"space": "dev", "key": "API_KEY", "value": REDACTED})
+    );
+    // `value` is only sensitive for tools that declare it
+    assert_eq!(redact_arguments("update_agent", &json!({"value": 3})), json!({"value": 3}));
+    let account = redact_arguments("create_service_account", &json!({"user": "bot", "pass": "pw"}));
+    assert_eq!(account["pass"], REDACTED);
+}
+
+#[test]
+fn test_sensitive_arguments_come_from_tool_schemas() {
+    use raworc_mcp::redact::{redact_result, sensitive_arguments, sensitive_result_fields, REDACTED};
+    use serde_json::json;
+
+    assert_eq!(sensitive_arguments("set_secret"), ["value"]);
+    assert_eq!(sensitive_arguments("create_service_account"), ["pass"]);
+    let mut passwords = sensitive_arguments("update_service_account_password").to_vec();
+    passwords.sort();
+    assert_eq!(passwords, ["current_password", "new_password"]);
+    assert!(sensitive_arguments("list_sessions").is_empty());
+
+    // Results: only tools that declare sensitive fields get them masked
+    assert_eq!(sensitive_result_fields("get_secret"), ["value"]);
+    assert!(sensitive_result_fields("delete_secret").is_empty());
+    let secret = json!({"key_name": "K", "value": "sk-live-1"}).to_string();
+    assert_eq!(redact_result("get_secret", &secret), json!({"key_name": "K", "value": REDACTED}).to_string());
+    assert_eq!(redact_result("get_secret", "| value | sk-live-1 |"), REDACTED);
+    let agent = json!({"env": {"value": "debug"}}).to_string();
+    assert_eq!(redact_result("get_agent", &agent), agent);
+
+    // Every credential-looking property, and every secret value, is marked in the schema
+    let capabilities: serde_json::Value = serde_json::from_str(raworc_mcp::CAPABILITIES).unwrap();
+    for tool in capabilities["tools"].as_array().unwrap() {
+        let name = tool["name"].as_str().unwrap();
+        let Some(properties) = tool["inputSchema"]["properties"].as_object() else {
+            continue;
+        };
+        for key in properties.keys() {
+            let credential = ["pass", "password", "token", "auth_token"].contains(&key.as_str()) || key.ends_with("_password");
+            let secret_value = key == "value" && name.ends_with("_secret");
+            if credential || secret_value {
+                assert!(sensitive_arguments(name).contains(key), "{}.{} is not marked writeOnly", name, key);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_read_only_and_tool_filters() {
+    use raworc_mcp::policy::glob_match;
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcError, RaworcMcpServer, ToolFilter};
+    use serde_json::json;
+
+    assert!(glob_match("list_*", "list_sessions"));
+    assert!(glob_match("*_secret?", "list_secrets"));
+    assert!(!glob_match("list_*", "get_session"));
+
+    let tools = json!({"tools": [
+        {"name": "list_sessions"}, {"name": "send_message"}, {"name": "delete_agent"},
+        {"name": "get_secret"}, {"name": "list_secrets"}
+    ]});
+    let names = |tools: serde_json::Value| -> Vec<String> {
+        tools["tools"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap().to_string()).collect()
+    };
+
+    let config = Config::new("memory://".to_string()).with_read_only();
+    let mut server = RaworcMcpServer::with_client(InMemoryRaworc::new(), config);
+    assert_eq!(names(server.filter_tools(tools.clone())), ["list_sessions", "get_secret", "list_secrets"]);
+    assert!(server.handle_tool_call("list_sessions", &json!({})).await.is_ok());
+    let refused = server.handle_tool_call("create_session", &json!({})).await;
+    assert!(matches!(refused, Err(RaworcError::PolicyError(msg)) if msg.contains("read-only")));
+
+    let filter = ToolFilter {
+        allow: vec!["list_*".to_string(), "*_secret".to_string()],
+        deny: vec!["get_secret".to_string()],
+    };
+    let server = RaworcMcpServer::with_client(InMemoryRaworc::new(), Config::new("memory://".to_string()).with_tool_filter(filter));
+    assert_eq!(names(server.filter_tools(tools)), ["list_sessions", "list_secrets"]);
+}
+
+#[tokio::test]
+async fn test_allowed_spaces_limit_calls_and_listing() {
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcApi, RaworcError, RaworcMcpServer};
+    use serde_json::json;
+
+    let backend = InMemoryRaworc::new();
+    backend.create_space(&space_request("team-a")).await.unwrap();
+    backend.create_space(&space_request("prod-billing")).await.unwrap();
+    let config = Config::new("memory://".to_string())
+        .with_allowed_spaces(vec!["default".to_string(), "team-*".to_string()])
+        .with_output_format(raworc_mcp::OutputFormat::Json);
+    let mut server = RaworcMcpServer::with_client(backend, config);
+
+    let listed = server.handle_tool_call("list_spaces", &json!({})).await.unwrap();
+    let spaces: serde_json::Value = serde_json::from_str(listed.content[0].text.as_ref().unwrap()).unwrap();
+    let mut names: Vec<&str> = spaces.as_array().unwrap().iter().map(|s| s["name"].as_str().unwrap()).collect();
+    names.sort();
+    assert_eq!(names, ["default", "team-a"]);
+
+    assert!(server.handle_tool_call("list_sessions", &json!({"space": "team-a"})).await.is_ok());
+    // No `space` argument resolves to the default space
+    assert!(server.handle_tool_call("list_agents", &json!({})).await.is_ok());
+    let denied = server.handle_tool_call("list_secrets", &json!({"space": "prod-billing"})).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("prod-billing")));
+}
+
+#[tokio::test]
+async fn test_allowed_spaces_check_the_source_session_of_session_tools() {
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcApi, RaworcError, RaworcMcpServer};
+    use serde_json::json;
+
+    let backend = InMemoryRaworc::new();
+    backend.create_space(&space_request("team-a")).await.unwrap();
+    backend.create_space(&space_request("prod-billing")).await.unwrap();
+    let prod_session = backend.create_session(Some("prod-billing"), None).await.unwrap();
+    let team_session = backend.create_session(Some("team-a"), None).await.unwrap();
+    let config = Config::new("memory://".to_string()).with_allowed_spaces(vec!["team-*".to_string()]);
+    let mut server = RaworcMcpServer::with_client(backend, config);
+
+    // An allowed target space does not make a denied source session reachable
+    let remix = json!({"session_id": prod_session.id, "space": "team-a"});
+    let denied = server.handle_tool_call("remix_session", &remix).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("prod-billing")));
+    let close = json!({"session_id": prod_session.id, "space": "team-a"});
+    let denied = server.handle_tool_call("close_session", &close).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("prod-billing")));
+
+    // Nor can an allowed session be remixed into a denied space
+    let remix = json!({"session_id": team_session.id, "space": "prod-billing"});
+    let denied = server.handle_tool_call("remix_session", &remix).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("prod-billing")));
+    let remix = json!({"session_id": team_session.id, "space": "team-a"});
+    assert!(server.handle_tool_call("remix_session", &remix).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_policy_rules_decide_before_dispatch() {
+    use raworc_mcp::policy::PolicyAction;
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{Policy, RaworcApi, RaworcError, RaworcMcpServer};
+    use serde_json::json;
+
+    let policy = Policy::from_toml(
+        r#"
+[[rules]]
+tool = "delete_*"
+when = { space = "prod-*" }
+action = "deny"
+reason = "Nothing is deleted in production spaces"
+
+[[rules]]
+tool = "send_message"
+when = { content = { longer_than = 20 } }
+action = "require_confirmation"
+reason = "Long message"
+
+[[rules]]
+tool = "close_session"
+when = { space = "prod-*" }
+action = "deny"
+reason = "Production sessions stay open"
+
+[[rules]]
+tool = "remix_session"
+when = { space = "prod-*" }
+action = "deny"
+reason = "Production sessions are not copied"
+"#,
+    )
+    .unwrap();
+
+    let decision = policy.evaluate("delete_secret", &json!({"space": "prod-eu", "key": "K"}));
+    assert_eq!(decision.action, PolicyAction::Deny);
+    assert_eq!(decision.rule, Some(1));
+    assert_eq!(policy.evaluate("delete_secret", &json!({"space": "dev"})).action, PolicyAction::Allow);
+    assert_eq!(policy.evaluate("send_message", &json!({"content": "hi"})).rule, None);
+    assert!(Policy::from_toml("[[rules]]\ntool = \"x\"\naction = \"maybe\"").is_err());
+
+    let backend = InMemoryRaworc::new();
+    backend.create_space(&space_request("prod-eu")).await.unwrap();
+    backend.set_secret("prod-eu", "K", "v").await.unwrap();
+    let session = backend.create_session(None, None).await.unwrap();
+    let prod_session = backend.create_session(Some("prod-eu"), None).await.unwrap();
+    let dev_session = backend.create_session(None, None).await.unwrap();
+    let mut server = RaworcMcpServer::with_client(backend, Config::new("memory://".to_string()).with_policy(policy));
+
+    let denied = server.handle_tool_call("delete_secret", &json!({"space": "prod-eu", "key": "K"})).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("production")));
+    // delete_space names its space in `name`, close_session only through the session
+    let denied = server.handle_tool_call("delete_space", &json!({"name": "prod-eu"})).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("production")));
+    let close = json!({"session_id": prod_session.id});
+    let denied = server.handle_tool_call("close_session", &close).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("stay open")));
+    assert!(server.handle_tool_call("close_session", &json!({"session_id": dev_session.id})).await.is_ok());
+    // A `space` argument neither hides the source session's space nor escapes the rules as a target
+    let remix = json!({"session_id": prod_session.id, "space": "default"});
+    let denied = server.handle_tool_call("remix_session", &remix).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("not copied")));
+    let remix = json!({"session_id": dev_session.id, "space": "prod-eu"});
+    let denied = server.handle_tool_call("remix_session", &remix).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("not copied")));
+    assert!(server.handle_tool_call("remix_session", &json!({"session_id": dev_session.id})).await.is_ok());
+
+    // Without elicitation support the confirmation cannot be given
+    let long = json!({"session_id": session.id, "content": "a message longer than twenty characters"});
+    let unconfirmed = server.handle_tool_call("send_message", &long).await;
+    assert!(matches!(unconfirmed, Err(RaworcError::PolicyError(msg)) if msg.contains("confirmation is unavailable")));
+    let short = json!({"session_id": session.id, "content": "short"});
+    assert!(server.handle_tool_call("send_message", &short).await.is_ok());
+
+    // A host that can ask but is told no reports a decline instead
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    server.set_progress_sender(tx);
+    server.set_elicitation_supported(true);
+    let elicitor = server.elicitor();
+    let host = tokio::spawn(async move {
+        let request = rx.recv().await.unwrap();
+        elicitor.resolve(&json!({"jsonrpc": "2.0", "id": request["id"], "result": {"action": "decline"}}))
+    });
+    let declined = server.handle_tool_call("send_message", &long).await;
+    assert!(matches!(declined, Err(RaworcError::PolicyError(msg)) if msg.contains("user declined")));
+    assert!(host.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_admin_tools_obey_read_only_allowed_spaces_and_policy() {
+    use raworc_mcp::mcp::SUPPORTED_TOOLS;
+    use raworc_mcp::policy::{is_mutating, space_target, SpaceTarget};
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{Policy, RaworcApi, RaworcError, RaworcMcpServer};
+    use serde_json::json;
+
+    let reads = [
+        "list_service_accounts", "get_service_account", "list_roles", "get_role", "list_role_bindings",
+        "get_role_binding", "get_space", "get_message_count", "get_agent", "list_running_agents",
+        "get_latest_build", "get_build",
+    ];
+    let writes = [
+        "create_service_account", "update_service_account", "delete_service_account",
+        "update_service_account_password", "create_role", "delete_role", "create_role_binding",
+        "delete_role_binding", "create_space", "update_space", "delete_space", "update_session",
+        "update_session_state", "close_session", "restore_session", "remix_session", "clear_messages",
+        "create_agent", "update_agent", "delete_agent", "update_agent_status", "deploy_agent", "stop_agent",
+        "create_secret", "update_secret", "create_build",
+    ];
+    for tool in reads.iter().chain(&writes) {
+        assert!(SUPPORTED_TOOLS.contains(tool), "{tool} is dispatched");
+    }
+    for tool in reads {
+        assert!(!is_mutating(tool), "{tool} only reads");
+    }
+    for tool in writes {
+        assert!(is_mutating(tool), "{tool} changes state");
+    }
+
+    // Accounts, roles and bindings are global; spaces are named by `name`;
+    // close/restore/remix follow the session; the rest take `space`
+    let spaces = ["create_space", "get_space", "update_space", "delete_space"];
+    let by_session = ["close_session", "restore_session", "remix_session"];
+    let (admin, scoped): (Vec<&str>, Vec<&str>) = reads
+        .iter()
+        .chain(&writes)
+        .filter(|t| !spaces.contains(t) && !by_session.contains(t))
+        .partition(|t| t.contains("service_account") || t.contains("role"));
+    for tool in &admin {
+        assert_eq!(space_target(tool, &json!({})), SpaceTarget::None, "{tool}");
+    }
+    for tool in spaces {
+        assert_eq!(space_target(tool, &json!({"name": "prod"})), SpaceTarget::Named("prod"), "{tool}");
+    }
+    for tool in by_session {
+        let target = SpaceTarget::Session { id: "s1", space: None };
+        assert_eq!(space_target(tool, &json!({"session_id": "s1"})), target, "{tool}");
+    }
+    for tool in &scoped {
+        assert_eq!(space_target(tool, &json!({"space": "prod"})), SpaceTarget::Named("prod"), "{tool}");
+        assert_eq!(space_target(tool, &json!({})), SpaceTarget::Default, "{tool}");
+    }
+
+    // Read-only refuses every write before it reaches the backend
+    let config = Config::new("memory://".to_string()).with_read_only();
+    let mut server = RaworcMcpServer::with_client(InMemoryRaworc::new(), config);
+    for tool in writes {
+        let refused = server.handle_tool_call(tool, &json!({})).await;
+        assert!(matches!(refused, Err(RaworcError::PolicyError(msg)) if msg.contains("read-only")), "{tool}");
+    }
+    for tool in reads {
+        let result = server.handle_tool_call(tool, &json!({})).await;
+        assert!(!matches!(result, Err(RaworcError::PolicyError(_))), "{tool}");
+    }
+
+    // allowed_spaces covers the space-scoped tools, not the global admin ones
+    let backend = InMemoryRaworc::new();
+    backend.create_space(&space_request("prod")).await.unwrap();
+    let prod_session = backend.create_session(Some("prod"), None).await.unwrap();
+    let config = Config::new("memory://".to_string()).with_allowed_spaces(vec!["default".to_string(), "team-*".to_string()]);
+    let mut server = RaworcMcpServer::with_client(backend, config);
+    let outside = |msg: &str| msg.contains("'prod' is not in allowed_spaces");
+    for tool in &scoped {
+        let denied = server.handle_tool_call(tool, &json!({"space": "prod"})).await;
+        assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if outside(&msg)), "{tool}");
+    }
+    for tool in spaces {
+        let denied = server.handle_tool_call(tool, &json!({"name": "prod"})).await;
+        assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if outside(&msg)), "{tool}");
+    }
+    for tool in by_session {
+        let denied = server.handle_tool_call(tool, &json!({"session_id": prod_session.id})).await;
+        assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if outside(&msg)), "{tool}");
+    }
+    for tool in &admin {
+        let result = server.handle_tool_call(tool, &json!({})).await;
+        assert!(!matches!(result, Err(RaworcError::PolicyError(_))), "{tool}");
+    }
+
+    // Policy rules match the new tools like any other
+    let policy = Policy::from_toml(
+        r#"
+[[rules]]
+tool = "delete_space"
+when = { name = "prod*" }
+action = "deny"
+reason = "Production spaces stay"
+
+[[rules]]
+tool = "update_service_account_password"
+action = "require_confirmation"
+"#,
+    )
+    .unwrap();
+    let mut server = RaworcMcpServer::with_client(InMemoryRaworc::new(), Config::new("memory://".to_string()).with_policy(policy));
+    let denied = server.handle_tool_call("delete_space", &json!({"name": "prod-eu"})).await;
+    assert!(matches!(denied, Err(RaworcError::PolicyError(msg)) if msg.contains("Production spaces stay")));
+    let unconfirmed = server
+        .handle_tool_call("update_service_account_password", &json!({"id": "ci", "current_password": "a", "new_password": "b"}))
+        .await;
+    assert!(matches!(unconfirmed, Err(RaworcError::PolicyError(msg)) if msg.contains("not confirmed")));
+    assert!(server.handle_tool_call("list_roles", &json!({})).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_hooks_veto_and_observe_tool_calls() {
+    use raworc_mcp::hooks::{FailurePolicy, Hook, HookEvent};
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{RaworcApi, RaworcError, RaworcMcpServer};
+    use serde_json::json;
+
+    let log = std::env::temp_dir().join(format!("raworc-hook-{}.json", std::process::id()));
+    let got = std::env::temp_dir().join(format!("raworc-hook-get-{}.json", std::process::id()));
+    let sh = |event, tool: &str, script: String, on_failure| Hook {
+        tool: tool.to_string(),
+        event,
+        command: vec!["sh".to_string(), "-c".to_string(), script],
+        timeout_seconds: 5,
+        on_failure,
+    };
+    let config = Config::new("memory://".to_string())
+        .with_revealed_secrets("default".to_string())
+        .with_hook(sh(HookEvent::Pre, "delete_*", "echo frozen >&2; exit 2".to_string(), FailurePolicy::Open))
+        .with_hook(sh(HookEvent::Post, "set_secret", format!("cat > {}", log.display()), FailurePolicy::Closed))
+        .with_hook(sh(HookEvent::Pre, "list_*", "exit 1".to_string(), FailurePolicy::Open))
+        .with_hook(sh(HookEvent::Pre, "get_version", "exit 1".to_string(), FailurePolicy::Closed))
+        .with_hook(sh(HookEvent::Post, "get_secret", format!("cat > {}", got.display()), FailurePolicy::Closed))
+        // Floods stdout before reading a large stdin; both pipes must be serviced at once
+        .with_hook(sh(
+            HookEvent::Pre,
+            "list_spaces",
+            "head -c 300000 /dev/zero; cat > /dev/null".to_string(),
+            FailurePolicy::Closed,
+        ));
+
+    let backend = InMemoryRaworc::new();
+    backend.set_secret("default", "K", "v").await.unwrap();
+    let mut server = RaworcMcpServer::with_client(backend, config);
+
+    let vetoed = server.handle_tool_call("delete_secret", &json!({"space": "default", "key": "K"})).await;
+    assert!(matches!(vetoed, Err(RaworcError::PolicyError(msg)) if msg.contains("vetoed by pre hook: frozen")));
+
+    server
+        .handle_tool_call("set_secret", &json!({"space": "default", "key": "API", "value": "sk-live-123456789"}))
+        .await
+        .unwrap();
+    let input: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&log).unwrap()).unwrap();
+    std::fs::remove_file(&log).ok();
+    assert_eq!(input["event"], "post");
+    assert_eq!(input["tool"], "set_secret");
+    assert_eq!(input["arguments"]["value"], "[REDACTED]");
+    assert_eq!(input["result"]["ok"], true);
+    assert!(input.get("identity").is_some());
+
+    // Fail-open hooks only log; fail-closed ones refuse the call
+    assert!(server.handle_tool_call("list_secrets", &json!({"space": "default"})).await.is_ok());
+    let closed = server.handle_tool_call("get_version", &json!({})).await;
+    assert!(matches!(closed, Err(RaworcError::PolicyError(msg)) if msg.contains("pre hook failed")));
+
+    // Post hooks see results with secret values redacted
+    let secret = server.handle_tool_call("get_secret", &json!({"space": "default", "key": "API"})).await.unwrap();
+    assert!(secret.content[0].text.as_deref().unwrap().contains("sk-live-123456789"));
+    let input = std::fs::read_to_string(&got).unwrap();
+    std::fs::remove_file(&got).ok();
+    assert!(!input.contains("sk-live-123456789"), "{}", input);
+    assert!(input.contains("[REDACTED]"));
+
+    let padding = "x".repeat(300_000);
+    assert!(server.handle_tool_call("list_spaces", &json!({"padding": padding})).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_audit_log_records_mutating_calls() {
+    use raworc_mcp::audit::{AuditLog, AuditQuery};
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{AuditConfig, Policy, RaworcApi, RaworcMcpServer};
+    use serde_json::json;
+
+    let dir = std::env::temp_dir().join(format!("raworc-audit-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let path = dir.join("audit.jsonl");
+    let mut audit = AuditConfig::new(&path).with_hash_chain();
+    audit.max_bytes = 1500;
+    let policy = Policy::from_toml("[[rules]]\ntool = \"delete_*\"\naction = \"deny\"").unwrap();
+    let config = Config::new("memory://".to_string()).with_audit_log(audit.clone()).with_policy(policy);
+    let mut server = RaworcMcpServer::with_client(InMemoryRaworc::new(), config);
+    server.set_client_name("test-client");
+
+    for i in 0..4 {
+        let args = json!({"space": "default", "key": format!("KEY_{i}"), "value": "sk-live-123456789"});
+        server.handle_tool_call("set_secret", &args).await.unwrap();
+    }
+    assert!(server.handle_tool_call("delete_secret", &json!({"space": "default", "key": "KEY_0"})).await.is_err());
+    server.handle_tool_call("list_secrets", &json!({"space": "default"})).await.unwrap();
+    assert!(dir.join("audit.jsonl.1").is_file(), "log should have rotated");
+
+    let log = AuditLog::new(audit);
+    let records = log.query(&AuditQuery::default()).unwrap();
+    assert_eq!(records.len(), 5, "reads are not audited");
+    assert_eq!(records[0].tool, "delete_secret");
+    assert_eq!(records[0].outcome, "denied");
+    assert_eq!(records[1].outcome, "ok");
+    assert_eq!(records[1].client.as_deref(), Some("test-client"));
+    assert_eq!(records[1].space.as_deref(), Some("default"));
+    assert_eq!(records[1].resource.as_deref(), Some("KEY_3"));
+    assert_eq!(records[1].arguments["value"], "[REDACTED]");
+    assert_eq!(log.verify().unwrap(), 5);
+
+    let listed = server
+        .handle_tool_call("audit_log", &json!({"tool": "set_*", "limit": 2, "format": "json"}))
+        .await
+        .unwrap();
+    let listed: serde_json::Value = serde_json::from_str(listed.content[0].text.as_deref().unwrap()).unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+    assert_eq!(listed[0]["resource"], "KEY_3");
+
+    // Editing a record breaks the chain
+    let text = std::fs::read_to_string(&path).unwrap().replace("KEY_3", "KEY_9");
+    std::fs::write(&path, text).unwrap();
+    assert!(log.verify().unwrap_err().to_string().contains("modified"));
+
+    // Session tools are recorded under the session's space, resolved through the API
+    let backend = InMemoryRaworc::new();
+    backend.create_space(&space_request("team-a")).await.unwrap();
+    let session = backend.create_session(Some("team-a"), None).await.unwrap();
+    let sessions = AuditConfig::new(dir.join("sessions.jsonl"));
+    let config = Config::new("memory://".to_string()).with_audit_log(sessions.clone());
+    let mut server = RaworcMcpServer::with_client(backend, config);
+    server.handle_tool_call("close_session", &json!({"session_id": session.id})).await.unwrap();
+    let records = AuditLog::new(sessions.clone()).query(&AuditQuery::default()).unwrap();
+    assert_eq!(records[0].space.as_deref(), Some("team-a"));
+
+    // Rotating with no rotated files to keep would drop the live log
+    let no_rotation = AuditConfig { max_files: 0, ..sessions };
+    let config = Config::new("http://localhost/api/v0".to_string()).with_audit_log(no_rotation);
+    assert!(config.validate().unwrap_err().to_string().contains("max_files"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_audit_chain_survives_torn_lines_and_failed_writes() {
+    use raworc_mcp::audit::{AuditLog, AuditRecord};
+    use raworc_mcp::AuditConfig;
+    use serde_json::json;
+
+    let record = |tool: &str| AuditRecord {
+        timestamp: chrono::Utc::now(),
+        identity: json!(null),
+        client: None,
+        tool: tool.to_string(),
+        arguments: json!({}),
+        space: None,
+        resource: None,
+        outcome: "ok".to_string(),
+        error: None,
+        request_id: None,
+        prev_hash: None,
+        hash: None,
+    };
+    let dir = std::env::temp_dir().join(format!("raworc-audit-torn-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // A crash left half a record at the end; the next process chains onto the last whole one
+    let path = dir.join("audit.jsonl");
+    AuditLog::new(AuditConfig::new(&path).with_hash_chain()).append(record("first")).unwrap();
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    std::io::Write::write_all(&mut file, br#"{"timestamp":"2026-"#).unwrap();
+    AuditLog::new(AuditConfig::new(&path).with_hash_chain()).append(record("second")).unwrap();
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["prev_hash"], lines[0]["hash"]);
+
+    // A record that never reached the file is not chained onto
+    let blocked = dir.join("blocked");
+    std::fs::write(&blocked, "").unwrap();
+    let log = AuditLog::new(AuditConfig::new(blocked.join("audit.jsonl")).with_hash_chain());
+    assert!(log.append(record("lost")).is_err());
+    std::fs::remove_file(&blocked).unwrap();
+    log.append(record("kept")).unwrap();
+    assert_eq!(log.verify().unwrap(), 1);
+    let kept: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(blocked.join("audit.jsonl")).unwrap()).unwrap();
+    assert!(kept.get("prev_hash").is_none());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_dry_run_plans_writes_without_sending() {
+    use raworc_mcp::testing::InMemoryRaworc;
+    use raworc_mcp::{Policy, RaworcMcpServer};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/staging/secrets/API_KEY"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "key_name": "API_KEY",
+            "value": "sk-live-0123456789abcd",
+            "description": null,
+            "space": "staging",
+            "created_at": "2025-01-01T00:00:00Z",
+            "updated_at": "2025-01-01T00:00:00Z",
+            "created_by": "tester"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/staging/sessions/s-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(session_json("s-1", "IDLE")))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let config = Config::new(format!("{}/api/v0", server.uri()))
+        .with_auth_token("t".to_string())
+        .with_default_space("staging".to_string());
+    let mut mcp = RaworcMcpServer::new(config.clone()).unwrap();
+    let args = json!({"space": "staging", "key": "API_KEY", "value": "sk-new-secret-value", "dry_run": true});
+    let response = mcp.handle_tool_call("set_secret", &args).await.unwrap();
+    let plan: serde_json::Value = serde_json::from_str(response.content[0].text.as_deref().unwrap()).unwrap();
+    assert_eq!(plan["request"]["method"], "POST");
+    assert_eq!(plan["request"]["path"], "/api/v0/spaces/staging/secrets/API_KEY");
+    assert_eq!(plan["request"]["body"]["value"], "[REDACTED]");
+    assert_eq!(plan["current"]["value"], "sk-…abcd");
+    assert!(plan["policy"].is_null());
+
+    // Confirmation rules don't prompt for a plan, and the plan shows the decision; deny still refuses
+    let policy = Policy::from_toml(
+        "[[rules]]\ntool = \"delete_*\"\naction = \"deny\"\n\n\
+         [[rules]]\ntool = \"set_*\"\naction = \"require_confirmation\"\nreason = \"second look\"",
+    )
+    .unwrap();
+    let mut guarded = RaworcMcpServer::new(config.clone().with_policy(policy)).unwrap();
+    let response = guarded.handle_tool_call("set_secret", &args).await.unwrap();
+    let plan: serde_json::Value = serde_json::from_str(response.content[0].text.as_deref().unwrap()).unwrap();
+    assert_eq!(plan["policy"], json!({"action": "require_confirmation", "rule": 2, "reason": "second look"}));
+    let denied = guarded
+        .handle_tool_call("delete_secret", &json!({"space": "staging", "key": "API_KEY", "dry_run": true}))
+        .await;
+    assert!(matches!(denied, Err(raworc_mcp::RaworcError::PolicyError(_))));
+    let unconfirmed = guarded
+        .handle_tool_call("set_secret", &json!({"space": "staging", "key": "API_KEY", "value": "v"}))
+        .await;
+    assert!(unconfirmed.unwrap_err().to_string().contains("not confirmed"));
+
+    // Globally, every mutating tool is planned; the default space is resolved
+    let mut mcp = RaworcMcpServer::new(config.with_dry_run()).unwrap();
+    let response = mcp.handle_tool_call("pause_session", &json!({"session_id": "s-1"})).await.unwrap();
+    let plan: serde_json::Value = serde_json::from_str(response.content[0].text.as_deref().unwrap()).unwrap();
+    assert_eq!(plan["request"]["path"], "/api/v0/spaces/staging/sessions/s-1/pause");
+    assert_eq!(plan["current"]["state"], "IDLE");
+    assert!(mcp.handle_tool_call("get_secret", &json!({"space": "staging", "key": "API_KEY"})).await.is_ok());
+
+    // Backends that cannot plan refuse rather than write
+    let mut memory = RaworcMcpServer::with_client(InMemoryRaworc::new(), Config::new("memory://".to_string()));
+    let refused = memory.handle_tool_call("create_session", &json!({"dry_run": true})).await;
+    assert!(refused.unwrap_err().to_string().contains("dry_run is not supported"));
+    let listed = memory.handle_tool_call("list_sessions", &json!({"format": "json"})).await.unwrap();
+    assert_eq!(listed.content[0].text.as_deref().map(str::trim), Some("[]"));
+}
+
+#[tokio::test]
+async fn test_api_errors_carry_request_id() {
+    use raworc_mcp::client::USER_AGENT;
+    use raworc_mcp::{RaworcClient, RaworcMcpServer};
+    use serde_json::json;
+    use wiremock::matchers::{header, header_exists, method, path_regex};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path_regex("^/api/v0/spaces/gone"))
+        .respond_with(ResponseTemplate::new(404).insert_header("X-Request-Id", "srv-404").set_body_string("no space gone"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex("^/api/v0/spaces/locked"))
+        .respond_with(ResponseTemplate::new(401).insert_header("X-Request-Id", "srv-401").set_body_string("token expired"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex("^/api/v0/spaces"))
+        .and(header_exists("x-request-id"))
+        .and(header("user-agent", USER_AGENT))
+        .respond_with(
+            ResponseTemplate::new(500)
+                .insert_header("X-Request-Id", "srv-42")
+                .set_body_json(json!({ "error": { "message": "database unavailable" } })),
+        )
+        .mount(&server)
+        .await;
+
+    let config = Config::new(format!("{}/api/v0", server.uri())).with_auth_token("t".to_string());
+    let client = RaworcClient::new(&config).unwrap();
+    let err = client.get_space("default").await.unwrap_err();
+    assert_eq!(err.request_id(), Some("srv-42"));
+    assert_eq!(err.to_string(), "API error: database unavailable (request id: srv-42)");
+
+    let mut mcp = RaworcMcpServer::new(config).unwrap();
+    let err = mcp
+        .handle_tool_call("list_spaces", &json!({}))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("srv-42"));
+
+    // Not-found and auth failures carry the id too
+    let err = mcp.handle_tool_call("get_space", &json!({ "name": "gone" })).await.unwrap_err();
+    assert!(matches!(err, raworc_mcp::RaworcError::NotFound { .. }));
+    assert_eq!(err.to_string(), "Resource not found: no space gone (request id: srv-404)");
+    let err = mcp.handle_tool_call("get_space", &json!({ "name": "locked" })).await.unwrap_err();
+    assert_eq!(err.request_id(), Some("srv-401"));
+    assert!(err.to_string().contains("srv-401"));
+}
+
+#[test]
+fn test_route_template_hides_ids() {
+    use raworc_mcp::client::route_template;
+
+    assert_eq!(
+        route_template("spaces/dev/sessions/42/messages?limit=5"),
+        "spaces/{space}/sessions/{session}/messages"
+    );
+    assert_eq!(route_template("spaces/dev/build/latest"), "spaces/{space}/build/latest");
+    assert_eq!(route_template("spaces/dev/build/b1"), "spaces/{space}/build/{build}");
+    assert_eq!(route_template("auth/login"), "auth/login");
+    assert_eq!(route_template("service-accounts/sa-1/password"), "service-accounts/{service_account}/password");
+    assert_eq!(route_template("roles/admin"), "roles/{role}");
+    assert_eq!(route_template("spaces/dev/role-bindings/rb-7"), "spaces/{space}/role-bindings/{role_binding}");
+}
+
+#[cfg(feature = "otel")]
+#[tokio::test]
+async fn test_tool_call_spans_are_exported_over_otlp() {
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::json;
+    use tracing_subscriber::layer::SubscriberExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/otel/sessions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/traces"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    // The exporter uses a blocking HTTP client, so build and flush it off the runtime
+    let endpoint = format!("{}/v1/traces", server.uri());
+    let (layer, guard) = tokio::task::spawn_blocking(move || raworc_mcp::telemetry::layer(&endpoint))
+        .await
+        .unwrap()
+        .unwrap();
+    let dispatch = tracing::Dispatch::new(tracing_subscriber::registry().with(layer));
+    {
+        let _default = tracing::dispatcher::set_default(&dispatch);
+        let config = Config::new(format!("{}/api/v0", server.uri())).with_auth_token("t".to_string());
+        let mut mcp = RaworcMcpServer::new(config).unwrap();
+        mcp.handle_tool_call("list_sessions", &json!({"space": "otel", "password": "hunter2"})).await.unwrap();
+    }
+    drop(dispatch);
+    tokio::task::spawn_blocking(move || drop(guard)).await.unwrap();
+
+    let exports: Vec<_> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|r| r.url.path() == "/v1/traces")
+        .collect();
+    assert!(!exports.is_empty(), "no spans exported");
+    let body: Vec<u8> = exports.iter().flat_map(|r| r.body.clone()).collect();
+    let contains = |needle: &str| body.windows(needle.len()).any(|w| w == needle.as_bytes());
+    assert!(contains("mcp.tool_call"));
+    assert!(contains("raworc.http"));
+    assert!(contains("spaces/{space}/sessions"));
+    assert!(!contains("hunter2"));
+}
+
+#[tokio::test]
+async fn test_tool_calls_and_api_responses_are_counted() {
+    use raworc_mcp::metrics::metrics;
+    use raworc_mcp::RaworcMcpServer;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/spaces/metrics-space/sessions"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let config = Config::new(format!("{}/api/v0", server.uri())).with_auth_token("t".to_string());
+    let mut mcp = RaworcMcpServer::new(config).unwrap();
+    let before = metrics().tool_calls.with_label_values(&["list_sessions", "error"]).get();
+    let _ = mcp.handle_tool_call("list_sessions", &json!({ "space": "metrics-space" })).await;
+    assert_eq!(metrics().tool_calls.with_label_values(&["list_sessions", "error"]).get(), before + 1);
+
+    let unknown = metrics().tool_calls.with_label_values(&["unknown", "error"]).get();
+    let _ = mcp.handle_tool_call("made_up_tool_a1b2", &json!({})).await;
+    assert_eq!(metrics().tool_calls.with_label_values(&["unknown", "error"]).get(), unknown + 1);
+
+    let text = metrics().render();
+    assert!(!text.contains("made_up_tool_a1b2"));
+    assert!(text.contains("raworc_api_retries_total"));
+    assert!(text.contains("raworc_mcp_tool_call_duration_seconds_bucket"));
+    assert!(text.contains(r#"raworc_api_responses_total{method="GET",route="spaces/{space}/sessions",status="503"}"#));
+}
+
+#[tokio::test]
+async fn test_consecutive_failures_gauge_counts_until_an_answer() {
+    use raworc_mcp::metrics::metrics;
+    use raworc_mcp::RaworcClient;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/auth/me"))
+        .respond_with(ResponseTemplate::new(502))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v0/auth/me"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&server)
+        .await;
+
+    let config = Config::new(format!("{}/api/v0", server.uri())).with_auth_token("t".to_string());
+    let client = RaworcClient::new(&config).unwrap();
+    let gauge = metrics().api_consecutive_failures.with_label_values(&["auth/me"]);
+    assert!(client.get_user_info().await.is_err());
+    assert!(client.get_user_info().await.is_err());
+    assert_eq!(gauge.get(), 2);
+    // A 4xx is an answer: the upstream is reachable, so the run ends
+    assert!(client.get_user_info().await.is_err());
+    assert_eq!(gauge.get(), 0);
+    assert!(metrics().render().contains(r#"raworc_api_consecutive_failures{route="auth/me"} 0"#));
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_serves_text_format() {
+    let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    tokio::spawn(raworc_mcp::metrics::serve(addr));
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let response = reqwest::get(format!("http://{}/metrics", addr)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    assert!(response.text().await.unwrap().contains("raworc_api_reauth_total"));
+
+    // One keep-alive client for every request below
+    let client = reqwest::Client::new();
+    let scraped = client.get(format!("http://{}/metrics?name[]=up", addr)).send().await.unwrap();
+    assert_eq!(scraped.status(), 200);
+    assert!(scraped.text().await.unwrap().contains("raworc_api_reauth_total"));
+    let head = client.head(format!("http://{}/metrics", addr)).send().await.unwrap();
+    assert_eq!(head.status(), 200);
+    assert!(head.text().await.unwrap().is_empty());
+
+    let missing = client.get(format!("http://{}/other", addr)).send().await.unwrap();
+    assert_eq!(missing.status(), 404);
+}